@@ -9,11 +9,20 @@ impl Args {
             rpc_url: Network::default().url(),
             block_number: 0,
             watch_tokens: Default::default(),
+            token_modes: Default::default(),
+            watch_contracts: Default::default(),
+            abi_dir: Default::default(),
+            auto_discover_tokens: false,
             retention_duration: 0,
             cleanup_interval: 0,
             address_filter: Default::default(),
             lazy: false,
             threads: 3,
+            confirmation_depth: 5,
+            finalized_only: false,
+            bulk_load: false,
+            block_reward: None,
+            modules: self.modules.clone(),
         };
 
         if self.rpc_url.is_some() {
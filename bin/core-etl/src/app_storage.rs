@@ -1,6 +1,10 @@
-use crate::Args;
+use crate::{Args, Commands};
+use clickhouse_storage::ClickhouseStorage;
+use file_storage::{Compression, FileStorage, Format};
 use mock_storage::MockStorage;
+use parquet_storage::{ParquetStorage, Partition};
 use postgres_storage::PostgresStorage;
+use publisher_storage::{Encoding, KafkaPublisher, PublishingStorage};
 use serde::Serialize;
 use sqlite3_storage::Sqlite3Storage;
 use std::sync::Arc;
@@ -13,13 +17,46 @@ pub enum StorageType {
     #[default]
     Sqlite3,
     Postgres,
+    Clickhouse,
+    Parquet,
+    Csv,
+    Jsonl,
     Mock,
 }
 
+/// How the parquet files are partitioned
+#[derive(clap::ValueEnum, Clone, Default, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParquetPartition {
+    #[default]
+    BlockRange,
+    Date,
+}
+
+/// Compression of the csv and jsonl files
+#[derive(clap::ValueEnum, Clone, Default, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileCompression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// Encoding of the messages published to Kafka
+#[derive(clap::ValueEnum, Clone, Default, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KafkaEncoding {
+    #[default]
+    Json,
+    Avro,
+}
+
 impl Args {
-    pub async fn choose_storage(&self) -> Arc<dyn Storage + Send + Sync> {
+    /// Connects to the storage. If `prepare` is set, pending migrations are applied and indexes are created
+    pub async fn choose_storage(&self, prepare: bool) -> Arc<dyn Storage + Send + Sync> {
         info!("Storing data about: {:?}", self.modules.clone());
-        match self.storage {
+        let storage: Arc<dyn Storage + Send + Sync> = match self.storage {
             StorageType::Mock => Arc::new(MockStorage::new()),
             StorageType::Sqlite3 => {
                 if self.sqlite3_path.is_none() {
@@ -36,7 +73,9 @@ impl Args {
                 )
                 .await
                 .unwrap();
-                db.prepare_db().await.unwrap();
+                if prepare {
+                    db.prepare_db().await.unwrap();
+                }
                 Arc::new(db)
             }
             StorageType::Postgres => {
@@ -60,9 +99,129 @@ impl Args {
                     );
                 }
                 let postgres = postgres.unwrap();
-                postgres.prepare_db().await.unwrap();
+                if prepare {
+                    postgres.prepare_db().await.unwrap();
+                }
                 Arc::new(postgres)
             }
+            StorageType::Clickhouse => {
+                if self.clickhouse_db_dsn.is_none() {
+                    panic!("clickhouse_db_dsn is required for ClickHouse Storage");
+                }
+                info!(
+                    "Connecting to ClickHouse Storage database: {:?}",
+                    self.clickhouse_db_dsn
+                );
+                let clickhouse = ClickhouseStorage::new(
+                    self.clickhouse_db_dsn.as_ref().unwrap().to_string(),
+                    self.tables_prefix.clone(),
+                    self.modules.clone(),
+                )
+                .await;
+                if clickhouse.is_err() {
+                    panic!(
+                        "Failed to connect to ClickHouse Storage database: {:?}",
+                        clickhouse.err()
+                    );
+                }
+                let clickhouse = clickhouse.unwrap();
+                if prepare {
+                    clickhouse.prepare_db().await.unwrap();
+                }
+                Arc::new(clickhouse)
+            }
+            StorageType::Parquet => {
+                if self.parquet_dir.is_none() {
+                    panic!("parquet_dir is required for Parquet Storage");
+                }
+                info!("Writing Parquet Storage files to: {:?}", self.parquet_dir);
+                let partition = match self.parquet_partition {
+                    ParquetPartition::BlockRange => {
+                        Partition::BlockRange(self.parquet_partition_blocks)
+                    }
+                    ParquetPartition::Date => Partition::Day,
+                };
+                let parquet = ParquetStorage::new(
+                    self.parquet_dir.as_ref().unwrap().to_string(),
+                    partition,
+                    self.modules.clone(),
+                );
+                if parquet.is_err() {
+                    panic!(
+                        "Failed to open Parquet Storage directory: {:?}",
+                        parquet.err()
+                    );
+                }
+                let parquet = parquet.unwrap();
+                if prepare {
+                    parquet.prepare_db().await.unwrap();
+                }
+                Arc::new(parquet)
+            }
+            StorageType::Csv | StorageType::Jsonl => {
+                if self.file_dir.is_none() {
+                    panic!("file_dir is required for CSV and JSON lines Storage");
+                }
+                info!(
+                    "Writing {:?} Storage files to: {:?}",
+                    self.storage, self.file_dir
+                );
+                let format = match self.storage {
+                    StorageType::Csv => Format::Csv,
+                    _ => Format::Jsonl,
+                };
+                let compression = match self.file_compression {
+                    FileCompression::None => Compression::None,
+                    FileCompression::Gzip => Compression::Gzip,
+                    FileCompression::Zstd => Compression::Zstd,
+                };
+                let files = FileStorage::new(
+                    self.file_dir.as_ref().unwrap().to_string(),
+                    format,
+                    compression,
+                    self.file_rotate_size_mb * 1024 * 1024,
+                    self.modules.clone(),
+                );
+                if files.is_err() {
+                    panic!(
+                        "Failed to open {:?} Storage directory: {:?}",
+                        self.storage,
+                        files.err()
+                    );
+                }
+                let files = files.unwrap();
+                if prepare {
+                    files.prepare_db().await.unwrap();
+                }
+                Arc::new(files)
+            }
+        };
+
+        // only exported blocks are published, other commands use the storage as it is
+        match (&self.kafka_brokers, &self.command) {
+            (Some(brokers), Commands::Export(_)) => {
+                info!("Publishing exported blocks to Kafka: {:?}", brokers);
+                let publisher = KafkaPublisher::new(brokers);
+                if publisher.is_err() {
+                    panic!("Failed to create Kafka producer: {:?}", publisher.err());
+                }
+                let encoding = match self.kafka_encoding {
+                    KafkaEncoding::Json => Encoding::Json,
+                    KafkaEncoding::Avro => Encoding::Avro,
+                };
+                let publishing = PublishingStorage::new(
+                    storage,
+                    Arc::new(publisher.unwrap()),
+                    encoding,
+                    self.kafka_topic_prefix.clone(),
+                    self.modules.clone(),
+                );
+                if publishing.is_err() {
+                    panic!("Failed to prepare Kafka messages: {:?}", publishing.err());
+                }
+                Arc::new(publishing.unwrap())
+            }
+            _ => storage,
         }
     }
 }
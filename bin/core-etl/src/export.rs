@@ -6,9 +6,11 @@ use std::{
 
 use clap::Parser;
 use config::Config;
+use contracts::ExtractionMode;
 use provider::Provider;
 use storage::Storage;
 use tracing::{error, info};
+use types::IcanAddress;
 
 #[derive(Parser, Debug)]
 pub struct ExportArgs {
@@ -16,16 +18,37 @@ pub struct ExportArgs {
     /// Block to start syncing from
     pub block: Option<i64>,
 
-    #[clap(short, long, env, value_parser, num_args = 1.., value_delimiter = ',')]
+    #[clap(short, long, env, value_parser = parse_watch_token, num_args = 1.., value_delimiter = ',')]
     /// Watch token transfers. Provide a token type and address to watch
-    /// in the format: "token_type:token_address,token_type:token_address"
+    /// in the format: "token_type:token_address[:mode],token_type:token_address[:mode]"
+    /// Mode is one of calldata (default), event or both and defines where transfers are taken from
     /// Example: "cbc20:cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c" - to watch Core Token transfers
+    /// Example: "cbc20:cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c:event" - to watch Core Token Transfer events
+    /// Example: "cbc721:<address>" - to watch NFT transfers, token IDs are stored in the token_id column
+    /// Example: "cbc1155:<address>" - to watch multi-token transfers, every transferred (id, amount) pair is stored separately with its batch_index
     pub watch_tokens: Option<Vec<String>>,
 
+    #[clap(long, env, value_parser, num_args = 1.., value_delimiter = ',')]
+    /// Decode calls and events of the contracts with their JSON ABI. Provide a contract name and address to watch
+    /// in the format: "contract_name:contract_address,contract_name:contract_address"
+    /// ABI of the contract is loaded from the "<contract_name>.json" file in the ABI directory
+    /// Every function and event is stored in its own "<contract_name>_<method>_calls" or "<contract_name>_<event>_events" table
+    /// Example: "pool:cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c"
+    pub watch_contracts: Option<Vec<String>>,
+
+    #[clap(long, env, default_value = "abi")]
+    /// Directory with the JSON ABI files of the watched contracts
+    pub abi_dir: String,
+
+    #[clap(long, env)]
+    /// Discover CBC20 tokens while syncing and watch their transfers
+    /// Deployed contracts, addresses called with CBC20 transfer functions and emitting Transfer events are checked with CBC20 view calls
+    pub auto_discover_tokens: bool,
+
     #[clap(short, long, env, value_parser, num_args = 1.., value_delimiter = ',')]
-    /// Filter transactions by address. Provide a list of addresses to filter
-    /// Example: "0x123,0x456,0x789"
-    pub address_filter: Option<Vec<String>>,
+    /// Filter transactions by address. Provide a list of addresses to filter, checksums are validated
+    /// Example: "cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c,ab7935cdef94ac9e6bcbcf779277aad7025993bc1964"
+    pub address_filter: Option<Vec<IcanAddress>>,
 
     #[clap(short, long, env, default_value = "0")]
     /// How long to retain data in the database
@@ -40,6 +63,25 @@ pub struct ExportArgs {
     /// Lazy mode. Do not sync while node is syncing
     /// This is useful for nodes that take a long time to sync
     pub lazy: bool,
+
+    #[clap(long, env, default_value = "5", value_parser = clap::value_parser!(i64).range(0..))]
+    /// Number of blocks on top of a block after which it is considered matured
+    pub confirmation_depth: i64,
+
+    #[clap(long, env)]
+    /// Finalized-only mode. Insert blocks only after they reach the confirmation depth
+    /// This is useful for consumers that cannot handle reorgs
+    pub finalized_only: bool,
+
+    #[clap(long, env)]
+    /// Bulk load mode. Historical blocks are loaded with Postgres COPY while indexes are dropped
+    /// Indexes are built again once the sync reaches the chain head, where batches are inserted as usual
+    pub bulk_load: bool,
+
+    #[clap(long, env)]
+    /// Reward of the block miner in ore. Required by the balance_changes module
+    /// Uncle rewards are not included in the balance changes
+    pub block_reward: Option<u128>,
 }
 
 impl ExportArgs {
@@ -74,17 +116,51 @@ impl ExportArgs {
         config.block_number = self.block.unwrap_or_default();
         config.retention_duration = self.retention_duration;
         config.cleanup_interval = self.cleanup_interval;
-        config.address_filter = self.address_filter.clone().unwrap_or_default();
+        config.address_filter = self
+            .address_filter
+            .iter()
+            .flatten()
+            .map(|address| address.to_string())
+            .collect();
         config.lazy = self.lazy;
+        config.confirmation_depth = self.confirmation_depth;
+        config.finalized_only = self.finalized_only;
+        config.bulk_load = self.bulk_load;
+        config.block_reward = self.block_reward;
+        config.auto_discover_tokens = self.auto_discover_tokens;
+        config.abi_dir = self.abi_dir.clone();
 
         if let Some(watch_tokens) = &self.watch_tokens {
             config.watch_tokens = self.parse_watch_tokens(network_id, watch_tokens);
+            config.token_modes = self.parse_token_modes(watch_tokens);
             info!("Monitoring token transfers: {:?}", config.watch_tokens);
         }
 
+        if let Some(watch_contracts) = &self.watch_contracts {
+            config.watch_contracts = self.parse_watch_contracts(watch_contracts);
+            info!("Decoding contracts: {:?}", config.watch_contracts);
+        }
+
         config
     }
 
+    /// Returns addresses of the watched contracts grouped by the contract name
+    pub fn parse_watch_contracts(
+        &self,
+        watch_contracts: &Vec<String>,
+    ) -> HashMap<String, HashSet<String>> {
+        let mut map: HashMap<String, HashSet<String>> = HashMap::new();
+        for contract in watch_contracts {
+            let (contract_name, contract_address) = contract
+                .split_once(':')
+                .unwrap_or_else(|| panic!("Invalid contract to watch: {}", contract));
+            map.entry(contract_name.to_string())
+                .or_default()
+                .insert(parse_address(contract_address));
+        }
+        map
+    }
+
     pub fn parse_watch_tokens(
         &self,
         network_id: u64,
@@ -107,9 +183,42 @@ impl ExportArgs {
             }
             let mut split = token.split(':');
             let token_type = split.next().unwrap().to_string();
-            let token_address = split.next().unwrap().to_string();
+            let token_address = parse_address(split.next().unwrap());
             map.entry(token_type).or_default().insert(token_address);
         }
         map
     }
+
+    /// Returns the extraction mode for every watched token which has it set.
+    /// Modes are validated by `parse_watch_token` when the flags are parsed
+    pub fn parse_token_modes(&self, watch_tokens: &Vec<String>) -> HashMap<String, ExtractionMode> {
+        let mut map: HashMap<String, ExtractionMode> = HashMap::new();
+        for token in watch_tokens {
+            let split: Vec<&str> = token.split(':').collect();
+            if let [_, token_address, mode] = split[..] {
+                let mode = mode
+                    .parse()
+                    .unwrap_or_else(|e| panic!("Invalid token to watch {}: {}", token, e));
+                map.insert(parse_address(token_address), mode);
+            }
+        }
+        map
+    }
+}
+
+/// Validates the extraction mode of the watched token, so an unknown mode is reported as a flag error
+fn parse_watch_token(token: &str) -> Result<String, String> {
+    match token.split(':').collect::<Vec<_>>()[..] {
+        [_] | [_, _] => Ok(token.to_string()),
+        [_, _, mode] => mode.parse::<ExtractionMode>().map(|_| token.to_string()),
+        _ => Err(format!("Invalid token to watch: {}", token)),
+    }
+}
+
+/// Validates the address given in the flags and returns it in the form it is stored in
+fn parse_address(address: &str) -> String {
+    address
+        .parse::<IcanAddress>()
+        .unwrap_or_else(|e| panic!("Invalid address {}: {}", address, e))
+        .to_string()
 }
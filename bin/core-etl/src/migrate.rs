@@ -0,0 +1,36 @@
+use clap::Parser;
+use std::{pin::Pin, sync::Arc};
+use storage::Storage;
+use tracing::info;
+
+#[derive(Parser, Debug)]
+pub struct MigrateArgs {
+    #[clap(long)]
+    /// Only list pending migrations without applying them
+    dry_run: bool,
+}
+
+impl MigrateArgs {
+    pub async fn exec(
+        &self,
+        storage: Arc<dyn Storage>,
+    ) -> Result<(), Pin<Box<dyn std::error::Error + Send + Sync>>> {
+        info!("Schema version: {}", storage.schema_version().await?);
+        let migrations = storage.run_migrations(self.dry_run).await?;
+        if migrations.is_empty() {
+            info!("Database schema is up to date");
+            return Ok(());
+        }
+        for migration in &migrations {
+            if self.dry_run {
+                info!("Pending migration {}", migration);
+            } else {
+                info!("Applied migration {}", migration);
+            }
+        }
+        if !self.dry_run {
+            storage.create_indexes().await?;
+        }
+        Ok(())
+    }
+}
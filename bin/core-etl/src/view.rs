@@ -3,6 +3,7 @@ use config::Config;
 use std::{pin::Pin, sync::Arc};
 use storage::Storage;
 use tracing::info;
+use types::IcanAddress;
 #[derive(Parser, Debug)]
 pub struct ViewArgs {
     #[command(subcommand)]
@@ -23,6 +24,42 @@ pub enum ViewSubcommands {
         #[clap(flatten)]
         group: TokenTransferGroup,
     },
+    Receipt {
+        #[clap(short = 'n', long, env)]
+        hash: String,
+    },
+    Log {
+        #[clap(flatten)]
+        group: LogGroup,
+    },
+    Trace {
+        #[clap(short = 'n', long, env)]
+        tx_hash: String,
+    },
+    TokenBalance {
+        #[clap(short = 'a', long, env)]
+        token_address: IcanAddress,
+        #[clap(flatten)]
+        group: TokenBalanceGroup,
+    },
+    Balance {
+        #[clap(short = 'a', long, env)]
+        address: IcanAddress,
+        /// Block height of the balance. Latest stored balance if not set
+        #[clap(short = 'b', long, env)]
+        block_number: Option<i64>,
+        /// Print balance changes instead of the balance
+        #[clap(long, env)]
+        changes: bool,
+    },
+    Contract {
+        /// Address of the contract. All deployed contracts are printed if not set
+        #[clap(short = 'a', long, env, conflicts_with = "cbc20")]
+        address: Option<IcanAddress>,
+        /// Print only contracts which look like CBC20 tokens
+        #[clap(long, env)]
+        cbc20: bool,
+    },
 }
 
 #[derive(Debug, clap::Args)]
@@ -75,28 +112,97 @@ impl ViewArgs {
                     },
             } => {
                 if let Some(address) = token_address {
-                    let transfers = storage
-                        .get_token_transfers(address.clone(), from.clone(), to.clone())
-                        .await?;
+                    let transfers = storage.get_token_transfers(*address, *from, *to).await?;
                     info!("Requested token transfers: {:#?}", transfers);
                     return Ok(());
                 }
                 if let Some(from) = from {
                     let transfers = storage
-                        .get_address_token_transfers(from.to_string(), types::TransferType::From)
+                        .get_address_token_transfers(*from, types::TransferType::From)
                         .await?;
                     info!("Requested token transfers: {:#?}", transfers);
                     return Ok(());
                 }
                 if let Some(to) = to {
                     let transfers = storage
-                        .get_address_token_transfers(to.to_string(), types::TransferType::To)
+                        .get_address_token_transfers(*to, types::TransferType::To)
                         .await?;
                     info!("Requested token transfers: {:#?}", transfers);
                     return Ok(());
                 }
                 panic!("Invalid token transfer query");
             }
+            ViewSubcommands::Receipt { hash } => {
+                let receipt = storage.get_transaction_receipt(hash.clone()).await?;
+                info!("Requested receipt:\n {:#?}", receipt);
+                Ok(())
+            }
+            ViewSubcommands::Log {
+                group:
+                    LogGroup {
+                        tx_hash,
+                        address,
+                        topic0,
+                        from_block,
+                        to_block,
+                    },
+            } => {
+                let logs = if let Some(tx_hash) = tx_hash {
+                    storage.get_transaction_logs(tx_hash.clone()).await?
+                } else {
+                    storage
+                        .get_logs(*address, topic0.clone(), *from_block, *to_block)
+                        .await?
+                };
+                info!("Requested logs: {:#?}", logs);
+                Ok(())
+            }
+            ViewSubcommands::Trace { tx_hash } => {
+                let traces = storage.get_transaction_traces(tx_hash.clone()).await?;
+                info!("Requested traces: {:#?}", traces);
+                Ok(())
+            }
+            ViewSubcommands::TokenBalance {
+                token_address,
+                group: TokenBalanceGroup { holder, top },
+            } => {
+                if let Some(holder) = holder {
+                    let balance = storage.get_token_balance(*token_address, *holder).await?;
+                    info!("Requested token balance: {:#?}", balance);
+                } else {
+                    let holders = storage
+                        .get_top_holders(*token_address, top.unwrap())
+                        .await?;
+                    info!("Requested top holders: {:#?}", holders);
+                }
+                Ok(())
+            }
+            ViewSubcommands::Balance {
+                address,
+                block_number,
+                changes,
+            } => {
+                if *changes {
+                    let balance_changes = storage.get_balance_changes(*address).await?;
+                    info!("Requested balance changes: {:#?}", balance_changes);
+                    return Ok(());
+                }
+                let balance = storage
+                    .get_balance_at(*address, block_number.unwrap_or(i64::MAX))
+                    .await?;
+                info!("Requested balance: {}", balance);
+                Ok(())
+            }
+            ViewSubcommands::Contract { address, cbc20 } => {
+                if let Some(address) = address {
+                    let contract = storage.get_contract(*address).await?;
+                    info!("Requested contract:\n {:#?}", contract);
+                    return Ok(());
+                }
+                let contracts = storage.get_contracts(*cbc20).await?;
+                info!("Requested contracts: {:#?}", contracts);
+                Ok(())
+            }
         }
     }
 }
@@ -114,9 +220,33 @@ pub struct TransactionGroup {
 #[group(required = true, multiple = false)]
 pub struct TokenTransferGroup {
     #[clap(short = 'a', long, env)]
-    token_address: Option<String>,
+    token_address: Option<IcanAddress>,
     #[clap(short = 'f', long, env)]
-    from: Option<String>,
+    from: Option<IcanAddress>,
+    #[clap(short = 't', long, env)]
+    to: Option<IcanAddress>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct LogGroup {
+    #[clap(short = 'n', long, env, conflicts_with_all = ["address", "topic0", "from_block", "to_block"])]
+    tx_hash: Option<String>,
+    #[clap(short = 'a', long, env)]
+    address: Option<IcanAddress>,
+    #[clap(short = 't', long, env)]
+    topic0: Option<String>,
+    #[clap(short = 'f', long, env)]
+    from_block: Option<i64>,
+    #[clap(short = 'e', long, env)]
+    to_block: Option<i64>,
+}
+
+#[derive(Debug, clap::Args)]
+#[group(required = true, multiple = false)]
+pub struct TokenBalanceGroup {
+    #[clap(short = 'o', long, env)]
+    holder: Option<IcanAddress>,
+    /// Number of the holders with the biggest balances
     #[clap(short = 't', long, env)]
-    to: Option<String>,
+    top: Option<i64>,
 }
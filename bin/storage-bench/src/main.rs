@@ -0,0 +1,310 @@
+use chrono::{TimeZone, Utc};
+use clap::{Parser, ValueEnum};
+use postgres_storage::PostgresStorage;
+use sqlite3_storage::Sqlite3Storage;
+use sqlx::{PgPool, SqlitePool};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use storage::Storage;
+use types::{Batch, Block, Log, Receipt, Transaction};
+
+const MODULES: [&str; 4] = ["blocks", "transactions", "receipts", "logs"];
+
+#[derive(ValueEnum, Clone, Debug)]
+enum StorageType {
+    Sqlite3,
+    Postgres,
+}
+
+/// Compares inserting blocks, transactions, receipts and logs with SQL formatted VALUES
+/// against the storage inserts with bound parameters or the bulk load of the storage
+#[derive(Parser, Debug)]
+#[clap(name = "storage-bench", about)]
+struct Args {
+    /// Storage type which is benchmarked
+    #[clap(long, default_value = "sqlite3", value_enum)]
+    storage: StorageType,
+
+    /// Path to SQlite3 file used by the benchmark
+    #[clap(long, default_value = "/tmp/core-etl-bench.db")]
+    sqlite3_path: String,
+
+    /// Postgres database DSN used by the benchmark
+    #[clap(long)]
+    postgres_db_dsn: Option<String>,
+
+    /// Number of generated blocks
+    #[clap(long, default_value = "5000")]
+    blocks: i64,
+
+    /// Number of transactions (and receipts) in each block
+    #[clap(long, default_value = "10")]
+    transactions_per_block: i64,
+
+    /// Number of logs emitted by each transaction
+    #[clap(long, default_value = "2")]
+    logs_per_transaction: i64,
+
+    /// Number of blocks inserted in a single database transaction
+    #[clap(long, default_value = "500")]
+    batch_size: i64,
+
+    /// Load batches of the storage in the bulk mode, e.g. with COPY in Postgres
+    #[clap(long)]
+    bulk_mode: bool,
+}
+
+/// Pool used to run the formatted statements
+enum Pool {
+    Sqlite3(SqlitePool),
+    Postgres(PgPool),
+}
+
+impl Pool {
+    async fn execute_in_transaction(&self, queries: &[String]) -> Result<(), sqlx::Error> {
+        match self {
+            Pool::Sqlite3(pool) => {
+                let mut tx = pool.begin().await?;
+                for query in queries {
+                    sqlx::query(query).execute(&mut tx).await?;
+                }
+                tx.commit().await
+            }
+            Pool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                for query in queries {
+                    sqlx::query(query).execute(&mut tx).await?;
+                }
+                tx.commit().await
+            }
+        }
+    }
+}
+
+fn hash(kind: u8, n: i64) -> String {
+    format!("{:02x}{:062x}", kind, n)
+}
+
+fn address(n: i64) -> String {
+    format!("cb{:042x}", n)
+}
+
+/// Generates batches of blocks with transactions, receipts and logs
+fn generate(args: &Args) -> Vec<Batch> {
+    let mut batches = vec![];
+    let mut batch = Batch::default();
+    for number in 1..=args.blocks {
+        let block_hash = hash(0, number);
+        let timestamp = 1_700_000_000 + number * 7;
+        batch.blocks.push(Block {
+            number,
+            hash: block_hash.clone(),
+            parent_hash: hash(0, number - 1),
+            nonce: format!("{:016x}", number),
+            sha3_uncles: hash(1, number),
+            logs_bloom: "0".repeat(512),
+            transactions_root: hash(2, number),
+            state_root: hash(3, number),
+            receipts_root: hash(4, number),
+            miner: address(number % 100),
+            difficulty: "1000000".to_string(),
+            total_difficulty: (number * 1000000).to_string(),
+            extra_data: "d883010000846763746888676f312e32302e31856c696e7578".to_string(),
+            energy_limit: 30_000_000,
+            energy_used: args.transactions_per_block * 50_000,
+            timestamp,
+            transaction_count: args.transactions_per_block,
+            matured: 1,
+        });
+        for index in 0..args.transactions_per_block {
+            let n = number * args.transactions_per_block + index;
+            let tx_hash = hash(5, n);
+            batch.transactions.push(Transaction {
+                hash: tx_hash.clone(),
+                nonce: index.to_string(),
+                block_hash: block_hash.clone(),
+                block_number: number,
+                transaction_index: index,
+                from: address(n),
+                to: address(n + 1),
+                value: format!("{}000000000000000000", n),
+                energy: "50000".to_string(),
+                energy_price: "1000000000".to_string(),
+                input: format!("a9059cbb{:064x}{:064x}", n, n * 10),
+            });
+            batch.receipts.push(Receipt {
+                transaction_hash: tx_hash.clone(),
+                block_hash: block_hash.clone(),
+                block_number: number,
+                transaction_index: index,
+                status: 1,
+                energy_used: 50_000,
+                cumulative_energy_used: (index + 1) * 50_000,
+                effective_energy_price: "1000000000".to_string(),
+                contract_address: "".to_string(),
+                logs_bloom: "0".repeat(512),
+            });
+            for log_index in 0..args.logs_per_transaction {
+                batch.logs.push(Log {
+                    block_number: number,
+                    block_hash: block_hash.clone(),
+                    tx_hash: tx_hash.clone(),
+                    log_index: index * args.logs_per_transaction + log_index,
+                    address: address(n % 50),
+                    topic0: Some(hash(6, log_index)),
+                    topic1: Some(format!("{:0>64}", address(n))),
+                    topic2: Some(format!("{:0>64}", address(n + 1))),
+                    topic3: None,
+                    data: format!("{:064x}", n * 10),
+                });
+            }
+        }
+        if number % args.batch_size == 0 || number == args.blocks {
+            batches.push(std::mem::take(&mut batch));
+        }
+    }
+    batches
+}
+
+/// Builds the INSERT statements with values formatted into the SQL, the way batches were stored before
+fn formatted_queries(prefix: &str, batch: &Batch, padded: bool) -> Vec<String> {
+    let numeric = |value: &str| {
+        if padded {
+            format!("{:0>78}", value)
+        } else {
+            value.to_string()
+        }
+    };
+    let topic = |t: &Option<String>| {
+        t.as_ref()
+            .map(|t| format!("'{}'", t))
+            .unwrap_or("NULL".to_string())
+    };
+    let timestamps: HashMap<&String, i64> = batch
+        .blocks
+        .iter()
+        .map(|block| (&block.hash, block.timestamp))
+        .collect();
+    let created_at = |block_hash: &String| {
+        Utc.timestamp_opt(timestamps[block_hash], 0)
+            .unwrap()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    };
+    vec![
+        format!(
+            "INSERT INTO {}_blocks (number, hash, parent_hash, nonce, sha3_uncles, logs_bloom, transactions_root, state_root, receipts_root, miner, difficulty, total_difficulty, extra_data, energy_limit, energy_used, timestamp, transaction_count, matured, created_at) VALUES {}",
+            prefix,
+            batch.blocks.iter().map(|block| format!(
+                "({}, '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', {}, {}, {}, {}, {}, '{}')",
+                block.number, block.hash, block.parent_hash, block.nonce, block.sha3_uncles, block.logs_bloom, block.transactions_root, block.state_root, block.receipts_root, block.miner, numeric(&block.difficulty), numeric(&block.total_difficulty), block.extra_data, block.energy_limit, block.energy_used, block.timestamp, block.transaction_count, block.matured, created_at(&block.hash)
+            )).collect::<Vec<_>>().join(", ")
+        ),
+        format!(
+            "INSERT INTO {}_transactions (hash, nonce, block_hash, block_number, transaction_index, from_addr, to_addr, value, energy, energy_price, input, created_at) VALUES {}",
+            prefix,
+            batch.transactions.iter().map(|tx| format!(
+                "('{}', '{}', '{}', {}, {}, '{}', '{}', '{}', '{}', '{}', '{}', '{}')",
+                tx.hash, tx.nonce, tx.block_hash, tx.block_number, tx.transaction_index, tx.from, tx.to, numeric(&tx.value), tx.energy, numeric(&tx.energy_price), tx.input, created_at(&tx.block_hash)
+            )).collect::<Vec<_>>().join(", ")
+        ),
+        format!(
+            "INSERT INTO {}_receipts (transaction_hash, block_hash, block_number, transaction_index, status, energy_used, cumulative_energy_used, effective_energy_price, contract_address, logs_bloom, created_at) VALUES {}",
+            prefix,
+            batch.receipts.iter().map(|r| format!(
+                "('{}', '{}', {}, {}, {}, {}, {}, '{}', '{}', '{}', '{}')",
+                r.transaction_hash, r.block_hash, r.block_number, r.transaction_index, r.status, r.energy_used, r.cumulative_energy_used, r.effective_energy_price, r.contract_address, r.logs_bloom, created_at(&r.block_hash)
+            )).collect::<Vec<_>>().join(", ")
+        ),
+        format!(
+            "INSERT INTO {}_logs (block_number, block_hash, tx_hash, log_index, address, topic0, topic1, topic2, topic3, data, created_at) VALUES {}",
+            prefix,
+            batch.logs.iter().map(|log| format!(
+                "({}, '{}', '{}', {}, '{}', {}, {}, {}, {}, '{}', '{}')",
+                log.block_number, log.block_hash, log.tx_hash, log.log_index, log.address, topic(&log.topic0), topic(&log.topic1), topic(&log.topic2), topic(&log.topic3), log.data, created_at(&log.block_hash)
+            )).collect::<Vec<_>>().join(", ")
+        ),
+    ]
+}
+
+async fn connect(args: &Args, prefix: &str) -> (Box<dyn Storage>, Pool) {
+    let modules: Vec<String> = MODULES.iter().map(|m| m.to_string()).collect();
+    match args.storage {
+        StorageType::Sqlite3 => {
+            let db = Sqlite3Storage::new(args.sqlite3_path.clone(), prefix.to_string(), modules)
+                .await
+                .expect("failed to open the sqlite3 database");
+            let pool = Pool::Sqlite3(db.get_db().clone());
+            (Box::new(db), pool)
+        }
+        StorageType::Postgres => {
+            let dsn = args
+                .postgres_db_dsn
+                .clone()
+                .expect("postgres_db_dsn is required for Postgres Storage");
+            let db = PostgresStorage::new(dsn, prefix.to_string(), modules)
+                .await
+                .expect("failed to connect to the postgres database");
+            let pool = Pool::Postgres(db.pool.clone());
+            (Box::new(db), pool)
+        }
+    }
+}
+
+fn report(name: &str, rows: usize, elapsed: Duration) {
+    println!(
+        "{:<10} {:>10} rows in {:>8.3}s, {:>10.0} rows/s",
+        name,
+        rows,
+        elapsed.as_secs_f64(),
+        rows as f64 / elapsed.as_secs_f64()
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let batches = generate(&args);
+    let rows: usize = batches
+        .iter()
+        .map(|b| b.blocks.len() + b.transactions.len() + b.receipts.len() + b.logs.len())
+        .sum();
+
+    let (formatted, pool) = connect(&args, "bench_formatted").await;
+    formatted.prepare_db().await.unwrap();
+    formatted.rollback_blocks(0).await.unwrap();
+    let padded = matches!(args.storage, StorageType::Sqlite3);
+    let start = Instant::now();
+    for batch in &batches {
+        pool.execute_in_transaction(&formatted_queries("bench_formatted", batch, padded))
+            .await
+            .unwrap();
+    }
+    report("formatted", rows, start.elapsed());
+
+    let (bound, _) = connect(&args, "bench_bound").await;
+    bound.prepare_db().await.unwrap();
+    bound.rollback_blocks(0).await.unwrap();
+    let mut batches = batches;
+    let start = Instant::now();
+    if args.bulk_mode {
+        bound.set_bulk_mode(true).await.unwrap();
+    }
+    for batch in &mut batches {
+        bound
+            .insert_blocks_with_txs_and_token_transfers(true, batch)
+            .await
+            .unwrap();
+    }
+    if args.bulk_mode {
+        // indexes are built again when the bulk mode is switched off, which is a part of the load
+        bound.set_bulk_mode(false).await.unwrap();
+    }
+    report(
+        if args.bulk_mode { "bulk" } else { "bound" },
+        rows,
+        start.elapsed(),
+    );
+}
@@ -1,3 +1,4 @@
+use contracts::ExtractionMode;
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
@@ -11,6 +12,18 @@ pub struct Config {
     /// Watch token transfers. Provide a token type and address to watch
     pub watch_tokens: HashMap<String, HashSet<String>>,
 
+    /// Where to take transfers of the watched token from (calldata, event or both). Calldata is used if not set
+    pub token_modes: HashMap<String, ExtractionMode>,
+
+    /// Decode calls and events of the contracts with their ABI. Provide a contract name and address to watch
+    pub watch_contracts: HashMap<String, HashSet<String>>,
+
+    /// Directory with the `<contract name>.json` ABI files of the watched contracts
+    pub abi_dir: String,
+
+    /// Watch transfers of the CBC20 tokens found while syncing in addition to the watched tokens
+    pub auto_discover_tokens: bool,
+
     /// Filter transactions by address
     pub address_filter: Vec<String>,
 
@@ -25,4 +38,20 @@ pub struct Config {
 
     /// Number of threads to use for the sync
     pub threads: usize,
+
+    /// Number of blocks on top of a block after which it is considered matured
+    pub confirmation_depth: i64,
+
+    /// Finalized-only mode. Insert blocks only after they reach the confirmation depth
+    pub finalized_only: bool,
+
+    /// Bulk load mode. Load historical blocks with the fastest method of the storage and build indexes at the chain head
+    pub bulk_load: bool,
+
+    /// Reward of the block miner in ore. Used to calculate native coin balance changes,
+    /// required if the balance_changes module is enabled
+    pub block_reward: Option<u128>,
+
+    /// Which data to store (e.g. blocks, transactions, token_transfers, receipts)
+    pub modules: Vec<String>,
 }
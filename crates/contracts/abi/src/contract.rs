@@ -0,0 +1,370 @@
+use crate::{
+    decoder::{decode, decode_word, hex_to_bytes},
+    AbiError, AbiItem, ParamType,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use sha3::{Digest, Sha3_256};
+use std::path::Path;
+
+/// Columns which are stored for every decoded call and event
+const RESERVED_COLUMNS: [&str; 7] = [
+    "block_number",
+    "block_hash",
+    "tx_hash",
+    "address",
+    "log_index",
+    "status",
+    "created_at",
+];
+
+/// Longest table or column name generated from the ABI. Postgres truncates identifiers longer than 63 bytes,
+/// so the rest is left for the tables prefix
+pub const MAX_NAME_LENGTH: usize = 48;
+
+/// Function or event of the contract. Every method is decoded into its own table
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub name: String,
+    pub signature: String,
+    /// Selector of the function or topic0 of the event
+    pub id: String,
+    pub table_name: String,
+    /// Column name and type of every argument
+    pub columns: Vec<(String, String)>,
+    types: Vec<ParamType>,
+    /// Event arguments which are stored in the topics
+    indexed: Vec<bool>,
+}
+
+/// Contract which calls and events are decoded with its JSON ABI
+#[derive(Debug, Clone)]
+pub struct AbiContract {
+    pub name: String,
+    pub address: String,
+    pub functions: Vec<Method>,
+    pub events: Vec<Method>,
+}
+
+/// ABI file is either the plain ABI or a build artifact with the ABI in the `abi` field
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AbiFile {
+    Abi(Vec<AbiItem>),
+    Artifact { abi: Vec<AbiItem> },
+}
+
+impl AbiContract {
+    /// Loads the contract ABI from the JSON file
+    pub fn load(path: &Path, name: &str, address: &str) -> Result<Self, AbiError> {
+        let file = std::fs::read_to_string(path)?;
+        let items = match serde_json::from_str(&file)? {
+            AbiFile::Abi(items) => items,
+            AbiFile::Artifact { abi } => abi,
+        };
+        Self::new(name, address, items)
+    }
+
+    pub fn new(name: &str, address: &str, items: Vec<AbiItem>) -> Result<Self, AbiError> {
+        let name = name.to_lowercase();
+        let mut functions = vec![];
+        let mut events = vec![];
+        for item in &items {
+            match item.kind.as_str() {
+                "function" => functions.push(Method::new(&name, item, "calls")?),
+                // anonymous events have no topic0, so they can not be matched
+                "event" if !item.anonymous => events.push(Method::new(&name, item, "events")?),
+                _ => {}
+            }
+        }
+        // overloaded methods have the same name, so their tables are distinguished by the selector
+        for (methods, kind) in [(&mut functions, "calls"), (&mut events, "events")] {
+            let names: Vec<String> = methods.iter().map(|method| method.name.clone()).collect();
+            for method in methods.iter_mut() {
+                if names.iter().filter(|name| **name == method.name).count() > 1 {
+                    method.table_name =
+                        table_name(&name, &method.name, Some(&method.id[..8]), kind);
+                }
+            }
+        }
+        Ok(AbiContract {
+            name,
+            address: address.to_string(),
+            functions,
+            events,
+        })
+    }
+
+    /// Finds the function by the selector and decodes its arguments into column values.
+    /// Returns None if the input is not a call of the known function
+    pub fn decode_call(&self, input: &str) -> Option<(&Method, Result<Vec<String>, AbiError>)> {
+        let input = input.trim_start_matches("0x");
+        let method = self
+            .functions
+            .iter()
+            .find(|method| input.get(..8) == Some(method.id.as_str()))?;
+        let values = hex_to_bytes(&input[8..]).and_then(|data| decode(&method.types, &data));
+        Some((method, values.map(|values| column_values(&values))))
+    }
+
+    /// Finds the event by the topic0 and decodes its arguments from the topics and data into column values.
+    /// Returns None if the log is not the known event
+    pub fn decode_log(
+        &self,
+        topics: &[String],
+        data: &str,
+    ) -> Option<(&Method, Result<Vec<String>, AbiError>)> {
+        let method = self
+            .events
+            .iter()
+            .find(|method| topics.first() == Some(&method.id))?;
+        let values = method.decode_log(&topics[1..], data);
+        Some((method, values.map(|values| column_values(&values))))
+    }
+}
+
+impl Method {
+    fn new(contract_name: &str, item: &AbiItem, kind: &str) -> Result<Self, AbiError> {
+        let types = item
+            .inputs
+            .iter()
+            .map(|param| ParamType::parse(&param.kind, &param.components))
+            .collect::<Result<Vec<_>, _>>()?;
+        let signature = format!(
+            "{}({})",
+            item.name,
+            types
+                .iter()
+                .map(|ty| ty.canonical())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let hash = format!("{:x}", Sha3_256::digest(signature.as_bytes()));
+        let id = if kind == "calls" {
+            hash[..8].to_string()
+        } else {
+            hash
+        };
+        let columns = item
+            .inputs
+            .iter()
+            .zip(&types)
+            .enumerate()
+            .map(|(i, (param, ty))| {
+                let name = match param.name.as_str() {
+                    "" => format!("arg{}", i),
+                    name if RESERVED_COLUMNS.contains(&name) => format!("arg_{}", name),
+                    name => name.to_string(),
+                };
+                (shorten(&name, ""), ty.canonical())
+            })
+            .collect();
+        Ok(Method {
+            name: item.name.clone(),
+            signature,
+            id,
+            table_name: table_name(contract_name, &item.name, None, kind),
+            columns,
+            types,
+            indexed: item.inputs.iter().map(|param| param.indexed).collect(),
+        })
+    }
+
+    /// Indexed arguments are taken from the topics in order, the others are decoded from the data.
+    /// Indexed dynamic arguments are stored as their hash
+    fn decode_log(&self, topics: &[String], data: &str) -> Result<Vec<Value>, AbiError> {
+        let data_types: Vec<ParamType> = self
+            .types
+            .iter()
+            .zip(&self.indexed)
+            .filter(|(_, indexed)| !**indexed)
+            .map(|(ty, _)| ty.clone())
+            .collect();
+        let mut data_values = decode(&data_types, &hex_to_bytes(data)?)?.into_iter();
+        let mut topics = topics.iter();
+        self.types
+            .iter()
+            .zip(&self.indexed)
+            .map(|(ty, indexed)| {
+                if !indexed {
+                    return Ok(data_values.next().unwrap_or_default());
+                }
+                let topic = topics.next().ok_or(AbiError::MissingTopic)?;
+                if ty.is_dynamic() || matches!(ty, ParamType::FixedArray(..) | ParamType::Tuple(_))
+                {
+                    Ok(Value::String(topic.clone()))
+                } else {
+                    decode_word(ty, &hex_to_bytes(topic)?)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Name of the method table `{contract}_{method}_{kind}`, with the selector before the kind for overloaded methods.
+/// Characters which are not allowed in unquoted identifiers are replaced with underscores
+fn table_name(contract_name: &str, method: &str, selector: Option<&str>, kind: &str) -> String {
+    let name = match selector {
+        Some(selector) => format!("{}_{}_{}", contract_name, method, selector),
+        None => format!("{}_{}", contract_name, method),
+    };
+    let name: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    shorten(&name, &format!("_{}", kind))
+}
+
+/// Shortens the name with the suffix to `MAX_NAME_LENGTH` bytes. The end of a long name is replaced
+/// with the hash of the full name, so names which only differ in the end stay unique
+fn shorten(name: &str, suffix: &str) -> String {
+    if name.len() + suffix.len() <= MAX_NAME_LENGTH {
+        return format!("{}{}", name, suffix);
+    }
+    let hash = format!("{:x}", Sha3_256::digest(name.as_bytes()));
+    let mut end = MAX_NAME_LENGTH - suffix.len() - 9;
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}_{}{}", &name[..end], &hash[..8], suffix)
+}
+
+/// Converts the decoded values into the column values. Arrays and tuples are stored as JSON
+fn column_values(values: &[Value]) -> Vec<String> {
+    values
+        .iter()
+        .map(|value| match value {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contract(abi: &str) -> AbiContract {
+        AbiContract::new(
+            "Pool",
+            "cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c",
+            serde_json::from_str(abi).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn names_tables_by_contract_and_method() {
+        let contract = contract(
+            r#"[
+                {"type": "function", "name": "swap", "inputs": [{"name": "amountIn", "type": "uint256"}, {"name": "", "type": "address"}, {"name": "status", "type": "bool"}]},
+                {"type": "event", "name": "Swap", "inputs": [{"name": "amount", "type": "uint256"}]}
+            ]"#,
+        );
+        assert_eq!(contract.functions[0].table_name, "pool_swap_calls");
+        assert_eq!(contract.events[0].table_name, "pool_swap_events");
+        assert_eq!(
+            contract.functions[0].columns,
+            vec![
+                ("amountIn".to_string(), "uint256".to_string()),
+                ("arg1".to_string(), "address".to_string()),
+                ("arg_status".to_string(), "bool".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_tables_of_overloaded_methods_by_selector() {
+        let contract = contract(
+            r#"[
+                {"type": "function", "name": "swap", "inputs": [{"name": "amount", "type": "uint256"}]},
+                {"type": "function", "name": "swap", "inputs": [{"name": "amount", "type": "uint128"}]}
+            ]"#,
+        );
+        for method in &contract.functions {
+            assert_eq!(
+                method.table_name,
+                format!("pool_swap_{}_calls", &method.id[..8])
+            );
+        }
+    }
+
+    #[test]
+    fn replaces_unsafe_chars_of_table_names() {
+        assert_eq!(
+            table_name("my-pool", "swap\"; DROP", None, "calls"),
+            "my_pool_swap___drop_calls"
+        );
+    }
+
+    #[test]
+    fn shortens_long_names() {
+        let method = "a".repeat(60);
+        let long = table_name("pool", &method, None, "events");
+        assert_eq!(long.len(), MAX_NAME_LENGTH);
+        assert!(long.starts_with("pool_aaaa"));
+        assert!(long.ends_with("_events"));
+        // names which differ only in the cut off part stay unique
+        let other = table_name("pool", &format!("{}b", method), None, "events");
+        assert_eq!(other.len(), MAX_NAME_LENGTH);
+        assert_ne!(long, other);
+
+        assert_eq!(shorten("amount", ""), "amount");
+        assert_eq!(shorten(&"é".repeat(40), "").len(), MAX_NAME_LENGTH - 1);
+    }
+
+    #[test]
+    fn decodes_call_into_columns() {
+        let contract = contract(
+            r#"[{"type": "function", "name": "swap", "inputs": [{"name": "path", "type": "uint256[]"}, {"name": "to", "type": "address"}]}]"#,
+        );
+        let method = &contract.functions[0];
+        let input = format!(
+            "{}{:0>64}{:0>64}{:0>64}{:0>64}",
+            method.id, "40", "ab416902d2548d52352a05423d13266ee7aaf140a068", "1", "7"
+        );
+        let (decoded, values) = contract.decode_call(&input).unwrap();
+        assert_eq!(decoded.name, "swap");
+        assert_eq!(
+            values.unwrap(),
+            vec![
+                "[\"7\"]".to_string(),
+                "ab416902d2548d52352a05423d13266ee7aaf140a068".to_string()
+            ]
+        );
+        assert!(contract.decode_call("a9059cbb").is_none());
+        assert!(contract.decode_call(&method.id).unwrap().1.is_err());
+    }
+
+    #[test]
+    fn decodes_log_with_indexed_args() {
+        let contract = contract(
+            r#"[{"type": "event", "name": "Swap", "inputs": [{"name": "sender", "type": "address", "indexed": true}, {"name": "note", "type": "string", "indexed": true}, {"name": "amount", "type": "uint256"}]}]"#,
+        );
+        let event = &contract.events[0];
+        let topics = vec![
+            event.id.clone(),
+            format!("{:0>64}", "ab416902d2548d52352a05423d13266ee7aaf140a068"),
+            "ff".repeat(32),
+        ];
+        let (_, values) = contract
+            .decode_log(&topics, &format!("{:0>64}", "2a"))
+            .unwrap();
+        assert_eq!(
+            values.unwrap(),
+            vec![
+                "ab416902d2548d52352a05423d13266ee7aaf140a068".to_string(),
+                "ff".repeat(32),
+                "42".to_string()
+            ]
+        );
+        assert!(matches!(
+            contract
+                .decode_log(&topics[..2], &format!("{:0>64}", "2a"))
+                .unwrap()
+                .1,
+            Err(AbiError::MissingTopic)
+        ));
+    }
+}
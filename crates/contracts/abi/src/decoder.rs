@@ -0,0 +1,322 @@
+use crate::{AbiError, ParamType};
+use base_primitives::U256;
+use serde_json::Value;
+
+/// Decodes hex string into bytes
+pub fn hex_to_bytes(data: &str) -> Result<Vec<u8>, AbiError> {
+    let data = data.trim_start_matches("0x");
+    if !data.len().is_multiple_of(2) {
+        return Err(AbiError::InvalidHex);
+    }
+    data.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or(AbiError::InvalidHex)
+        })
+        .collect()
+}
+
+fn bytes_to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes ABI encoded values of the given types.
+/// Numbers are decoded into decimal strings, addresses and bytes into hex strings, arrays and tuples into arrays
+pub fn decode(types: &[ParamType], data: &[u8]) -> Result<Vec<Value>, AbiError> {
+    let mut budget = element_budget(data);
+    decode_params(types, data, 0, &mut budget)
+}
+
+/// Decodes the value of a single type which is encoded in the 32 bytes word, e.g. indexed event param
+pub fn decode_word(ty: &ParamType, word: &[u8]) -> Result<Value, AbiError> {
+    let mut budget = element_budget(word);
+    decode_value(ty, word, 0, &mut budget)
+}
+
+/// Returns how many values may be decoded from the data. Every value of well-formed data takes at least
+/// one word, which is shared with at most one array or tuple containing it. Nested arrays which reuse
+/// the same offsets would decode many more values from a short input, so they are rejected
+fn element_budget(data: &[u8]) -> usize {
+    (data.len() / 32 + 1) * 2 + MIN_ELEMENT_BUDGET
+}
+
+/// Number of values which may always be decoded, e.g. empty arrays and tuples nested in each other
+const MIN_ELEMENT_BUDGET: usize = 64;
+
+fn decode_params(
+    types: &[ParamType],
+    data: &[u8],
+    base: usize,
+    budget: &mut usize,
+) -> Result<Vec<Value>, AbiError> {
+    let mut head = base;
+    let mut values = Vec::with_capacity(types.len());
+    for ty in types {
+        if ty.is_dynamic() {
+            let offset = read_usize(data, head)?;
+            let start = base
+                .checked_add(offset)
+                .ok_or(AbiError::InvalidOffset(head))?;
+            values.push(decode_value(ty, data, start, budget)?);
+            head = head.checked_add(32).ok_or(AbiError::OutOfBounds(head))?;
+        } else {
+            values.push(decode_value(ty, data, head, budget)?);
+            head = head
+                .checked_add(ty.head_size())
+                .ok_or(AbiError::OutOfBounds(head))?;
+        }
+    }
+    Ok(values)
+}
+
+fn decode_value(
+    ty: &ParamType,
+    data: &[u8],
+    at: usize,
+    budget: &mut usize,
+) -> Result<Value, AbiError> {
+    *budget = budget.checked_sub(1).ok_or(AbiError::TooManyElements)?;
+    let value = match ty {
+        // Core addresses are 22 bytes long
+        ParamType::Address => Value::String(bytes_to_hex(&word(data, at)?[10..])),
+        ParamType::Uint(_) => Value::String(U256::from_be_slice(word(data, at)?).to_string()),
+        ParamType::Int(_) => {
+            let word = word(data, at)?;
+            let value = U256::from_be_slice(word);
+            // negative numbers are in two's complement
+            if word[0] & 0x80 != 0 {
+                Value::String(format!("-{}", (!value).wrapping_add(U256::from(1u64))))
+            } else {
+                Value::String(value.to_string())
+            }
+        }
+        ParamType::Bool => Value::Bool(word(data, at)?[31] != 0),
+        ParamType::FixedBytes(size) => Value::String(bytes_to_hex(&word(data, at)?[..*size])),
+        ParamType::Bytes => Value::String(bytes_to_hex(read_bytes(data, at)?)),
+        ParamType::String => {
+            Value::String(String::from_utf8_lossy(read_bytes(data, at)?).into_owned())
+        }
+        ParamType::Array(inner) => {
+            let length = read_usize(data, at)?;
+            // every element takes at least one word, so the length can not be bigger than the data
+            if length > data.len() / 32 {
+                return Err(AbiError::InvalidOffset(at));
+            }
+            if length > *budget {
+                return Err(AbiError::TooManyElements);
+            }
+            Value::Array(decode_params(
+                &vec![inner.as_ref().clone(); length],
+                data,
+                at + 32,
+                budget,
+            )?)
+        }
+        ParamType::FixedArray(inner, size) => {
+            if *size > *budget {
+                return Err(AbiError::TooManyElements);
+            }
+            Value::Array(decode_params(
+                &vec![inner.as_ref().clone(); *size],
+                data,
+                at,
+                budget,
+            )?)
+        }
+        ParamType::Tuple(types) => Value::Array(decode_params(types, data, at, budget)?),
+    };
+    Ok(value)
+}
+
+fn word(data: &[u8], at: usize) -> Result<&[u8], AbiError> {
+    at.checked_add(32)
+        .and_then(|end| data.get(at..end))
+        .ok_or(AbiError::OutOfBounds(at))
+}
+
+fn read_usize(data: &[u8], at: usize) -> Result<usize, AbiError> {
+    let word = word(data, at)?;
+    if word[..24].iter().any(|byte| *byte != 0) {
+        return Err(AbiError::InvalidOffset(at));
+    }
+    Ok(word[24..]
+        .iter()
+        .fold(0, |value, byte| (value << 8) | *byte as usize))
+}
+
+fn read_bytes(data: &[u8], at: usize) -> Result<&[u8], AbiError> {
+    let length = read_usize(data, at)?;
+    // the length word was read, so the start of the bytes does not overflow
+    let start = at + 32;
+    start
+        .checked_add(length)
+        .and_then(|end| data.get(start..end))
+        .ok_or(AbiError::OutOfBounds(start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ADDRESS: &str = "ab416902d2548d52352a05423d13266ee7aaf140a068";
+
+    /// Joins the hex values into 32-byte words padded with zeros and decodes them into bytes
+    fn words(values: &[&str]) -> Vec<u8> {
+        let hex: String = values
+            .iter()
+            .map(|value| format!("{:0>64}", value))
+            .collect();
+        hex_to_bytes(&hex).unwrap()
+    }
+
+    fn array(inner: ParamType) -> ParamType {
+        ParamType::Array(Box::new(inner))
+    }
+
+    #[test]
+    fn decodes_static_values() {
+        let types = [
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Int(256),
+            ParamType::Bool,
+            ParamType::FixedBytes(4),
+        ];
+        let data = words(&[
+            ADDRESS,
+            "2a",
+            &"f".repeat(64),
+            "1",
+            &format!("{:0<64}", "a9059cbb"),
+        ]);
+        assert_eq!(
+            decode(&types, &data).unwrap(),
+            vec![
+                json!(ADDRESS),
+                json!("42"),
+                json!("-1"),
+                json!(true),
+                json!("a9059cbb"),
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_dynamic_values() {
+        let types = [
+            ParamType::String,
+            array(ParamType::Uint(256)),
+            ParamType::Bytes,
+        ];
+        let data = words(&[
+            "60",
+            "a0",
+            "100",
+            "4",
+            &format!("{:0<64}", "436f7265"),
+            "2",
+            "7",
+            "8",
+            "2",
+            &format!("{:0<64}", "abcd"),
+        ]);
+        assert_eq!(
+            decode(&types, &data).unwrap(),
+            vec![json!("Core"), json!(["7", "8"]), json!("abcd")]
+        );
+    }
+
+    #[test]
+    fn decodes_tuple_with_dynamic_member() {
+        let types = [ParamType::Tuple(vec![
+            ParamType::Uint(8),
+            ParamType::String,
+        ])];
+        let data = words(&["20", "5", "40", "2", &format!("{:0<64}", "6869")]);
+        assert_eq!(decode(&types, &data).unwrap(), vec![json!(["5", "hi"])]);
+    }
+
+    #[test]
+    fn decodes_fixed_array() {
+        let types = [ParamType::FixedArray(Box::new(ParamType::Uint(256)), 2)];
+        assert_eq!(
+            decode(&types, &words(&["1", "2"])).unwrap(),
+            vec![json!(["1", "2"])]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        assert!(matches!(
+            decode(&[ParamType::Uint(256), ParamType::Bool], &words(&["1"])),
+            Err(AbiError::OutOfBounds(32))
+        ));
+        // string is longer than the data
+        assert!(matches!(
+            decode(&[ParamType::String], &words(&["20", "40", "1"])),
+            Err(AbiError::OutOfBounds(64))
+        ));
+    }
+
+    #[test]
+    fn rejects_huge_offsets_and_lengths() {
+        assert!(matches!(
+            decode(&[ParamType::String], &words(&[&"f".repeat(64)])),
+            Err(AbiError::InvalidOffset(0))
+        ));
+        assert!(matches!(
+            decode(&[ParamType::Bytes], &words(&["20", "ffffffffffffffff"])),
+            Err(AbiError::OutOfBounds(64))
+        ));
+        assert!(matches!(
+            decode(&[array(ParamType::Uint(256))], &words(&["20", "ffff", "1"])),
+            Err(AbiError::InvalidOffset(32))
+        ));
+    }
+
+    #[test]
+    fn rejects_reused_offsets() {
+        // every element of the outer array points to the same inner array, so the values are decoded 50 times
+        let inner_offset = format!("{:x}", 50 * 32);
+        let mut values = vec!["20", "32"];
+        values.extend(vec![inner_offset.as_str(); 50]);
+        values.push("32");
+        values.extend(vec!["7"; 50]);
+        let types = [array(array(ParamType::Uint(256)))];
+        assert!(matches!(
+            decode(&types, &words(&values)),
+            Err(AbiError::TooManyElements)
+        ));
+
+        // the same layout with a few elements is valid
+        let values = ["20", "2", "40", "40", "1", "7"];
+        assert_eq!(
+            decode(&types, &words(&values)).unwrap(),
+            vec![json!([["7"], ["7"]])]
+        );
+    }
+
+    #[test]
+    fn decodes_word() {
+        assert_eq!(
+            decode_word(&ParamType::Address, &words(&[ADDRESS])).unwrap(),
+            json!(ADDRESS)
+        );
+        assert!(matches!(
+            decode_word(&ParamType::Uint(256), &[0; 31]),
+            Err(AbiError::OutOfBounds(0))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_hex() {
+        assert_eq!(hex_to_bytes("0x0aff").unwrap(), vec![10, 255]);
+        assert!(matches!(hex_to_bytes("abc"), Err(AbiError::InvalidHex)));
+        assert!(matches!(hex_to_bytes("zz"), Err(AbiError::InvalidHex)));
+        assert!(matches!(hex_to_bytes("é"), Err(AbiError::InvalidHex)));
+    }
+}
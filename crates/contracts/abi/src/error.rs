@@ -0,0 +1,19 @@
+#[derive(Debug, thiserror::Error)]
+pub enum AbiError {
+    #[error("failed to read ABI file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse ABI: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unsupported ABI type {0}")]
+    InvalidType(String),
+    #[error("invalid hex data")]
+    InvalidHex,
+    #[error("data is too short to read a word at byte {0}")]
+    OutOfBounds(usize),
+    #[error("offset or length at byte {0} is too big")]
+    InvalidOffset(usize),
+    #[error("data has more values than it can encode, offsets are reused")]
+    TooManyElements,
+    #[error("log has less topics than indexed arguments")]
+    MissingTopic,
+}
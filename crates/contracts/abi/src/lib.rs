@@ -0,0 +1,7 @@
+pub mod contract;
+pub mod decoder;
+pub mod error;
+pub mod param;
+pub use contract::{AbiContract, Method};
+pub use error::AbiError;
+pub use param::{AbiItem, Param, ParamType};
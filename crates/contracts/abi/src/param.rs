@@ -0,0 +1,129 @@
+use crate::AbiError;
+use serde::Deserialize;
+
+/// Function, event or any other item of the JSON ABI
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbiItem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub inputs: Vec<Param>,
+    #[serde(default)]
+    pub anonymous: bool,
+}
+
+/// Input of a function or an event
+#[derive(Debug, Clone, Deserialize)]
+pub struct Param {
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub indexed: bool,
+    #[serde(default)]
+    pub components: Vec<Param>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamType {
+    Address,
+    Uint(usize),
+    Int(usize),
+    Bool,
+    FixedBytes(usize),
+    Bytes,
+    String,
+    Array(Box<ParamType>),
+    FixedArray(Box<ParamType>, usize),
+    Tuple(Vec<ParamType>),
+}
+
+impl ParamType {
+    /// Parses the type of the param, e.g. "uint256", "address[]" or "tuple" with its components
+    pub fn parse(kind: &str, components: &[Param]) -> Result<Self, AbiError> {
+        let invalid = || AbiError::InvalidType(kind.to_string());
+        if let Some(inner) = kind.strip_suffix("[]") {
+            return Ok(ParamType::Array(Box::new(Self::parse(inner, components)?)));
+        }
+        if let Some((inner, size)) = kind.strip_suffix(']').and_then(|k| k.rsplit_once('[')) {
+            let size = size.parse().map_err(|_| invalid())?;
+            return Ok(ParamType::FixedArray(
+                Box::new(Self::parse(inner, components)?),
+                size,
+            ));
+        }
+        let bits = |size: &str, max: usize| match size {
+            "" => Ok(max),
+            _ => size
+                .parse::<usize>()
+                .ok()
+                .filter(|size| *size > 0 && *size <= max)
+                .ok_or_else(invalid),
+        };
+        match kind {
+            "address" => Ok(ParamType::Address),
+            "bool" => Ok(ParamType::Bool),
+            "bytes" => Ok(ParamType::Bytes),
+            "string" => Ok(ParamType::String),
+            "tuple" => Ok(ParamType::Tuple(
+                components
+                    .iter()
+                    .map(|param| Self::parse(&param.kind, &param.components))
+                    .collect::<Result<_, _>>()?,
+            )),
+            _ if kind.starts_with("uint") => Ok(ParamType::Uint(bits(&kind[4..], 256)?)),
+            _ if kind.starts_with("int") => Ok(ParamType::Int(bits(&kind[3..], 256)?)),
+            _ if kind.starts_with("bytes") => Ok(ParamType::FixedBytes(bits(&kind[5..], 32)?)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Type as it is used in the function signature
+    pub fn canonical(&self) -> String {
+        match self {
+            ParamType::Address => "address".to_string(),
+            ParamType::Uint(bits) => format!("uint{}", bits),
+            ParamType::Int(bits) => format!("int{}", bits),
+            ParamType::Bool => "bool".to_string(),
+            ParamType::FixedBytes(size) => format!("bytes{}", size),
+            ParamType::Bytes => "bytes".to_string(),
+            ParamType::String => "string".to_string(),
+            ParamType::Array(inner) => format!("{}[]", inner.canonical()),
+            ParamType::FixedArray(inner, size) => format!("{}[{}]", inner.canonical(), size),
+            ParamType::Tuple(types) => format!(
+                "({})",
+                types
+                    .iter()
+                    .map(|ty| ty.canonical())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        }
+    }
+
+    /// Dynamic types are encoded after the head and only their offset is in the head
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Bytes | ParamType::String | ParamType::Array(_) => true,
+            ParamType::FixedArray(inner, _) => inner.is_dynamic(),
+            ParamType::Tuple(types) => types.iter().any(|ty| ty.is_dynamic()),
+            _ => false,
+        }
+    }
+
+    /// Size of the static type in bytes
+    pub fn head_size(&self) -> usize {
+        match self {
+            ParamType::FixedArray(inner, size) if !inner.is_dynamic() => {
+                inner.head_size().saturating_mul(*size)
+            }
+            ParamType::Tuple(types) if !self.is_dynamic() => {
+                types.iter().map(|ty| ty.head_size()).sum()
+            }
+            _ => 32,
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use crate::constants::NAME;
+use contracts::{
+    read_array, topic, word, CallKind, DecodeError, DecodedCall, DecodedValue, ExtractionMode,
+    SmartContract,
+};
+use std::vec;
+use types::IcanAddress;
+
+/// Multi-token contract. Every transferred (id, amount) pair becomes its own transfer
+#[derive(Debug, Clone)]
+pub struct Cbc1155 {
+    address: String,
+    mode: ExtractionMode,
+    transfer_selectors: Vec<String>,
+    transfer_single_topic: String,
+    transfer_batch_topic: String,
+}
+
+impl Cbc1155 {
+    pub fn new(address: String, mode: ExtractionMode) -> Self {
+        Cbc1155 {
+            address,
+            mode,
+            transfer_selectors: vec![
+                "abfbcbd5".to_string(), // safeTransferFrom(address,address,uint256,uint256,bytes)
+                "36aa5a49".to_string(), // safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)
+            ],
+            // TransferSingle(address,address,address,uint256,uint256)
+            transfer_single_topic:
+                "58916468075c89b44f05cbf7e91675e68c4007f49e67a7363e67fde2dfe09f2f".to_string(),
+            // TransferBatch(address,address,address,uint256[],uint256[])
+            transfer_batch_topic:
+                "ee46ffaf4707bc9e075a53bee0835553276e6684a888b3ab002dfa66ae5f1ca3".to_string(),
+        }
+    }
+}
+
+/// Builds the transfer of the `value` amount of the `id` token from the ABI words
+fn transfer(
+    kind: CallKind,
+    method: &str,
+    index: usize,
+    from: &str,
+    to: &str,
+    id: &str,
+    value: &str,
+) -> Result<DecodedCall, DecodeError> {
+    Ok(DecodedCall::new(kind, method, index)
+        .with_arg("from", DecodedValue::address_at(from, 0)?)
+        .with_arg("to", DecodedValue::address_at(to, 0)?)
+        .with_arg("id", DecodedValue::uint_at(id, 0)?)
+        .with_arg("value", DecodedValue::uint_at(value, 0)?))
+}
+
+/// Builds transfers of the ids and values arrays which offsets are stored in the words at the given positions
+fn batch_transfers(
+    kind: CallKind,
+    method: &str,
+    from: &str,
+    to: &str,
+    data: &str,
+    ids_at: usize,
+    values_at: usize,
+) -> Result<Vec<DecodedCall>, DecodeError> {
+    let token_ids = read_array(data, ids_at)?;
+    let values = read_array(data, values_at)?;
+    if token_ids.len() != values.len() {
+        return Err(DecodeError::LengthMismatch(token_ids.len(), values.len()));
+    }
+    token_ids
+        .iter()
+        .zip(values.iter())
+        .enumerate()
+        .map(|(i, (id, value))| transfer(kind, method, i, from, to, id, value))
+        .collect()
+}
+
+impl SmartContract for Cbc1155 {
+    fn check_if_call(&self, input: String) -> bool {
+        for selector in &self.transfer_selectors {
+            if input.starts_with(selector) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn extract_call_data(
+        &self,
+        _sender: String,
+        input: String,
+    ) -> Result<Vec<DecodedCall>, DecodeError> {
+        let args = input.get(8..).unwrap_or_default();
+        let from = word(args, 0)?;
+        let to = word(args, 64)?;
+        match input.get(..8).unwrap_or_default() {
+            // Example: abfbcbd5 + 00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068 + 00000000000000000000ab7153b962840676c37ba604c7816b0967cdb645cc54 + 0000000000000000000000000000000000000000000000000000000000000007 + 0000000000000000000000000000000000000000000000000000000000000001 + 00000000000000000000000000000000000000000000000000000000000000a0 + 0000000000000000000000000000000000000000000000000000000000000000
+            "abfbcbd5" => Ok(vec![transfer(
+                CallKind::Call,
+                "safeTransferFrom",
+                0,
+                from,
+                to,
+                word(args, 128)?,
+                word(args, 192)?,
+            )?]),
+            /*
+            /// Example:
+            /// 36aa5a49 +                                                         // function signature
+            /// 00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068 + // from
+            /// 00000000000000000000ab7153b962840676c37ba604c7816b0967cdb645cc54 + // to
+            /// 00000000000000000000000000000000000000000000000000000000000000a0 + // offset to the ids - 160 bytes
+            /// 0000000000000000000000000000000000000000000000000000000000000100 + // offset to the values - 256 bytes
+            /// 0000000000000000000000000000000000000000000000000000000000000160 + // offset to the data - 352 bytes
+            /// 0000000000000000000000000000000000000000000000000000000000000002 + // number of ids
+            /// 0000000000000000000000000000000000000000000000000000000000000007 + // first id
+            /// 0000000000000000000000000000000000000000000000000000000000000008 + // second id
+            /// 0000000000000000000000000000000000000000000000000000000000000002 + // number of values
+            /// 0000000000000000000000000000000000000000000000000000000000000001 + // first value
+            /// 0000000000000000000000000000000000000000000000000000000000000005 + // second value
+            /// 0000000000000000000000000000000000000000000000000000000000000000   // length of the data
+             */
+            "36aa5a49" => batch_transfers(
+                CallKind::Call,
+                "safeBatchTransferFrom",
+                from,
+                to,
+                args,
+                128,
+                192,
+            ),
+            selector => Err(DecodeError::UnknownSelector(selector.to_string())),
+        }
+    }
+
+    fn check_if_event(&self, topics: &[String]) -> bool {
+        // operator, from and to are indexed, ids and values are in the data
+        topics.len() == 4
+            && (topics[0] == self.transfer_single_topic || topics[0] == self.transfer_batch_topic)
+    }
+
+    /// Example:
+    /// topics: [
+    ///     58916468075c89b44f05cbf7e91675e68c4007f49e67a7363e67fde2dfe09f2f, // TransferSingle(address,address,address,uint256,uint256)
+    ///     00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068, // operator
+    ///     00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068, // from
+    ///     00000000000000000000ab7153b962840676c37ba604c7816b0967cdb645cc54, // to
+    /// ]
+    /// data: 0000000000000000000000000000000000000000000000000000000000000007 + // id
+    ///       0000000000000000000000000000000000000000000000000000000000000001   // value
+    /// TransferBatch has the same topics and the ids and values arrays encoded in the data
+    fn extract_event_data(
+        &self,
+        topics: &[String],
+        data: String,
+    ) -> Result<Vec<DecodedCall>, DecodeError> {
+        let (from, to) = (topic(topics, 2)?, topic(topics, 3)?);
+        if topic(topics, 0)? == self.transfer_single_topic {
+            return Ok(vec![transfer(
+                CallKind::Event,
+                "TransferSingle",
+                0,
+                from,
+                to,
+                word(&data, 0)?,
+                word(&data, 64)?,
+            )?]);
+        }
+        batch_transfers(CallKind::Event, "TransferBatch", from, to, &data, 0, 64)
+    }
+
+    fn get_mode(&self) -> ExtractionMode {
+        self.mode
+    }
+
+    fn get_address(&self) -> String {
+        self.address.clone()
+    }
+
+    fn get_table_name(&self) -> String {
+        format!(
+            "{}_{}_transfers",
+            NAME,
+            IcanAddress::table_id(&self.get_address())
+        )
+    }
+
+    fn clone_dyn(&self) -> Box<dyn SmartContract> {
+        Box::new(self.clone()) // Forward to the derive(Clone) impl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c";
+    const SENDER: &str = "ab416902d2548d52352a05423d13266ee7aaf140a068";
+    const RECIPIENT: &str = "ab7153b962840676c37ba604c7816b0967cdb645cc54";
+    const TRANSFER_SINGLE_TOPIC: &str =
+        "58916468075c89b44f05cbf7e91675e68c4007f49e67a7363e67fde2dfe09f2f";
+    const TRANSFER_BATCH_TOPIC: &str =
+        "ee46ffaf4707bc9e075a53bee0835553276e6684a888b3ab002dfa66ae5f1ca3";
+
+    /// Joins the hex values into 32-byte words padded with zeros
+    fn words(values: &[&str]) -> String {
+        values
+            .iter()
+            .map(|value| format!("{:0>64}", value))
+            .collect()
+    }
+
+    fn cbc1155(mode: ExtractionMode) -> Cbc1155 {
+        Cbc1155::new(TOKEN.to_string(), mode)
+    }
+
+    fn decode(input: String) -> Result<Vec<DecodedCall>, DecodeError> {
+        cbc1155(ExtractionMode::Calldata).extract_call_data(SENDER.to_string(), input)
+    }
+
+    fn decode_event(topic0: &str, data: String) -> Result<Vec<DecodedCall>, DecodeError> {
+        let topics = vec![
+            topic0.to_string(),
+            words(&[SENDER]),
+            words(&[SENDER]),
+            words(&[RECIPIENT]),
+        ];
+        let contract = cbc1155(ExtractionMode::Event);
+        assert!(contract.check_if_event(&topics));
+        contract.extract_event_data(&topics, data)
+    }
+
+    /// Returns the batch index, sender, recipient, token ID and amount of the transfers
+    fn transfers(calls: &[DecodedCall]) -> Vec<(usize, String, String, String, String)> {
+        calls
+            .iter()
+            .map(|call| {
+                (
+                    call.index,
+                    call.address("from").unwrap().to_string(),
+                    call.address("to").unwrap().to_string(),
+                    call.uint("id").unwrap().to_string(),
+                    call.uint("value").unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn transfer(index: usize, id: &str, value: &str) -> (usize, String, String, String, String) {
+        (
+            index,
+            SENDER.to_string(),
+            RECIPIENT.to_string(),
+            id.to_string(),
+            value.to_string(),
+        )
+    }
+
+    #[test]
+    fn decodes_single_transfer_call() {
+        let input = format!(
+            "abfbcbd5{}",
+            words(&[SENDER, RECIPIENT, "7", "1", "a0", "0"])
+        );
+        assert_eq!(
+            transfers(&decode(input).unwrap()),
+            vec![transfer(0, "7", "1")]
+        );
+    }
+
+    #[test]
+    fn decodes_batch_transfer_call() {
+        let input = format!(
+            "36aa5a49{}",
+            words(&[SENDER, RECIPIENT, "a0", "100", "160", "2", "7", "8", "2", "1", "5", "0"])
+        );
+        assert_eq!(
+            transfers(&decode(input).unwrap()),
+            vec![transfer(0, "7", "1"), transfer(1, "8", "5")]
+        );
+    }
+
+    #[test]
+    fn rejects_batch_transfer_call_with_length_mismatch() {
+        let input = format!(
+            "36aa5a49{}",
+            words(&[SENDER, RECIPIENT, "a0", "100", "160", "2", "7", "8", "1", "1", "0"])
+        );
+        assert!(matches!(
+            decode(input),
+            Err(DecodeError::LengthMismatch(2, 1))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_calls() {
+        assert!(matches!(
+            decode(format!("abfbcbd5{}", words(&[SENDER, RECIPIENT, "7"]))),
+            Err(DecodeError::OutOfBounds(192))
+        ));
+        assert!(matches!(
+            decode(format!("a9059cbb{}", words(&[SENDER, RECIPIENT]))),
+            Err(DecodeError::UnknownSelector(_))
+        ));
+    }
+
+    #[test]
+    fn decodes_transfer_single_event() {
+        let calls = decode_event(TRANSFER_SINGLE_TOPIC, words(&["7", "1"])).unwrap();
+        assert_eq!(calls[0].method, "TransferSingle");
+        assert_eq!(transfers(&calls), vec![transfer(0, "7", "1")]);
+    }
+
+    #[test]
+    fn decodes_transfer_batch_event() {
+        let data = words(&["40", "a0", "2", "7", "8", "2", "1", "5"]);
+        let calls = decode_event(TRANSFER_BATCH_TOPIC, data).unwrap();
+        assert_eq!(calls[0].method, "TransferBatch");
+        // pairs of the batch are emitted by one log, so they are told apart by the index
+        assert_eq!(
+            transfers(&calls),
+            vec![transfer(0, "7", "1"), transfer(1, "8", "5")]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_events() {
+        assert!(matches!(
+            decode_event(TRANSFER_SINGLE_TOPIC, words(&["7"])),
+            Err(DecodeError::OutOfBounds(64))
+        ));
+        assert!(matches!(
+            decode_event(TRANSFER_BATCH_TOPIC, words(&["40", "a0", "2", "7"])),
+            Err(DecodeError::OutOfBounds(256))
+        ));
+    }
+}
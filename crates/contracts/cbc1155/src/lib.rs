@@ -0,0 +1,4 @@
+pub mod cbc1155;
+pub mod constants;
+pub use cbc1155::Cbc1155;
+pub use constants::NAME as CBC1155_NAME;
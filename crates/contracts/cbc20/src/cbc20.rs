@@ -1,22 +1,32 @@
 use crate::constants::NAME;
-use contracts::SmartContract;
+use contracts::{
+    read_array, topic, CallKind, DecodeError, DecodedCall, DecodedValue, ExtractionMode,
+    SmartContract,
+};
 use std::vec;
+use types::IcanAddress;
 
 #[derive(Debug, Clone)]
 pub struct Cbc20 {
     address: String,
+    mode: ExtractionMode,
     transfer_selectors: Vec<String>,
+    transfer_topic: String,
 }
 
 impl Cbc20 {
-    pub fn new(address: String) -> Self {
+    pub fn new(address: String, mode: ExtractionMode) -> Self {
         Cbc20 {
             address,
+            mode,
             transfer_selectors: vec![
                 "4b40e901".to_string(), // transfer(address,uint256)
                 "e86e7c5f".to_string(), // batchTransfer(address[],uint256[])
                 "31f2e679".to_string(), // transferFrom(address,address,uint256)
             ],
+            // Transfer(address,address,uint256)
+            transfer_topic: "c17a9d92b89f27cb79cc390f23a1a5d302fefab8c7911075ede952ac2b5607a1"
+                .to_string(),
         }
     }
 }
@@ -35,15 +45,16 @@ impl SmartContract for Cbc20 {
         &self,
         sender: String,
         input: String,
-    ) -> Vec<(usize, String, String, String)> {
-        match &input[..8] {
+    ) -> Result<Vec<DecodedCall>, DecodeError> {
+        let args = input.get(8..).unwrap_or_default();
+        let sender = IcanAddress::from_hex(&sender)
+            .map_err(|_| DecodeError::InvalidSender(sender.clone()))?;
+        match input.get(..8).unwrap_or_default() {
             // Example: 4b40e901 + 00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068 + 0000000000000000000000000000000000000000000000000000000000000001
-            "4b40e901" => {
-                let from = sender;
-                let to = input[28..72].to_string();
-                let value = input[72..136].to_string();
-                vec![(0, from, to, value)]
-            }
+            "4b40e901" => Ok(vec![DecodedCall::new(CallKind::Call, "transfer", 0)
+                .with_arg("from", DecodedValue::Address(sender))
+                .with_arg("to", DecodedValue::address_at(args, 0)?)
+                .with_arg("value", DecodedValue::uint_at(args, 64)?)]),
             /*
             /// Example:
             ///	e86e7c5f +                                                         // function signature
@@ -57,38 +68,230 @@ impl SmartContract for Cbc20 {
             /// 0000000000000000000000000000000000000000000000000000000000000001 + // second value
              */
             "e86e7c5f" => {
-                let mut res = vec![];
-                let offset = 136;
-                let count = usize::from_str_radix(&input[136..200], 16).unwrap();
-                for i in 0..count {
-                    let to = input[offset + 84 + i * 64..offset + 128 + i * 64].to_string();
-                    let value = input
-                        [offset + 128 + count * 64 + i * 64..offset + 192 + count * 64 + i * 64]
-                        .to_string();
-                    res.push((i, sender.clone(), to, value));
+                let recipients = read_array(args, 0)?;
+                let values = read_array(args, 64)?;
+                if recipients.len() != values.len() {
+                    return Err(DecodeError::LengthMismatch(recipients.len(), values.len()));
                 }
-                res
+                recipients
+                    .iter()
+                    .zip(values.iter())
+                    .enumerate()
+                    .map(|(i, (to, value))| {
+                        Ok(DecodedCall::new(CallKind::Call, "batchTransfer", i)
+                            .with_arg("from", DecodedValue::Address(sender))
+                            .with_arg("to", DecodedValue::address_at(to, 0)?)
+                            .with_arg("value", DecodedValue::uint_at(value, 0)?))
+                    })
+                    .collect()
             }
             // Example: 31f2e679 + 00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068 + 00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068 + 0000000000000000000000000000000000000000000000000000000000000001
-            "31f2e679" => {
-                let from = input[28..72].to_string();
-                let to = input[92..136].to_string();
-                let value = input[136..200].to_string();
-                vec![(0, from, to, value)]
-            }
-            _ => panic!("Unsupported transfer function: {:?}", input),
+            "31f2e679" => Ok(vec![DecodedCall::new(CallKind::Call, "transferFrom", 0)
+                .with_arg("from", DecodedValue::address_at(args, 0)?)
+                .with_arg("to", DecodedValue::address_at(args, 64)?)
+                .with_arg("value", DecodedValue::uint_at(args, 128)?)]),
+            selector => Err(DecodeError::UnknownSelector(selector.to_string())),
         }
     }
 
+    fn check_if_event(&self, topics: &[String]) -> bool {
+        // from and to are indexed, value is in the data
+        topics.len() == 3 && topics[0] == self.transfer_topic
+    }
+
+    /// Example:
+    /// topics: [
+    ///     c17a9d92b89f27cb79cc390f23a1a5d302fefab8c7911075ede952ac2b5607a1, // Transfer(address,address,uint256)
+    ///     00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068, // from
+    ///     00000000000000000000ab7153b962840676c37ba604c7816b0967cdb645cc54, // to
+    /// ]
+    /// data: 0000000000000000000000000000000000000000000000000000000000000001 // value
+    fn extract_event_data(
+        &self,
+        topics: &[String],
+        data: String,
+    ) -> Result<Vec<DecodedCall>, DecodeError> {
+        Ok(vec![DecodedCall::new(CallKind::Event, "Transfer", 0)
+            .with_arg("from", DecodedValue::address_at(topic(topics, 1)?, 0)?)
+            .with_arg("to", DecodedValue::address_at(topic(topics, 2)?, 0)?)
+            .with_arg("value", DecodedValue::uint_at(&data, 0)?)])
+    }
+
+    fn get_mode(&self) -> ExtractionMode {
+        self.mode
+    }
+
     fn get_address(&self) -> String {
         self.address.clone()
     }
 
     fn get_table_name(&self) -> String {
-        format!("{}_{}_transfers", NAME, &self.get_address()[..8])
+        format!(
+            "{}_{}_transfers",
+            NAME,
+            IcanAddress::table_id(&self.get_address())
+        )
     }
 
     fn clone_dyn(&self) -> Box<dyn SmartContract> {
         Box::new(self.clone()) // Forward to the derive(Clone) impl
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c";
+    const SENDER: &str = "ab416902d2548d52352a05423d13266ee7aaf140a068";
+    const RECIPIENT: &str = "ab7153b962840676c37ba604c7816b0967cdb645cc54";
+
+    /// Joins the hex values into 32-byte words padded with zeros
+    fn words(values: &[&str]) -> String {
+        values
+            .iter()
+            .map(|value| format!("{:0>64}", value))
+            .collect()
+    }
+
+    fn decode(input: String) -> Result<Vec<DecodedCall>, DecodeError> {
+        Cbc20::new(TOKEN.to_string(), ExtractionMode::Calldata)
+            .extract_call_data(SENDER.to_string(), input)
+    }
+
+    fn transfers(calls: &[DecodedCall]) -> Vec<(String, String, String)> {
+        calls
+            .iter()
+            .map(|call| {
+                (
+                    call.address("from").unwrap().to_string(),
+                    call.address("to").unwrap().to_string(),
+                    call.uint("value").unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_transfer() {
+        let calls = decode(format!("4b40e901{}", words(&[RECIPIENT, "a"]))).unwrap();
+        assert_eq!(
+            transfers(&calls),
+            vec![(SENDER.to_string(), RECIPIENT.to_string(), "10".to_string())]
+        );
+    }
+
+    #[test]
+    fn decodes_transfer_from() {
+        let calls = decode(format!("31f2e679{}", words(&[RECIPIENT, SENDER, "1"]))).unwrap();
+        assert_eq!(
+            transfers(&calls),
+            vec![(RECIPIENT.to_string(), SENDER.to_string(), "1".to_string())]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_calldata() {
+        assert!(matches!(
+            decode("4b40e901".to_string()),
+            Err(DecodeError::OutOfBounds(0))
+        ));
+        assert!(matches!(
+            decode(format!("4b40e901{}", words(&[RECIPIENT]))),
+            Err(DecodeError::OutOfBounds(64))
+        ));
+        assert!(matches!(
+            decode(format!(
+                "31f2e679{}",
+                &words(&[RECIPIENT, SENDER, "1"])[..191]
+            )),
+            Err(DecodeError::OutOfBounds(128))
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_selector() {
+        assert!(matches!(
+            decode(format!("a9059cbb{}", words(&[RECIPIENT, "1"]))),
+            Err(DecodeError::UnknownSelector(_))
+        ));
+    }
+
+    #[test]
+    fn decodes_batch_transfer() {
+        let input = format!(
+            "e86e7c5f{}",
+            words(&["40", "a0", "2", RECIPIENT, SENDER, "2", "1", "2"])
+        );
+        assert_eq!(
+            transfers(&decode(input).unwrap()),
+            vec![
+                (SENDER.to_string(), RECIPIENT.to_string(), "1".to_string()),
+                (SENDER.to_string(), SENDER.to_string(), "2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_batch_transfer_with_huge_offset() {
+        let input = format!(
+            "e86e7c5f{}",
+            words(&["f".repeat(64).as_str(), "a0", "1", RECIPIENT, "1", "1"])
+        );
+        assert!(matches!(decode(input), Err(DecodeError::InvalidOffset(0))));
+    }
+
+    #[test]
+    fn rejects_batch_transfer_with_dishonest_count() {
+        let input = format!(
+            "e86e7c5f{}",
+            words(&["40", "a0", "6", RECIPIENT, SENDER, "1", "1"])
+        );
+        assert!(matches!(
+            decode(input),
+            Err(DecodeError::InvalidOffset(128))
+        ));
+    }
+
+    #[test]
+    fn rejects_batch_transfer_with_length_mismatch() {
+        let input = format!(
+            "e86e7c5f{}",
+            words(&["40", "a0", "2", RECIPIENT, SENDER, "1", "1"])
+        );
+        assert!(matches!(
+            decode(input),
+            Err(DecodeError::LengthMismatch(2, 1))
+        ));
+    }
+
+    #[test]
+    fn decodes_transfer_event() {
+        let cbc20 = Cbc20::new(TOKEN.to_string(), ExtractionMode::Event);
+        let topics = vec![
+            cbc20.transfer_topic.clone(),
+            words(&[SENDER]),
+            words(&[RECIPIENT]),
+        ];
+        assert!(cbc20.check_if_event(&topics));
+        let calls = cbc20.extract_event_data(&topics, words(&["5"])).unwrap();
+        assert_eq!(
+            transfers(&calls),
+            vec![(SENDER.to_string(), RECIPIENT.to_string(), "5".to_string())]
+        );
+    }
+
+    #[test]
+    fn rejects_transfer_event_without_topics() {
+        let cbc20 = Cbc20::new(TOKEN.to_string(), ExtractionMode::Event);
+        let topics = vec![cbc20.transfer_topic.clone(), words(&[SENDER])];
+        assert!(matches!(
+            cbc20.extract_event_data(&topics, words(&["5"])),
+            Err(DecodeError::MissingTopic(2))
+        ));
+        assert!(matches!(
+            cbc20.extract_event_data(&topics, String::new()),
+            Err(DecodeError::MissingTopic(2))
+        ));
+    }
+}
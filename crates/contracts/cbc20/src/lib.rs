@@ -1,4 +1,5 @@
 pub mod cbc20;
 pub mod constants;
+pub mod probe;
 pub use cbc20::Cbc20;
 pub use constants::NAME as CBC20_NAME;
@@ -0,0 +1,106 @@
+use contracts::{read_usize, word};
+
+/// Selectors of the CBC20 view functions which are called to check if the contract is a CBC20 token
+pub const NAME_SELECTOR: &str = "07ba2a17"; // name()
+pub const SYMBOL_SELECTOR: &str = "231782d8"; // symbol()
+pub const DECIMALS_SELECTOR: &str = "5d1fb5f9"; // decimals()
+pub const TOTAL_SUPPLY_SELECTOR: &str = "1f1881f8"; // totalSupply()
+
+/// Decodes ABI encoded string returned by a call.
+/// Example:
+/// 0000000000000000000000000000000000000000000000000000000000000020 + // offset to the string
+/// 0000000000000000000000000000000000000000000000000000000000000004 + // length of the string
+/// 436f726500000000000000000000000000000000000000000000000000000000   // "Core"
+pub fn decode_string(output: &str) -> Option<String> {
+    let offset = read_usize(output, 0).ok()?.checked_mul(2)?;
+    let length = read_usize(output, offset).ok()?.checked_mul(2)?;
+    let start = offset.checked_add(64)?;
+    let data = output.get(start..start.checked_add(length)?)?;
+    let bytes = data
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some(
+        String::from_utf8_lossy(&bytes)
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect(),
+    )
+}
+
+/// Decodes ABI encoded uint which fits into u64, e.g. decimals
+pub fn decode_uint(output: &str) -> Option<u64> {
+    let word = word(output, 0).ok()?;
+    if !word[..48].chars().all(|c| c == '0') {
+        return None;
+    }
+    u64::from_str_radix(&word[48..], 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Joins the hex values into 32-byte words padded with zeros
+    fn words(values: &[&str]) -> String {
+        values
+            .iter()
+            .map(|value| format!("{:0>64}", value))
+            .collect()
+    }
+
+    #[test]
+    fn decodes_string() {
+        let output = words(&["20", "4"]) + &format!("{:0<64}", "436f7265");
+        assert_eq!(decode_string(&output), Some("Core".to_string()));
+    }
+
+    #[test]
+    fn decodes_empty_string() {
+        let output = words(&["20", "0"]);
+        assert_eq!(decode_string(&output), Some(String::new()));
+    }
+
+    #[test]
+    fn drops_control_chars_of_string() {
+        let output = words(&["20", "3"]) + &format!("{:0<64}", "410042");
+        assert_eq!(decode_string(&output), Some("AB".to_string()));
+    }
+
+    #[test]
+    fn rejects_string_out_of_bounds() {
+        // length is bigger than the output
+        let output = words(&["20", "40"]) + &format!("{:0<64}", "436f7265");
+        assert_eq!(decode_string(&output), None);
+        // offset points behind the output
+        assert_eq!(decode_string(&words(&["60", "4"])), None);
+        // offset and length do not fit into usize
+        assert_eq!(decode_string(&words(&["f".repeat(64).as_str()])), None);
+        let output = words(&["20", "ffffffffffffffff"]);
+        assert_eq!(decode_string(&output), None);
+        assert_eq!(decode_string(""), None);
+    }
+
+    #[test]
+    fn rejects_string_with_invalid_hex() {
+        let output = words(&["20", "2"]) + &format!("{:0<64}", "zz");
+        assert_eq!(decode_string(&output), None);
+    }
+
+    #[test]
+    fn decodes_uint() {
+        assert_eq!(decode_uint(&words(&["12"])), Some(18));
+        assert_eq!(decode_uint(&words(&["0"])), Some(0));
+        assert_eq!(decode_uint(&words(&["ffffffffffffffff"])), Some(u64::MAX));
+    }
+
+    #[test]
+    fn rejects_invalid_uint() {
+        // does not fit into u64
+        assert_eq!(decode_uint(&words(&["10000000000000000"])), None);
+        assert_eq!(decode_uint(&words(&["12"])[..63]), None);
+        assert_eq!(decode_uint(&"z".repeat(64)), None);
+        assert_eq!(decode_uint(""), None);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::constants::NAME;
+use contracts::{
+    topic, CallKind, DecodeError, DecodedCall, DecodedValue, ExtractionMode, SmartContract,
+};
+use std::vec;
+use types::IcanAddress;
+
+/// Token transfers of CBC721 contracts move the single token of their token ID, which is stored in the token_id column.
+/// Approvals do not move tokens, so they are not extracted as transfers
+#[derive(Debug, Clone)]
+pub struct Cbc721 {
+    address: String,
+    mode: ExtractionMode,
+    transfer_selectors: Vec<String>,
+    transfer_topic: String,
+}
+
+impl Cbc721 {
+    pub fn new(address: String, mode: ExtractionMode) -> Self {
+        Cbc721 {
+            address,
+            mode,
+            transfer_selectors: vec![
+                "31f2e679".to_string(), // transferFrom(address,address,uint256)
+                "3453ba4a".to_string(), // safeTransferFrom(address,address,uint256)
+                "f3d63809".to_string(), // safeTransferFrom(address,address,uint256,bytes)
+            ],
+            // Transfer(address,address,uint256)
+            transfer_topic: "c17a9d92b89f27cb79cc390f23a1a5d302fefab8c7911075ede952ac2b5607a1"
+                .to_string(),
+        }
+    }
+}
+
+impl SmartContract for Cbc721 {
+    fn check_if_call(&self, input: String) -> bool {
+        for selector in &self.transfer_selectors {
+            if input.starts_with(selector) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// All transfer functions start with the same arguments, the data of safeTransferFrom is ignored
+    /// Example: 31f2e679 + 00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068 + 00000000000000000000ab7153b962840676c37ba604c7816b0967cdb645cc54 + 0000000000000000000000000000000000000000000000000000000000000007
+    fn extract_call_data(
+        &self,
+        _sender: String,
+        input: String,
+    ) -> Result<Vec<DecodedCall>, DecodeError> {
+        let method = match input.get(..8).unwrap_or_default() {
+            "31f2e679" => "transferFrom",
+            "3453ba4a" | "f3d63809" => "safeTransferFrom",
+            selector => return Err(DecodeError::UnknownSelector(selector.to_string())),
+        };
+        let args = input.get(8..).unwrap_or_default();
+        Ok(vec![DecodedCall::new(CallKind::Call, method, 0)
+            .with_arg("from", DecodedValue::address_at(args, 0)?)
+            .with_arg("to", DecodedValue::address_at(args, 64)?)
+            .with_arg("tokenId", DecodedValue::uint_at(args, 128)?)])
+    }
+
+    fn check_if_event(&self, topics: &[String]) -> bool {
+        // unlike CBC20, the token ID is indexed as well
+        topics.len() == 4 && topics[0] == self.transfer_topic
+    }
+
+    /// Example:
+    /// topics: [
+    ///     c17a9d92b89f27cb79cc390f23a1a5d302fefab8c7911075ede952ac2b5607a1, // Transfer(address,address,uint256)
+    ///     00000000000000000000ab416902d2548d52352a05423d13266ee7aaf140a068, // from
+    ///     00000000000000000000ab7153b962840676c37ba604c7816b0967cdb645cc54, // to
+    ///     0000000000000000000000000000000000000000000000000000000000000007, // token ID
+    /// ]
+    /// data: empty
+    fn extract_event_data(
+        &self,
+        topics: &[String],
+        _data: String,
+    ) -> Result<Vec<DecodedCall>, DecodeError> {
+        Ok(vec![DecodedCall::new(CallKind::Event, "Transfer", 0)
+            .with_arg("from", DecodedValue::address_at(topic(topics, 1)?, 0)?)
+            .with_arg("to", DecodedValue::address_at(topic(topics, 2)?, 0)?)
+            .with_arg(
+                "tokenId",
+                DecodedValue::uint_at(topic(topics, 3)?, 0)?,
+            )])
+    }
+
+    fn get_mode(&self) -> ExtractionMode {
+        self.mode
+    }
+
+    fn get_address(&self) -> String {
+        self.address.clone()
+    }
+
+    fn get_table_name(&self) -> String {
+        format!(
+            "{}_{}_transfers",
+            NAME,
+            IcanAddress::table_id(&self.get_address())
+        )
+    }
+
+    fn clone_dyn(&self) -> Box<dyn SmartContract> {
+        Box::new(self.clone()) // Forward to the derive(Clone) impl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c";
+    const SENDER: &str = "ab416902d2548d52352a05423d13266ee7aaf140a068";
+    const RECIPIENT: &str = "ab7153b962840676c37ba604c7816b0967cdb645cc54";
+    const TRANSFER_TOPIC: &str = "c17a9d92b89f27cb79cc390f23a1a5d302fefab8c7911075ede952ac2b5607a1";
+
+    /// Joins the hex values into 32-byte words padded with zeros
+    fn words(values: &[&str]) -> String {
+        values
+            .iter()
+            .map(|value| format!("{:0>64}", value))
+            .collect()
+    }
+
+    fn cbc721(mode: ExtractionMode) -> Cbc721 {
+        Cbc721::new(TOKEN.to_string(), mode)
+    }
+
+    fn decode(input: String) -> Result<Vec<DecodedCall>, DecodeError> {
+        cbc721(ExtractionMode::Calldata).extract_call_data(SENDER.to_string(), input)
+    }
+
+    fn transfers(calls: &[DecodedCall]) -> Vec<(&str, String, String, String)> {
+        calls
+            .iter()
+            .map(|call| {
+                (
+                    call.method.as_str(),
+                    call.address("from").unwrap().to_string(),
+                    call.address("to").unwrap().to_string(),
+                    call.uint("tokenId").unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_transfer_functions() {
+        let args = words(&[SENDER, RECIPIENT, "7"]);
+        for (selector, method) in [
+            ("31f2e679", "transferFrom"),
+            ("3453ba4a", "safeTransferFrom"),
+            ("f3d63809", "safeTransferFrom"),
+        ] {
+            let calls = decode(format!("{}{}", selector, args)).unwrap();
+            assert_eq!(
+                transfers(&calls),
+                vec![(
+                    method,
+                    SENDER.to_string(),
+                    RECIPIENT.to_string(),
+                    "7".to_string()
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn ignores_data_of_safe_transfer() {
+        let input = format!(
+            "f3d63809{}",
+            words(&[SENDER, RECIPIENT, "7", "80", "1", "ff"])
+        );
+        assert_eq!(transfers(&decode(input).unwrap()).len(), 1);
+    }
+
+    #[test]
+    fn rejects_truncated_calldata() {
+        assert!(matches!(
+            decode("31f2e679".to_string()),
+            Err(DecodeError::OutOfBounds(0))
+        ));
+        assert!(matches!(
+            decode(format!(
+                "31f2e679{}",
+                &words(&[SENDER, RECIPIENT, "7"])[..191]
+            )),
+            Err(DecodeError::OutOfBounds(128))
+        ));
+        assert!(matches!(
+            decode("31f2".to_string()),
+            Err(DecodeError::UnknownSelector(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_selector() {
+        // approve(address,uint256) does not move the token
+        assert!(matches!(
+            decode(format!("095ea7b3{}", words(&[RECIPIENT, "7"]))),
+            Err(DecodeError::UnknownSelector(_))
+        ));
+    }
+
+    #[test]
+    fn decodes_transfer_event() {
+        let topics = vec![
+            TRANSFER_TOPIC.to_string(),
+            words(&[SENDER]),
+            words(&[RECIPIENT]),
+            words(&["7"]),
+        ];
+        let contract = cbc721(ExtractionMode::Event);
+        assert!(contract.check_if_event(&topics));
+        let calls = contract.extract_event_data(&topics, String::new()).unwrap();
+        assert_eq!(
+            transfers(&calls),
+            vec![(
+                "Transfer",
+                SENDER.to_string(),
+                RECIPIENT.to_string(),
+                "7".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn skips_cbc20_transfer_event() {
+        // CBC20 tokens emit the same event with the value in the data instead of the indexed token ID
+        let topics = vec![
+            TRANSFER_TOPIC.to_string(),
+            words(&[SENDER]),
+            words(&[RECIPIENT]),
+        ];
+        assert!(!cbc721(ExtractionMode::Event).check_if_event(&topics));
+        assert!(matches!(
+            cbc721(ExtractionMode::Event).extract_event_data(&topics, words(&["7"])),
+            Err(DecodeError::MissingTopic(3))
+        ));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod cbc721;
+pub mod constants;
+pub use cbc721::Cbc721;
+pub use constants::NAME as CBC721_NAME;
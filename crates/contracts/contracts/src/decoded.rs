@@ -0,0 +1,120 @@
+use crate::{word, DecodeError};
+use base_primitives::U256;
+use std::fmt;
+use types::IcanAddress;
+
+/// CallKind tells whether the data was decoded from the calldata or from an emitted event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    Event,
+}
+
+/// Decoded value of a single argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodedValue {
+    Address(IcanAddress),
+    Uint(U256),
+    // hex encoded bytes
+    Bytes(String),
+    Array(Vec<DecodedValue>),
+}
+
+impl DecodedValue {
+    /// Reads the address from the 32-byte ABI word at the given hex char position
+    pub fn address_at(data: &str, at: usize) -> Result<Self, DecodeError> {
+        let address = IcanAddress::from_hex(&word(data, at)?[20..])
+            .map_err(|_| DecodeError::InvalidHex(at))?;
+        Ok(DecodedValue::Address(address))
+    }
+
+    /// Reads the uint256 from the 32-byte ABI word at the given hex char position
+    pub fn uint_at(data: &str, at: usize) -> Result<Self, DecodeError> {
+        let value =
+            U256::from_str_radix(word(data, at)?, 16).map_err(|_| DecodeError::InvalidHex(at))?;
+        Ok(DecodedValue::Uint(value))
+    }
+
+    pub fn as_address(&self) -> Option<IcanAddress> {
+        match self {
+            DecodedValue::Address(address) => Some(*address),
+            _ => None,
+        }
+    }
+
+    pub fn as_uint(&self) -> Option<U256> {
+        match self {
+            DecodedValue::Uint(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Uints are formatted as decimal strings and arrays as comma separated lists in brackets
+impl fmt::Display for DecodedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodedValue::Address(address) => write!(f, "{}", address),
+            DecodedValue::Uint(value) => write!(f, "{}", value),
+            DecodedValue::Bytes(bytes) => write!(f, "{}", bytes),
+            DecodedValue::Array(values) => {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                write!(f, "[{}]", values.join(","))
+            }
+        }
+    }
+}
+
+/// Named argument of the decoded call or event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedArg {
+    pub name: String,
+    pub value: DecodedValue,
+}
+
+/// DecodedCall is a single call or event decoded by the smart contract processor.
+/// Calls which contain several transfers, e.g. batch transfers, are split into several
+/// decoded calls with different indexes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedCall {
+    pub kind: CallKind,
+    // name of the function or event, e.g. `transfer` or `Transfer`
+    pub method: String,
+    pub index: usize,
+    pub args: Vec<DecodedArg>,
+}
+
+impl DecodedCall {
+    pub fn new(kind: CallKind, method: &str, index: usize) -> Self {
+        DecodedCall {
+            kind,
+            method: method.to_string(),
+            index,
+            args: vec![],
+        }
+    }
+
+    /// Appends the argument, used to build the decoded call
+    pub fn with_arg(mut self, name: &str, value: DecodedValue) -> Self {
+        self.args.push(DecodedArg {
+            name: name.to_string(),
+            value,
+        });
+        self
+    }
+
+    pub fn arg(&self, name: &str) -> Option<&DecodedValue> {
+        self.args
+            .iter()
+            .find(|arg| arg.name == name)
+            .map(|arg| &arg.value)
+    }
+
+    pub fn address(&self, name: &str) -> Option<IcanAddress> {
+        self.arg(name).and_then(DecodedValue::as_address)
+    }
+
+    pub fn uint(&self, name: &str) -> Option<U256> {
+        self.arg(name).and_then(DecodedValue::as_uint)
+    }
+}
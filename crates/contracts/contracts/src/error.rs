@@ -0,0 +1,17 @@
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("unsupported function selector {0}")]
+    UnknownSelector(String),
+    #[error("data is too short to read a word at char {0}")]
+    OutOfBounds(usize),
+    #[error("log has no topic {0}")]
+    MissingTopic(usize),
+    #[error("word at char {0} is not valid hex")]
+    InvalidHex(usize),
+    #[error("offset or length at char {0} is too big")]
+    InvalidOffset(usize),
+    #[error("invalid sender address {0}")]
+    InvalidSender(String),
+    #[error("arrays have different lengths: {0} and {1}")]
+    LengthMismatch(usize, usize),
+}
@@ -1,2 +1,10 @@
+mod decoded;
+mod error;
+mod mode;
 mod transfer;
+mod words;
+pub use decoded::{CallKind, DecodedArg, DecodedCall, DecodedValue};
+pub use error::DecodeError;
+pub use mode::ExtractionMode;
 pub use transfer::SmartContract;
+pub use words::{read_array, read_usize, topic, word};
@@ -0,0 +1,26 @@
+use std::str::FromStr;
+
+/// ExtractionMode defines where the token transfers are taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExtractionMode {
+    // Decode calldata of the direct calls to the token contract
+    #[default]
+    Calldata,
+    // Decode events emitted by the token contract
+    Event,
+    // Decode events and fall back to calldata for the calls which emitted no events, e.g. failed ones
+    Both,
+}
+
+impl FromStr for ExtractionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "calldata" => Ok(ExtractionMode::Calldata),
+            "event" => Ok(ExtractionMode::Event),
+            "both" => Ok(ExtractionMode::Both),
+            _ => Err(format!("Unknown extraction mode: {}", s)),
+        }
+    }
+}
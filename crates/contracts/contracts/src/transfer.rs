@@ -1,10 +1,31 @@
+use crate::{DecodeError, DecodedCall, ExtractionMode};
+
+/// Transfers are extracted as decoded calls with `from`, `to` and `value` arguments.
+/// Multi-token contracts add the `id` argument, NFT contracts use `tokenId` instead of `value`
 pub trait SmartContract: Send + Sync {
     fn check_if_call(&self, input: String) -> bool;
+    /// Decodes transfers from the calldata. Malformed calldata is reported as an error
     fn extract_call_data(
         &self,
         sender: String,
         input: String,
-    ) -> Vec<(usize, String, String, String)>;
+    ) -> Result<Vec<DecodedCall>, DecodeError>;
+
+    /// Checks if the log topics belong to a transfer event
+    fn check_if_event(&self, _topics: &[String]) -> bool {
+        false
+    }
+    /// Extracts transfers from the event topics and data
+    fn extract_event_data(
+        &self,
+        _topics: &[String],
+        _data: String,
+    ) -> Result<Vec<DecodedCall>, DecodeError> {
+        Ok(vec![])
+    }
+    fn get_mode(&self) -> ExtractionMode {
+        ExtractionMode::Calldata
+    }
 
     fn get_address(&self) -> String;
     fn get_table_name(&self) -> String;
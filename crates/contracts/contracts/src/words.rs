@@ -0,0 +1,138 @@
+use crate::DecodeError;
+
+/// Returns the 32-byte word which starts at the given hex char position of the data
+pub fn word(data: &str, at: usize) -> Result<&str, DecodeError> {
+    let word = data.get(at..at + 64).ok_or(DecodeError::OutOfBounds(at))?;
+    if !word.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(DecodeError::InvalidHex(at));
+    }
+    Ok(word)
+}
+
+/// Returns the topic of the log at the given position. Logs of other contracts may have fewer topics
+pub fn topic(topics: &[String], at: usize) -> Result<&str, DecodeError> {
+    topics
+        .get(at)
+        .map(String::as_str)
+        .ok_or(DecodeError::MissingTopic(at))
+}
+
+/// Reads the offset or length word. Values which point outside of the data are rejected
+pub fn read_usize(data: &str, at: usize) -> Result<usize, DecodeError> {
+    let digits = word(data, at)?.trim_start_matches('0');
+    if digits.len() > 16 {
+        return Err(DecodeError::InvalidOffset(at));
+    }
+    let value = usize::from_str_radix(digits, 16).unwrap_or(0);
+    if value > data.len() {
+        return Err(DecodeError::InvalidOffset(at));
+    }
+    Ok(value)
+}
+
+/// Reads the words of the dynamic array which offset is stored in the word at the given position.
+/// Offsets are counted in bytes from the start of the data
+pub fn read_array(data: &str, at: usize) -> Result<Vec<&str>, DecodeError> {
+    let start = read_usize(data, at)? * 2;
+    let count = read_usize(data, start)?;
+    if count > (data.len() - start) / 64 {
+        return Err(DecodeError::InvalidOffset(start));
+    }
+    (0..count)
+        .map(|i| word(data, start + 64 + i * 64))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Joins the hex values into 32-byte words padded with zeros
+    fn words(values: &[&str]) -> String {
+        values
+            .iter()
+            .map(|value| format!("{:0>64}", value))
+            .collect()
+    }
+
+    #[test]
+    fn word_of_truncated_data() {
+        let data = words(&["1"]);
+        assert!(matches!(
+            word(&data[..63], 0),
+            Err(DecodeError::OutOfBounds(0))
+        ));
+        assert!(matches!(word(&data, 64), Err(DecodeError::OutOfBounds(64))));
+    }
+
+    #[test]
+    fn word_with_invalid_hex() {
+        let data = "z".repeat(64);
+        assert!(matches!(word(&data, 0), Err(DecodeError::InvalidHex(0))));
+    }
+
+    #[test]
+    fn topic_out_of_bounds() {
+        let topics = vec!["a".to_string()];
+        assert_eq!(topic(&topics, 0).unwrap(), "a");
+        assert!(matches!(
+            topic(&topics, 1),
+            Err(DecodeError::MissingTopic(1))
+        ));
+    }
+
+    #[test]
+    fn read_usize_within_data() {
+        let data = words(&["40", "0"]);
+        assert_eq!(read_usize(&data, 0).unwrap(), 64);
+    }
+
+    #[test]
+    fn read_usize_with_huge_value() {
+        let data = words(&["f".repeat(64).as_str()]);
+        assert!(matches!(
+            read_usize(&data, 0),
+            Err(DecodeError::InvalidOffset(0))
+        ));
+        // fits into usize, but points outside of the data
+        let data = words(&["ffffffff"]);
+        assert!(matches!(
+            read_usize(&data, 0),
+            Err(DecodeError::InvalidOffset(0))
+        ));
+    }
+
+    #[test]
+    fn read_array_of_words() {
+        // offset of the array in bytes, its length and the elements
+        let data = words(&["20", "2", "a", "b"]);
+        assert_eq!(
+            read_array(&data, 0).unwrap(),
+            vec![format!("{:0>64}", "a"), format!("{:0>64}", "b")]
+        );
+    }
+
+    #[test]
+    fn read_array_with_offset_at_the_end_of_data() {
+        let data = words(&["40", "0"]);
+        assert!(matches!(
+            read_array(&data, 0),
+            Err(DecodeError::OutOfBounds(128))
+        ));
+    }
+
+    #[test]
+    fn read_array_with_dishonest_count() {
+        let data = words(&["20", "5", "a"]);
+        assert!(matches!(
+            read_array(&data, 0),
+            Err(DecodeError::InvalidOffset(64))
+        ));
+        // the length word fits into the data, the last element does not
+        let data = words(&["20", "2", "a"]);
+        assert!(matches!(
+            read_array(&data, 0),
+            Err(DecodeError::OutOfBounds(192))
+        ));
+    }
+}
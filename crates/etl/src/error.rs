@@ -2,4 +2,12 @@
 pub enum ETLError {
     #[error("chain on provider is not synced to requested block yet")]
     ChainIsNotSyncedOnProvider,
+    #[error("receipt of transaction {0} is missing")]
+    MissingReceipt(String),
+    #[error("node returned {1} traces for {2} transactions of block {0}")]
+    TraceCountMismatch(i64, usize, usize),
+    #[error("reorgs can not be detected without the blocks module, enable it or use the finalized-only mode")]
+    BlocksModuleRequired,
+    #[error("balance changes can not be calculated without the block reward, set it with --block-reward")]
+    BlockRewardRequired,
 }
@@ -1,28 +1,43 @@
 use crate::ETLError;
-use atoms_rpc_types::{BlockNumberOrTag, SyncStatus};
+use abi::AbiContract;
+use atoms_rpc_types::{BlockNumberOrTag, SyncStatus, TransactionReceipt};
+use base_primitives::{hex, U256};
 use config::Config;
-use contracts::SmartContract;
+use contracts::{DecodedCall, ExtractionMode, SmartContract};
 use futures::future::join_all;
 use futures::stream::StreamExt;
 use provider::Provider;
-use std::collections::HashMap;
+use sha3::{Digest, Sha3_256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::pin::Pin;
+use std::sync::{Mutex, RwLock};
 use std::{error::Error, sync::Arc};
 use storage::Storage;
 use tokio::spawn;
 use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration};
-use tracing::info;
-use types::{Block, TokenTransfer, Transaction};
+use tracing::{info, warn};
+use types::{
+    BalanceChange, Batch, Block, Contract, DecodeFailure, DecodedRow, DecodedTable, IcanAddress,
+    Log, Receipt, TokenTransfer, Trace, Transaction,
+};
 
 pub struct ETLWorker {
     pub config: Config,
     storage: Arc<dyn Storage + Send + Sync>,
     provider: Provider,
-    smart_contracts_processors: Vec<Box<dyn SmartContract>>,
+    /// Shared between the worker clones, so tokens discovered by any of them are processed by all
+    smart_contracts_processors: Arc<RwLock<Vec<Box<dyn SmartContract>>>>,
+    /// Addresses which were already checked by the token discovery
+    checked_addresses: Arc<Mutex<HashSet<String>>>,
+    /// Contracts which calls and events are decoded with their ABI
+    abi_contracts: Vec<AbiContract>,
 
     last_saved_block: i64,
     last_checked_block: i64,
+    /// Latest block which was marked as matured, -1 until the stored blocks are checked for the first time
+    last_matured_block: i64,
 }
 
 impl Clone for ETLWorker {
@@ -31,17 +46,20 @@ impl Clone for ETLWorker {
             storage: Arc::clone(&self.storage),
             config: self.config.clone(),
             provider: self.provider.clone(),
-            smart_contracts_processors: self.smart_contracts_processors.clone(),
+            smart_contracts_processors: Arc::clone(&self.smart_contracts_processors),
+            checked_addresses: Arc::clone(&self.checked_addresses),
+            abi_contracts: self.abi_contracts.clone(),
             last_saved_block: self.last_saved_block,
             last_checked_block: self.last_checked_block,
+            last_matured_block: self.last_matured_block,
         }
     }
 }
 
-type ProcessResult = Result<
-    (Block, Vec<Transaction>, HashMap<String, Vec<TokenTransfer>>),
-    Pin<Box<dyn Error + Send + Sync>>,
->;
+// minimal number of blocks to sync, for which the bulk load is used
+const BULK_LOAD_MIN_BLOCKS: i64 = 10_000;
+
+type ProcessResult = Result<Batch, Pin<Box<dyn Error + Send + Sync>>>;
 
 impl ETLWorker {
     pub async fn new(
@@ -53,11 +71,40 @@ impl ETLWorker {
             config,
             storage,
             provider,
-            smart_contracts_processors: vec![],
+            smart_contracts_processors: Default::default(),
+            checked_addresses: Default::default(),
+            abi_contracts: vec![],
             last_saved_block: 0,
             last_checked_block: 0,
+            last_matured_block: -1,
         };
 
+        // reorgs are detected by comparing hashes of the stored blocks with the canonical chain
+        if !etl.config.finalized_only && !etl.config.modules.contains(&"blocks".to_string()) {
+            panic!("{}", ETLError::BlocksModuleRequired);
+        }
+        if etl.config.modules.contains(&"balance_changes".to_string())
+            && etl.config.block_reward.is_none()
+        {
+            panic!("{}", ETLError::BlockRewardRequired);
+        }
+
+        // tokens discovered in the previous runs are watched as well
+        if etl.config.auto_discover_tokens {
+            match etl.storage.get_discovered_tokens().await {
+                Ok(tokens) => {
+                    for (contract_name, address_set) in tokens {
+                        etl.config
+                            .watch_tokens
+                            .entry(contract_name)
+                            .or_default()
+                            .extend(address_set);
+                    }
+                }
+                Err(e) => panic!("Failed to load discovered tokens: {:?}", e),
+            }
+        }
+
         if !etl.config.watch_tokens.is_empty() {
             match etl
                 .storage
@@ -70,12 +117,54 @@ impl ETLWorker {
 
             for (contract_name, address_set) in etl.config.watch_tokens.clone() {
                 for contract_address in address_set {
-                    etl.smart_contracts_processors
-                        .push(etl.select_sc_processor(&contract_name, &contract_address));
+                    let processor = etl.select_sc_processor(&contract_name, &contract_address);
+                    let mut processors = etl.smart_contracts_processors.write().unwrap();
+                    if let Some(owner) = processors.iter().find(|sc| {
+                        sc.get_table_name() == processor.get_table_name()
+                            && sc.get_address() != contract_address
+                    }) {
+                        panic!(
+                            "Tokens {} and {} share the table {}",
+                            owner.get_address(),
+                            contract_address,
+                            processor.get_table_name()
+                        );
+                    }
+                    processors.push(processor);
+                    etl.checked_addresses
+                        .lock()
+                        .unwrap()
+                        .insert(contract_address);
                 }
             }
         }
 
+        if !etl.config.watch_contracts.is_empty() {
+            let mut tables: Vec<DecodedTable> = vec![];
+            for (contract_name, address_set) in etl.config.watch_contracts.clone() {
+                let path = Path::new(&etl.config.abi_dir).join(format!("{}.json", contract_name));
+                for contract_address in address_set {
+                    let contract = AbiContract::load(&path, &contract_name, &contract_address)
+                        .unwrap_or_else(|e| {
+                            panic!("Failed to load ABI of {}: {:?}", contract_name, e)
+                        });
+                    for method in contract.functions.iter().chain(&contract.events) {
+                        if tables.iter().all(|table| table.name != method.table_name) {
+                            tables.push(DecodedTable {
+                                name: method.table_name.clone(),
+                                columns: method.columns.clone(),
+                            });
+                        }
+                    }
+                    etl.abi_contracts.push(contract);
+                }
+            }
+            match etl.storage.create_decoded_tables(tables).await {
+                Ok(_) => {}
+                Err(e) => panic!("Failed to create decoded tables: {:?}", e),
+            }
+        }
+
         etl
     }
 
@@ -116,6 +205,24 @@ impl ETLWorker {
             }
         }
 
+        // The chain could be reorganized while the worker was stopped
+        if latest_db_block != 0 {
+            let ancestor = self.find_common_ancestor(latest_db_block).await?;
+            if ancestor < latest_db_block {
+                info!(
+                    "Stored blocks after {} are not in the canonical chain anymore, rolling them back",
+                    ancestor
+                );
+                self.storage.rollback_blocks(ancestor + 1).await?;
+                self.last_saved_block = ancestor;
+            }
+        }
+
+        // bulk mode is left on if the previous run failed during the bulk load
+        if self.config.bulk_load {
+            self.storage.set_bulk_mode(false).await?;
+        }
+
         info!("ETLWorker is running");
         self.sync_old_blocks().await?;
         info!("Stale blocks syncing is finished");
@@ -131,50 +238,134 @@ impl ETLWorker {
             .take_while(|x| futures::future::ready(x.header.number.is_some()));
 
         while let Some(header) = stream.next().await {
+            if self.config.finalized_only {
+                // Only blocks with enough confirmations are imported,
+                // so just catch up with the new finalized height
+                self.sync_old_blocks().await?;
+                continue;
+            }
             if header.header.number.unwrap() <= self.last_saved_block.try_into().unwrap() {
                 continue;
             }
             let block_height = header.header.number.unwrap() as i64;
-            let (block, mut transactions, mut token_transfers) =
-                self.fetch_and_process_block(block_height).await?;
+            let mut batch = self
+                .fetch_and_process_block(block_height, block_height)
+                .await?;
+            let block = batch.blocks[0].clone();
+
+            // The new block does not continue the stored chain, so the stored tip is orphaned
+            if let Some(ancestor) = self.detect_reorg(&block).await? {
+                self.rollback_to_ancestor(ancestor).await?;
+                continue;
+            }
+
             info!(
                 "Imported new block {:?} with {:?} transactions and {:?} token transfers",
                 block.number,
-                transactions.len(),
-                token_transfers.values().map(|v| v.len()).sum::<usize>()
+                batch.transactions.len(),
+                batch
+                    .token_transfers
+                    .values()
+                    .map(|v| v.len())
+                    .sum::<usize>()
             );
 
-            if (self
-                .safe_insert(
-                    true,
-                    &mut vec![block.clone()],
-                    &mut transactions,
-                    &mut token_transfers,
-                )
-                .await)
-                .is_err()
-            {
-                info!(
-                    "Reorg detected on height {}, cleaning the data and reimporting the block",
-                    block.number
-                );
-                self.storage.clean_block_data(block.number).await?;
-
-                // On low memory devices cleaning will take some time
-                // and we can receive new blocks in the meantime
-                // so better to start syncning from the last saved block
-                self.last_checked_block = 0;
-                self.last_saved_block = self.storage.get_latest_block_number().await.unwrap_or(0);
-                self.sync_old_blocks().await?;
+            if let Err(e) = self.safe_insert(true, &mut batch).await {
+                // The block on this height can be already stored, if it was replaced by a sibling block.
+                // Any other failure is returned, so the block is not skipped
+                match self.stored_block(block.number).await? {
+                    Some(stored_block) if stored_block.hash != block.hash => {
+                        let ancestor = self.find_common_ancestor(block.number).await?;
+                        self.rollback_to_ancestor(ancestor).await?;
+                    }
+                    _ => return Err(e),
+                }
             }
 
-            self.update_blocks_to_matured(block_height - 10, block_height - 5)
-                .await?;
+            self.mature_blocks(block_height).await?;
         }
 
         Ok(())
     }
 
+    /// Checks if the block is a child of the stored block on the previous height.
+    /// If it is not, returns the number of the latest block which is both stored and canonical.
+    async fn detect_reorg(
+        &self,
+        block: &Block,
+    ) -> Result<Option<i64>, Pin<Box<dyn Error + Send + Sync>>> {
+        let Some(stored_parent) = self.stored_block(block.number - 1).await? else {
+            // nothing to compare with, the parent is below the first synced block
+            return Ok(None);
+        };
+        if stored_parent.hash == block.parent_hash {
+            return Ok(None);
+        }
+
+        Ok(Some(self.find_common_ancestor(block.number - 1).await?))
+    }
+
+    /// Walks back from the given height comparing stored block hashes with the canonical chain
+    /// and returns the number of the first block which is the same in both.
+    async fn find_common_ancestor(
+        &self,
+        mut height: i64,
+    ) -> Result<i64, Pin<Box<dyn Error + Send + Sync>>> {
+        while height > 0 {
+            let Some(stored_block) = self.stored_block(height).await? else {
+                // block is not stored, so there is nothing to roll back below this height
+                break;
+            };
+            let canonical_block = self
+                .provider_get_block(BlockNumberOrTag::Number(height as u64))
+                .await?;
+            if stored_block.hash == canonical_block.hash {
+                break;
+            }
+            height -= 1;
+        }
+        Ok(height)
+    }
+
+    /// Returns the stored block on the height, or `None` if the storage has no block on it.
+    /// Failures of the storage are returned, so they are not taken for a missing block
+    async fn stored_block(
+        &self,
+        height: i64,
+    ) -> Result<Option<Block>, Pin<Box<dyn Error + Send + Sync>>> {
+        match self.storage.get_block_by_number(height).await {
+            Ok(block) => Ok(Some(block)),
+            // storages return an error for a missing block, the range query tells it apart from a failure
+            Err(e) => match self.storage.get_blocks_in_range(height, height).await {
+                Ok(blocks) => Ok(blocks.into_iter().next()),
+                Err(_) => Err(e),
+            },
+        }
+    }
+
+    /// Removes all blocks above the common ancestor with their transactions and token transfers
+    /// and syncs the canonical chain again
+    async fn rollback_to_ancestor(
+        &mut self,
+        ancestor: i64,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        let latest_db_block = self.storage.get_latest_block_number().await.unwrap_or(0);
+        info!(
+            "Reorg detected, rolling back {} blocks to the common ancestor {}",
+            (latest_db_block - ancestor).max(0),
+            ancestor
+        );
+        self.storage.rollback_blocks(ancestor + 1).await?;
+
+        // On low memory devices cleaning will take some time
+        // and we can receive new blocks in the meantime
+        // so better to start syncning from the last saved block
+        self.last_checked_block = 0;
+        self.last_saved_block = ancestor;
+        self.last_matured_block = self.last_matured_block.min(ancestor);
+        self.sync_old_blocks().await
+    }
+
     pub async fn cleanup_last_blocks(
         &self,
         blocks: i64,
@@ -183,17 +374,63 @@ impl ETLWorker {
         Ok(())
     }
 
-    async fn fetch_and_process_block(
-        &self,
-        block_number: i64,
-    ) -> Result<
-        (Block, Vec<Transaction>, HashMap<String, Vec<TokenTransfer>>),
-        Pin<Box<dyn Error + Sync + Send>>,
-    > {
-        let (new_block, mut new_txs) = self
+    /// Fetches the block with transactions and extracts token transfers, receipts, logs, traces and balance changes.
+    /// `head` is the latest known block number which is used to decide if the block is matured.
+    async fn fetch_and_process_block(&self, block_number: i64, head: i64) -> ProcessResult {
+        let (mut new_block, mut new_txs) = self
             .provider_get_block_with_transactions(BlockNumberOrTag::Number(block_number as u64))
             .await?;
-        let new_token_transfers = self.extract_token_transfers(new_txs.clone()).await?;
+        if head - new_block.number >= self.config.confirmation_depth {
+            new_block.matured = 1;
+        }
+        let receipts = if self.config.modules.contains(&"receipts".to_string())
+            || self.config.modules.contains(&"logs".to_string())
+            || self.config.modules.contains(&"balance_changes".to_string())
+            || self.config.modules.contains(&"contracts".to_string())
+            || self.config.auto_discover_tokens
+            || !self.abi_contracts.is_empty()
+            || self
+                .processors()
+                .iter()
+                .any(|sc| sc.get_mode() != ExtractionMode::Calldata)
+        {
+            self.fetch_receipts(&new_txs).await?
+        } else {
+            HashMap::new()
+        };
+        let mut new_traces = if self.config.modules.contains(&"traces".to_string()) {
+            self.fetch_traces(&new_block, &new_txs).await?
+        } else {
+            vec![]
+        };
+        let mut new_contracts = if self.config.modules.contains(&"contracts".to_string())
+            || self.config.auto_discover_tokens
+        {
+            self.fetch_contracts(&new_block, &new_txs, &receipts, &new_traces)
+                .await?
+        } else {
+            vec![]
+        };
+        // new tokens are registered before the extraction, so their transfers in this block are not missed
+        if self.config.auto_discover_tokens {
+            self.discover_tokens(&new_block, &new_txs, &receipts, &new_contracts)
+                .await?;
+            if !self.config.modules.contains(&"contracts".to_string()) {
+                new_contracts.clear();
+            }
+        }
+        let mut new_decode_errors = vec![];
+        let new_token_transfers = self
+            .extract_token_transfers(new_txs.clone(), &receipts, &mut new_decode_errors)
+            .await?;
+        let mut new_decoded =
+            self.decode_contracts(&new_block, &new_txs, &receipts, &mut new_decode_errors);
+        let mut new_balance_changes =
+            if self.config.modules.contains(&"balance_changes".to_string()) {
+                self.calculate_balance_changes(&new_block, &new_txs, &receipts, &new_traces)?
+            } else {
+                vec![]
+            };
 
         // apply filters
         if !self.config.address_filter.is_empty() {
@@ -201,28 +438,519 @@ impl ETLWorker {
                 self.config.address_filter.contains(&tx.from)
                     || self.config.address_filter.contains(&tx.to)
             });
+            new_traces.retain(|trace| new_txs.iter().any(|tx| tx.hash == trace.tx_hash));
+            new_balance_changes
+                .retain(|change| self.config.address_filter.contains(&change.address));
+            new_contracts.retain(|contract| new_txs.iter().any(|tx| tx.hash == contract.tx_hash));
+            for rows in new_decoded.values_mut() {
+                rows.retain(|row| new_txs.iter().any(|tx| tx.hash == row.tx_hash));
+            }
+            new_decode_errors.retain(|failure| new_txs.iter().any(|tx| tx.hash == failure.tx_hash));
+        }
+
+        let mut batch = Batch {
+            blocks: vec![new_block],
+            ..Default::default()
+        };
+        for tx in &new_txs {
+            if let Some(receipt) = receipts.get(&tx.hash) {
+                batch
+                    .logs
+                    .extend(receipt.inner.logs().iter().map(Log::from));
+                batch.receipts.push(Receipt::from(receipt));
+            }
+        }
+        batch.transactions = new_txs;
+        batch.token_transfers = new_token_transfers;
+        batch.traces = new_traces;
+        batch.balance_changes = new_balance_changes;
+        batch.contracts = new_contracts;
+        batch.decoded = new_decoded;
+        batch.decode_errors = new_decode_errors;
+
+        Ok(batch)
+    }
+
+    /// Traces the block and flattens call trees of its transactions.
+    /// Traces are returned in the order of transactions, so the hash is taken from the transaction if node does not return it.
+    /// Returns an error if the node does not trace every transaction, as the traces could not be assigned to them
+    async fn fetch_traces(
+        &self,
+        block: &Block,
+        transactions: &[Transaction],
+    ) -> Result<Vec<Trace>, Pin<Box<dyn Error + Send + Sync>>> {
+        let results = self
+            .provider
+            .get_block_traces(BlockNumberOrTag::Number(block.number as u64))
+            .await?;
+        if results.len() != transactions.len() {
+            return Err(Box::pin(ETLError::TraceCountMismatch(
+                block.number,
+                results.len(),
+                transactions.len(),
+            )));
+        }
+        let mut traces = vec![];
+        for (tx, result) in transactions.iter().zip(results) {
+            let tx_hash = result
+                .tx_hash
+                .map(|hash| hash.trim_start_matches("0x").to_lowercase())
+                .unwrap_or(tx.hash.clone());
+            traces.extend(Trace::from_call_frame(
+                block.number,
+                &block.hash,
+                &tx_hash,
+                &result.result,
+            ));
+        }
+        Ok(traces)
+    }
+
+    /// Calculates native coin balance changes in the block from transaction values, fees paid to the miner and the block reward.
+    /// Values of successful internal calls are included if traces module is enabled
+    fn calculate_balance_changes(
+        &self,
+        block: &Block,
+        transactions: &[Transaction],
+        receipts: &HashMap<String, TransactionReceipt>,
+        traces: &[Trace],
+    ) -> Result<Vec<BalanceChange>, Pin<Box<dyn Error + Send + Sync>>> {
+        let parse = |value: &str| {
+            U256::from_str_radix(value, 10)
+                .map_err(|e| Box::pin(e) as Pin<Box<dyn Error + Send + Sync>>)
+        };
+        let mut deltas: HashMap<String, BalanceDelta> = HashMap::new();
+        // rewards of uncles and their nephews are not known from the block, so they are not included
+        let block_reward = U256::from(self.config.block_reward.unwrap_or_default());
+        deltas
+            .entry(block.miner.clone())
+            .or_default()
+            .credit(block_reward);
+
+        for tx in transactions {
+            let receipt = receipts.get(&tx.hash).ok_or_else(|| {
+                Box::pin(ETLError::MissingReceipt(tx.hash.clone())) as Pin<Box<_>>
+            })?;
+            let fee = U256::from(receipt.energy_used)
+                .saturating_mul(U256::from(receipt.effective_energy_price));
+            deltas.entry(tx.from.clone()).or_default().debit(fee);
+            deltas.entry(block.miner.clone()).or_default().credit(fee);
+
+            // value is not transferred if the transaction failed
+            if receipt.status() {
+                let value = parse(&tx.value)?;
+                let to = if tx.to.is_empty() {
+                    receipt
+                        .contract_address
+                        .map(|address| IcanAddress::from(address).to_string())
+                        .unwrap_or_default()
+                } else {
+                    tx.to.clone()
+                };
+                deltas.entry(tx.from.clone()).or_default().debit(value);
+                deltas.entry(to).or_default().credit(value);
+            }
+        }
+
+        // calls inside of a failed call are reverted as well
+        let failed_calls: Vec<(&String, Vec<usize>)> = traces
+            .iter()
+            .filter(|trace| !trace.error.is_empty())
+            .map(|trace| (&trace.tx_hash, trace.call_path()))
+            .collect();
+        for trace in traces {
+            // top level call value is already included from the transaction
+            // delegatecall and callcode do not move the value to another address
+            if trace.depth == 0
+                || trace.call_type == "delegatecall"
+                || trace.call_type == "callcode"
+            {
+                continue;
+            }
+            let call_path = trace.call_path();
+            if failed_calls.iter().any(|(tx_hash, failed_path)| {
+                **tx_hash == trace.tx_hash && call_path.starts_with(failed_path)
+            }) {
+                continue;
+            }
+            let value = parse(&trace.value)?;
+            if value.is_zero() {
+                continue;
+            }
+            deltas.entry(trace.from.clone()).or_default().debit(value);
+            deltas.entry(trace.to.clone()).or_default().credit(value);
+        }
+
+        let mut balance_changes: Vec<BalanceChange> = deltas
+            .into_iter()
+            .filter(|(address, _)| !address.is_empty())
+            .filter_map(|(address, delta)| {
+                Some(BalanceChange {
+                    block_number: block.number,
+                    block_hash: block.hash.clone(),
+                    address,
+                    delta: delta.to_decimal()?,
+                })
+            })
+            .collect();
+        balance_changes.sort_by(|a, b| a.address.cmp(&b.address));
+        Ok(balance_changes)
+    }
+
+    /// Finds contracts deployed in the block. Contracts are created by transactions without recipient
+    /// and by successful create calls if traces module is enabled
+    async fn fetch_contracts(
+        &self,
+        block: &Block,
+        transactions: &[Transaction],
+        receipts: &HashMap<String, TransactionReceipt>,
+        traces: &[Trace],
+    ) -> Result<Vec<Contract>, Pin<Box<dyn Error + Send + Sync>>> {
+        // (address, creator, tx_hash)
+        let mut deployments: Vec<(String, String, String)> = vec![];
+        for tx in transactions.iter().filter(|tx| tx.to.is_empty()) {
+            let receipt = receipts.get(&tx.hash).ok_or_else(|| {
+                Box::pin(ETLError::MissingReceipt(tx.hash.clone())) as Pin<Box<_>>
+            })?;
+            if let (true, Some(address)) = (receipt.status(), receipt.contract_address) {
+                deployments.push((
+                    IcanAddress::from(address).to_string(),
+                    tx.from.clone(),
+                    tx.hash.clone(),
+                ));
+            }
+        }
+        let failed_calls: Vec<(&String, Vec<usize>)> = traces
+            .iter()
+            .filter(|trace| !trace.error.is_empty())
+            .map(|trace| (&trace.tx_hash, trace.call_path()))
+            .collect();
+        for trace in traces {
+            // top level create is already included from the transaction
+            if trace.depth == 0 || !trace.call_type.starts_with("create") {
+                continue;
+            }
+            let call_path = trace.call_path();
+            if failed_calls.iter().any(|(tx_hash, failed_path)| {
+                **tx_hash == trace.tx_hash && call_path.starts_with(failed_path)
+            }) {
+                continue;
+            }
+            deployments.push((trace.to.clone(), trace.from.clone(), trace.tx_hash.clone()));
+        }
+
+        let mut contracts = vec![];
+        for (address, creator, tx_hash) in deployments {
+            let block_tag = BlockNumberOrTag::Number(block.number as u64);
+            // the contract is still recorded if its code can not be read, only without the bytecode hash
+            let bytecode_hash = match self.provider.get_code(address.clone(), block_tag).await {
+                Ok(code) => match hex::decode(&code) {
+                    Ok(bytecode) => format!("{:x}", Sha3_256::digest(&bytecode)),
+                    Err(e) => {
+                        warn!("Invalid bytecode of contract {}: {}", address, e);
+                        String::new()
+                    }
+                },
+                Err(e) => {
+                    warn!("Failed to get bytecode of contract {}: {:?}", address, e);
+                    String::new()
+                }
+            };
+            let probe = self.probe_cbc20(&address, block.number).await;
+            let (name, symbol, decimals) = probe.clone().unwrap_or_default();
+            contracts.push(Contract {
+                address,
+                creator,
+                tx_hash,
+                block_number: block.number,
+                block_hash: block.hash.clone(),
+                bytecode_hash,
+                is_cbc20: if probe.is_some() { 1 } else { 0 },
+                name,
+                symbol,
+                decimals,
+            });
+        }
+        Ok(contracts)
+    }
+
+    /// Calls CBC20 view functions of the contract. Returns its name, symbol and decimals if all of them succeed
+    async fn probe_cbc20(&self, address: &str, block_number: i64) -> Option<(String, String, i64)> {
+        let block_tag = BlockNumberOrTag::Number(block_number as u64);
+        let mut outputs = join_all(
+            [
+                cbc20::probe::NAME_SELECTOR,
+                cbc20::probe::SYMBOL_SELECTOR,
+                cbc20::probe::DECIMALS_SELECTOR,
+                cbc20::probe::TOTAL_SUPPLY_SELECTOR,
+            ]
+            .iter()
+            .map(|selector| {
+                self.provider
+                    .call(address.to_string(), selector.to_string(), block_tag)
+            }),
+        )
+        .await
+        .into_iter();
+        let (Some(Ok(name)), Some(Ok(symbol)), Some(Ok(decimals)), Some(Ok(total_supply))) = (
+            outputs.next(),
+            outputs.next(),
+            outputs.next(),
+            outputs.next(),
+        ) else {
+            return None;
+        };
+        if total_supply.len() < 64 {
+            return None;
+        }
+        Some((
+            cbc20::probe::decode_string(&name)?,
+            cbc20::probe::decode_string(&symbol)?,
+            cbc20::probe::decode_uint(&decimals)? as i64,
+        ))
+    }
+
+    /// Registers CBC20 processors for the tokens which are not watched yet.
+    /// Candidates are deployed contracts flagged as CBC20 and addresses which were called with
+    /// CBC20 transfer functions or emitted Transfer events. The latter are probed once per address
+    async fn discover_tokens(
+        &self,
+        block: &Block,
+        transactions: &[Transaction],
+        receipts: &HashMap<String, TransactionReceipt>,
+        contracts: &[Contract],
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        let mut tokens: Vec<String> = contracts
+            .iter()
+            .filter(|contract| contract.is_cbc20 == 1)
+            .map(|contract| contract.address.clone())
+            .collect();
+
+        let mut candidates: HashSet<String> = HashSet::new();
+        for tx in transactions {
+            if !tx.to.is_empty()
+                && cbc20::Cbc20::new(tx.to.clone(), ExtractionMode::Calldata)
+                    .check_if_call(tx.input.clone())
+            {
+                candidates.insert(tx.to.clone());
+            }
+            if let Some(receipt) = receipts.get(&tx.hash) {
+                for log in receipt.inner.logs().iter().map(Log::from) {
+                    let topics: Vec<String> = [log.topic0, log.topic1, log.topic2, log.topic3]
+                        .into_iter()
+                        .flatten()
+                        .collect();
+                    if cbc20::Cbc20::new(log.address.clone(), ExtractionMode::Event)
+                        .check_if_event(&topics)
+                    {
+                        candidates.insert(log.address);
+                    }
+                }
+            }
+        }
+        {
+            let checked_addresses = self.checked_addresses.lock().unwrap();
+            candidates.retain(|address| {
+                !checked_addresses.contains(address) && !tokens.contains(address)
+            });
+        }
+        for address in candidates {
+            if self.probe_cbc20(&address, block.number).await.is_some() {
+                tokens.push(address);
+            } else {
+                self.checked_addresses.lock().unwrap().insert(address);
+            }
         }
 
-        Ok((new_block, new_txs, new_token_transfers))
+        for address in tokens {
+            if self.checked_addresses.lock().unwrap().contains(&address) {
+                continue;
+            }
+            self.register_token(cbc20::CBC20_NAME, &address, block.number)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Creates tables of the discovered token and starts extracting its transfers
+    async fn register_token(
+        &self,
+        contract_name: &str,
+        contract_address: &str,
+        block_number: i64,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        // tables are named by a part of the address, so a table of another token is never reused
+        let table_name = self
+            .select_sc_processor(contract_name, contract_address)
+            .get_table_name();
+        let owner = self
+            .smart_contracts_processors
+            .read()
+            .unwrap()
+            .iter()
+            .find(|sc| sc.get_table_name() == table_name && sc.get_address() != contract_address)
+            .map(|sc| sc.get_address());
+        if let Some(owner) = owner {
+            warn!(
+                "Table {} of token {} already has transfers of token {}, the token is skipped",
+                table_name, contract_address, owner
+            );
+            self.checked_addresses
+                .lock()
+                .unwrap()
+                .insert(contract_address.to_string());
+            return Ok(());
+        }
+
+        let tokens = HashMap::from([(
+            contract_name.to_string(),
+            HashSet::from([contract_address.to_string()]),
+        )]);
+        self.storage.create_token_transfers_tables(tokens).await?;
+        self.storage
+            .add_discovered_token(
+                contract_name.to_string(),
+                contract_address.to_string(),
+                block_number,
+            )
+            .await?;
+
+        let mut processors = self.smart_contracts_processors.write().unwrap();
+        if processors
+            .iter()
+            .all(|sc| sc.get_address() != contract_address)
+        {
+            processors.push(self.select_sc_processor(contract_name, contract_address));
+            info!(
+                "Discovered {} token {} in block {}",
+                contract_name, contract_address, block_number
+            );
+        }
+        self.checked_addresses
+            .lock()
+            .unwrap()
+            .insert(contract_address.to_string());
+        Ok(())
+    }
+
+    /// Returns a snapshot of the token processors, so the lock is not held while the block is processed
+    fn processors(&self) -> Vec<Box<dyn SmartContract>> {
+        self.smart_contracts_processors.read().unwrap().clone()
+    }
+
+    /// Decodes calls and events of the watched contracts with their ABI. Returns them by table name.
+    /// Calls and events which can not be decoded are added to `decode_errors`
+    fn decode_contracts(
+        &self,
+        block: &Block,
+        transactions: &[Transaction],
+        receipts: &HashMap<String, TransactionReceipt>,
+        decode_errors: &mut Vec<DecodeFailure>,
+    ) -> HashMap<String, Vec<DecodedRow>> {
+        let mut decoded: HashMap<String, Vec<DecodedRow>> = HashMap::new();
+        for tx in transactions {
+            let receipt = receipts.get(&tx.hash);
+            for contract in &self.abi_contracts {
+                let mut rows = vec![];
+                if tx.to == contract.address {
+                    if let Some((method, values)) = contract.decode_call(&tx.input) {
+                        let status = receipt.map(|receipt| receipt.status()).unwrap_or(false);
+                        rows.push((
+                            method,
+                            values,
+                            0,
+                            if status { 1 } else { 0 },
+                            vec![],
+                            &tx.input,
+                        ));
+                    }
+                }
+                let logs: Vec<Log> = receipt
+                    .iter()
+                    .flat_map(|receipt| receipt.inner.logs().iter().map(Log::from))
+                    .filter(|log| log.address == contract.address)
+                    .collect();
+                for log in &logs {
+                    let topics: Vec<String> = [&log.topic0, &log.topic1, &log.topic2, &log.topic3]
+                        .into_iter()
+                        .flatten()
+                        .cloned()
+                        .collect();
+                    if let Some((method, values)) = contract.decode_log(&topics, &log.data) {
+                        // events are emitted only by successful transactions
+                        rows.push((method, values, log.log_index, 1, topics, &log.data));
+                    }
+                }
+                for (method, values, log_index, status, topics, data) in rows {
+                    let values = match values {
+                        Ok(values) => values,
+                        Err(e) => {
+                            warn!(
+                                "Failed to decode {} of {} in transaction {}: {}",
+                                method.signature, contract.name, tx.hash, e
+                            );
+                            decode_errors.push(Self::decode_failure(
+                                tx,
+                                contract.address.clone(),
+                                log_index,
+                                &topics,
+                                data.clone(),
+                                e.to_string(),
+                            ));
+                            continue;
+                        }
+                    };
+                    decoded
+                        .entry(method.table_name.clone())
+                        .or_default()
+                        .push(DecodedRow {
+                            block_number: block.number,
+                            block_hash: block.hash.clone(),
+                            tx_hash: tx.hash.clone(),
+                            address: contract.address.clone(),
+                            log_index,
+                            status,
+                            args: method
+                                .columns
+                                .iter()
+                                .map(|(name, _)| name.clone())
+                                .zip(values)
+                                .collect(),
+                        });
+                }
+            }
+        }
+        decoded
+    }
+
+    /// Fetches receipts of the given transactions concurrently. Returns them by transaction hash
+    async fn fetch_receipts(
+        &self,
+        transactions: &[Transaction],
+    ) -> Result<HashMap<String, TransactionReceipt>, Pin<Box<dyn Error + Send + Sync>>> {
+        let receipts = join_all(
+            transactions
+                .iter()
+                .map(|tx| self.provider.get_transaction_receipt(tx.hash.clone())),
+        )
+        .await;
+        transactions
+            .iter()
+            .zip(receipts)
+            .map(|(tx, receipt)| receipt.map(|receipt| (tx.hash.clone(), receipt)))
+            .collect()
     }
 
     async fn process_results(
         &self,
-        blocks: &mut Vec<Block>,
-        transactions: &mut Vec<Transaction>,
-        token_transfers: &mut HashMap<String, Vec<TokenTransfer>>,
+        batch: &mut Batch,
         results: Vec<ProcessResult>,
     ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
         for res in results {
             match res {
-                Ok((block, txs, token_transfers_batch)) => {
-                    blocks.push(block);
-                    transactions.extend(txs);
-                    for (key, values) in token_transfers_batch {
-                        token_transfers.entry(key).or_default().extend(values);
-                    }
-                    self.safe_insert(false, blocks, transactions, token_transfers)
-                        .await?;
+                Ok(new_batch) => {
+                    batch.extend(new_batch);
+                    self.safe_insert(false, batch).await?;
                 }
                 Err(e) => return Err(e),
             }
@@ -233,64 +961,76 @@ impl ETLWorker {
     async fn safe_insert(
         &self,
         insert_all: bool,
-        blocks: &mut Vec<Block>,
-        transactions: &mut Vec<Transaction>,
-        token_transfers: &mut HashMap<String, Vec<TokenTransfer>>,
+        batch: &mut Batch,
     ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
         self.storage
-            .insert_blocks_with_txs_and_token_transfers(
-                insert_all,
-                blocks,
-                transactions,
-                token_transfers,
-            )
+            .insert_blocks_with_txs_and_token_transfers(insert_all, batch)
             .await?;
         Ok(())
     }
 
     async fn sync_old_blocks(&mut self) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
         Box::pin(async move {
-            self.update_blocks_to_matured(self.last_saved_block - 10001, self.last_saved_block - 5)
-                .await?;
-
             let latest_provider_block = self.provider_get_block(BlockNumberOrTag::Latest).await?;
+            let head = latest_provider_block.number;
+            self.mature_blocks(head).await?;
+
+            // in finalized-only mode blocks are synced only up to the confirmation depth
+            let latest_block_number = if self.config.finalized_only {
+                head - self.config.confirmation_depth
+            } else {
+                head
+            };
 
             // already synced
-            if self.last_saved_block == latest_provider_block.number
+            if self.last_saved_block == latest_block_number
             // checked all blocks but the last one do not have data which needs to be stored
-                && self.last_checked_block == latest_provider_block.number
+                && self.last_checked_block == latest_block_number
             {
                 return Ok(());
             }
 
             let mut block_to_load = self.last_saved_block + 1;
 
-            if block_to_load > latest_provider_block.number {
+            if block_to_load > latest_block_number {
+                if self.config.finalized_only {
+                    // no new blocks reached the confirmation depth yet
+                    return Ok(());
+                }
                 return Err(Box::pin(ETLError::ChainIsNotSyncedOnProvider) as _);
             }
 
+            // long ranges of historical blocks are loaded in bulk, which is switched off at the chain head
+            let bulk_load = self.config.bulk_load
+                && latest_block_number - block_to_load >= BULK_LOAD_MIN_BLOCKS;
+            if bulk_load {
+                info!("Loading blocks in bulk mode");
+                self.storage.set_bulk_mode(true).await?;
+            }
+
             let mut log_counter = block_to_load;
 
             info!(
                 "Syncing stale blocks from {} to {}",
-                block_to_load, latest_provider_block.number
+                block_to_load, latest_block_number
             );
 
-            let mut blocks: Vec<Block> = Vec::new();
-            let mut transactions: Vec<Transaction> = Vec::new();
-            let mut token_transfers: HashMap<String, Vec<TokenTransfer>> = HashMap::new();
+            let mut batch = Batch::default();
 
             'outer: loop {
                 let mut tasks: Vec<JoinHandle<Result<_, _>>> = vec![];
+                let mut round = vec![];
+                let watched_tokens = self.processors().len();
 
                 for _ in 0..self.config.threads {
                     let clone: ETLWorker = self.clone();
                     let block_number = block_to_load;
+                    round.push(block_number);
                     tasks.push(spawn(async move {
-                        clone.fetch_and_process_block(block_number).await
+                        clone.fetch_and_process_block(block_number, head).await
                     }));
 
-                    if latest_provider_block.number == block_to_load {
+                    if latest_block_number == block_to_load {
                         break;
                     }
 
@@ -302,30 +1042,30 @@ impl ETLWorker {
                     }
                 }
 
-                let results = join_all(tasks)
+                let mut results = join_all(tasks)
                     .await
                     .into_iter()
                     .collect::<Result<Vec<_>, _>>()
                     .map_err(Box::from)?;
-                self.process_results(
-                    &mut blocks,
-                    &mut transactions,
-                    &mut token_transfers,
-                    results,
-                )
-                .await?;
-
-                if log_counter % 10000 == 0 {
-                    self.update_blocks_to_matured(log_counter - 10001, log_counter)
-                        .await?;
+                // blocks of the round are extracted concurrently, so tokens discovered in one of them may be
+                // missed by the later ones. The round is processed again in block order to extract their transfers
+                if self.processors().len() != watched_tokens {
+                    results = vec![];
+                    for block_number in round {
+                        results.push(self.fetch_and_process_block(block_number, head).await);
+                    }
                 }
+                self.process_results(&mut batch, results).await?;
 
-                if latest_provider_block.number == block_to_load {
-                    self.safe_insert(true, &mut blocks, &mut transactions, &mut token_transfers)
-                        .await?;
-                    info!("DB is synced on block {}", latest_provider_block.number);
-                    self.last_checked_block = latest_provider_block.number;
-                    self.last_saved_block = latest_provider_block.number;
+                if latest_block_number == block_to_load {
+                    self.safe_insert(true, &mut batch).await?;
+                    if bulk_load {
+                        info!("Bulk load is finished, building indexes");
+                        self.storage.set_bulk_mode(false).await?;
+                    }
+                    info!("DB is synced on block {}", latest_block_number);
+                    self.last_checked_block = latest_block_number;
+                    self.last_saved_block = latest_block_number;
                     self.sync_old_blocks().await?;
                     break 'outer;
                 }
@@ -335,6 +1075,19 @@ impl ETLWorker {
         .await
     }
 
+    /// Marks stored blocks which reached `confirmation_depth` blocks on top of them since the last call as matured.
+    /// All stored blocks are checked on the first call
+    async fn mature_blocks(&mut self, head: i64) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        let matured_block = head - self.config.confirmation_depth;
+        if matured_block <= self.last_matured_block {
+            return Ok(());
+        }
+        self.update_blocks_to_matured(self.last_matured_block + 1, matured_block)
+            .await?;
+        self.last_matured_block = matured_block;
+        Ok(())
+    }
+
     pub async fn update_blocks_to_matured(
         &mut self,
         from: i64,
@@ -343,34 +1096,108 @@ impl ETLWorker {
         self.storage.update_blocks_to_matured(from, to).await
     }
 
+    /// Extracts token transfers of the watched tokens from calldata and/or events depending on the token mode.
+    /// `receipts` must contain receipts of all transactions if any token uses events.
+    /// Calls and events which can not be decoded are added to `decode_errors`
     async fn extract_token_transfers(
         &self,
         transactions: Vec<Transaction>,
+        receipts: &HashMap<String, TransactionReceipt>,
+        decode_errors: &mut Vec<DecodeFailure>,
     ) -> Result<HashMap<String, Vec<TokenTransfer>>, Pin<Box<dyn Error + Send + Sync>>> {
+        let processors = self.processors();
         let mut transfers = HashMap::new();
         for tx in transactions {
-            for sc in &self.smart_contracts_processors {
-                if tx.to != sc.get_address() || !sc.check_if_call(tx.clone().input) {
-                    continue;
+            for sc in &processors {
+                let mut processor_token_transfers: Vec<TokenTransfer> = vec![];
+                if sc.get_mode() != ExtractionMode::Calldata {
+                    if let Some(receipt) = receipts.get(&tx.hash) {
+                        for log in receipt.inner.logs().iter().map(Log::from) {
+                            if log.address != sc.get_address() {
+                                continue;
+                            }
+                            let topics: Vec<String> =
+                                [log.topic0, log.topic1, log.topic2, log.topic3]
+                                    .into_iter()
+                                    .flatten()
+                                    .collect();
+                            if !sc.check_if_event(&topics) {
+                                continue;
+                            }
+                            let calls = match sc.extract_event_data(&topics, log.data.clone()) {
+                                Ok(calls) => calls,
+                                Err(e) => {
+                                    warn!(
+                                        "Failed to decode transfer event of {} in transaction {}: {}",
+                                        sc.get_address(),
+                                        tx.hash,
+                                        e
+                                    );
+                                    decode_errors.push(Self::decode_failure(
+                                        &tx,
+                                        sc.get_address(),
+                                        log.log_index,
+                                        &topics,
+                                        log.data,
+                                        e.to_string(),
+                                    ));
+                                    continue;
+                                }
+                            };
+                            // events are emitted only by successful transactions
+                            processor_token_transfers.extend(calls.iter().map(|call| {
+                                Self::token_transfer(call, &tx, sc.get_address(), log.log_index, 1)
+                            }));
+                        }
+                    }
+                }
+
+                // in both mode calldata is used only for the calls which emitted no events, e.g. failed ones
+                let use_calldata = match sc.get_mode() {
+                    ExtractionMode::Calldata => true,
+                    ExtractionMode::Event => false,
+                    ExtractionMode::Both => processor_token_transfers.is_empty(),
+                };
+                if use_calldata && tx.to == sc.get_address() && sc.check_if_call(tx.clone().input) {
+                    let transfer_data =
+                        match sc.extract_call_data(tx.clone().from, tx.clone().input) {
+                            Ok(calls) => calls,
+                            Err(e) => {
+                                warn!(
+                                    "Failed to decode transfer call of {} in transaction {}: {}",
+                                    sc.get_address(),
+                                    tx.hash,
+                                    e
+                                );
+                                decode_errors.push(Self::decode_failure(
+                                    &tx,
+                                    sc.get_address(),
+                                    0,
+                                    &[],
+                                    tx.input.clone(),
+                                    e.to_string(),
+                                ));
+                                continue;
+                            }
+                        };
+                    let status = match receipts.get(&tx.hash) {
+                        Some(receipt) => receipt.status(),
+                        None => self
+                            .provider
+                            .get_transaction_receipt(tx.clone().hash)
+                            .await?
+                            .status(),
+                    };
+                    processor_token_transfers.extend(transfer_data.iter().map(|call| {
+                        Self::token_transfer(
+                            call,
+                            &tx,
+                            sc.get_address(),
+                            call.index as i64,
+                            if status { 1 } else { 0 },
+                        )
+                    }));
                 }
-                let transfer_data = sc.extract_call_data(tx.clone().from, tx.clone().input);
-                let receipt = self
-                    .provider
-                    .get_transaction_receipt(tx.clone().hash)
-                    .await?;
-                let processor_token_transfers: Vec<TokenTransfer> = transfer_data
-                    .into_iter()
-                    .map(|(index, from, to, value)| TokenTransfer {
-                        block_number: tx.block_number,
-                        from,
-                        to,
-                        value,
-                        tx_hash: tx.hash.clone(),
-                        address: sc.get_address(),
-                        index: index as i64,
-                        status: if receipt.status() { 1 } else { 0 },
-                    })
-                    .collect();
                 if !processor_token_transfers.is_empty() {
                     transfers
                         .entry(sc.get_table_name())
@@ -399,14 +1226,114 @@ impl ETLWorker {
         Ok(res.unwrap())
     }
 
+    /// Builds the record of the call or event which could not be decoded
+    fn decode_failure(
+        tx: &Transaction,
+        address: String,
+        log_index: i64,
+        topics: &[String],
+        data: String,
+        error: String,
+    ) -> DecodeFailure {
+        DecodeFailure {
+            block_number: tx.block_number,
+            block_hash: tx.block_hash.clone(),
+            tx_hash: tx.hash.clone(),
+            address,
+            log_index,
+            topics: topics.join(","),
+            data,
+            error,
+        }
+    }
+
+    /// Converts the decoded transfer into the token transfer. Values and token IDs are stored as decimal strings.
+    /// NFT transfers have no value, they move the single token of their token ID.
+    /// `index` is the log index of events and the position in the batch of calls
+    fn token_transfer(
+        call: &DecodedCall,
+        tx: &Transaction,
+        address: String,
+        index: i64,
+        status: i64,
+    ) -> TokenTransfer {
+        let nft_id = call.uint("tokenId");
+        TokenTransfer {
+            block_number: tx.block_number,
+            from: call.address("from").unwrap_or_default().to_string(),
+            to: call.address("to").unwrap_or_default().to_string(),
+            value: match call.uint("value") {
+                Some(value) => value.to_string(),
+                None if nft_id.is_some() => "1".to_string(),
+                None => "".to_string(),
+            },
+            tx_hash: tx.hash.clone(),
+            address,
+            index,
+            token_id: call
+                .uint("id")
+                .or(nft_id)
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            batch_index: call.index as i64,
+            status,
+        }
+    }
+
+    fn extraction_mode(&self, contract_address: &str) -> ExtractionMode {
+        self.config
+            .token_modes
+            .get(contract_address)
+            .copied()
+            .unwrap_or(ExtractionMode::Calldata)
+    }
+
     fn select_sc_processor(
         &self,
         contract_name: &str,
         contract_address: &str,
     ) -> Box<dyn SmartContract> {
         match contract_name {
-            cbc20::CBC20_NAME => Box::new(cbc20::Cbc20::new(contract_address.to_string())),
+            cbc20::CBC20_NAME => Box::new(cbc20::Cbc20::new(
+                contract_address.to_string(),
+                self.extraction_mode(contract_address),
+            )),
+            cbc721::CBC721_NAME => Box::new(cbc721::Cbc721::new(
+                contract_address.to_string(),
+                self.extraction_mode(contract_address),
+            )),
+            cbc1155::CBC1155_NAME => Box::new(cbc1155::Cbc1155::new(
+                contract_address.to_string(),
+                self.extraction_mode(contract_address),
+            )),
             _ => panic!("Unknown contract name"),
         }
     }
 }
+
+/// Native coin balance change of an address. Credits and debits are summed apart,
+/// as the values are unsigned 256-bit numbers, and netted once the block is processed
+#[derive(Default)]
+struct BalanceDelta {
+    credit: U256,
+    debit: U256,
+}
+
+impl BalanceDelta {
+    fn credit(&mut self, value: U256) {
+        self.credit = self.credit.saturating_add(value);
+    }
+
+    fn debit(&mut self, value: U256) {
+        self.debit = self.debit.saturating_add(value);
+    }
+
+    /// Signed decimal change of the balance, None if the balance did not change
+    fn to_decimal(&self) -> Option<String> {
+        match self.credit.cmp(&self.debit) {
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some((self.credit - self.debit).to_string()),
+            std::cmp::Ordering::Less => Some(format!("-{}", self.debit - self.credit)),
+        }
+    }
+}
@@ -10,7 +10,7 @@ use std::{
 };
 use tokio::time::{sleep, Duration};
 use tracing::info;
-use types::{Block, Transaction};
+use types::{Block, TraceResult, Transaction};
 
 use crate::error::ProviderError; // Add this line to import the Send and Sync traits
 
@@ -88,6 +88,61 @@ impl Provider {
         }
     }
 
+    /// Traces all transactions of the block with the callTracer.
+    /// Requires the debug API to be enabled on the node
+    pub async fn get_block_traces(
+        &self,
+        query: BlockNumberOrTag,
+    ) -> Result<Vec<TraceResult>, Pin<Box<dyn Error + Send + Sync>>> {
+        let traces = self
+            .root
+            .client()
+            .request(
+                "debug_traceBlockByNumber",
+                (query, serde_json::json!({ "tracer": "callTracer" })),
+            )
+            .await
+            .map_err(|e| Box::pin(e) as Pin<Box<dyn Error + Send + Sync>>)?;
+        Ok(traces)
+    }
+
+    /// Returns hex encoded bytecode of the contract at the block
+    pub async fn get_code(
+        &self,
+        address: String,
+        block: BlockNumberOrTag,
+    ) -> Result<String, Pin<Box<dyn Error + Send + Sync>>> {
+        let code: String = self
+            .root
+            .client()
+            .request("xcb_getCode", (address, block))
+            .await
+            .map_err(|e| Box::pin(e) as Pin<Box<dyn Error + Send + Sync>>)?;
+        Ok(code.trim_start_matches("0x").to_string())
+    }
+
+    /// Executes a call of the contract without creating a transaction and returns hex encoded output
+    pub async fn call(
+        &self,
+        to: String,
+        data: String,
+        block: BlockNumberOrTag,
+    ) -> Result<String, Pin<Box<dyn Error + Send + Sync>>> {
+        let output: String = self
+            .root
+            .client()
+            .request(
+                "xcb_call",
+                (
+                    serde_json::json!({ "to": to, "data": format!("0x{}", data) }),
+                    block,
+                ),
+            )
+            .await
+            .map_err(|e| Box::pin(e) as Pin<Box<dyn Error + Send + Sync>>)?;
+        Ok(output.trim_start_matches("0x").to_string())
+    }
+
     pub async fn get_network_id(&self) -> Result<u64, Pin<Box<dyn Error + Send + Sync>>> {
         let network_id = self.root.get_chain_id().await.unwrap();
         Ok(network_id)
@@ -0,0 +1,20 @@
+use std::{error::Error, pin::Pin};
+
+use thiserror::Error as ThisError;
+
+#[derive(ThisError, Debug)]
+pub enum ClickhouseStorageError {
+    #[error(transparent)]
+    DatabaseError(#[from] clickhouse::error::Error),
+    #[error("invalid ClickHouse DSN: {0}")]
+    InvalidDsn(#[from] url::ParseError),
+    /// Rows of the batch are stored with their block, so the block must be in the batch
+    #[error("no block of {0} in the batch")]
+    MissingBlock(String),
+}
+
+impl From<ClickhouseStorageError> for Pin<Box<dyn Error + Send + Sync>> {
+    fn from(err: ClickhouseStorageError) -> Self {
+        Pin::from(Box::new(err))
+    }
+}
@@ -0,0 +1,4 @@
+pub mod clickhouse;
+pub use crate::clickhouse::ClickhouseStorage;
+pub mod error;
+mod rows;
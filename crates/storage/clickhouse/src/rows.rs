@@ -0,0 +1,654 @@
+use clickhouse::Row;
+use serde::{Deserialize, Serialize};
+use types::{
+    BalanceChange, Block, Contract, DecodeFailure, Log, Receipt, TokenTransfer, Trace, Transaction,
+};
+
+/// UInt256 columns are sent in RowBinary as 32 little endian bytes, values are kept as decimal strings
+pub(crate) mod uint256 {
+    use base_primitives::U256;
+    use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = if value.is_empty() {
+            U256::ZERO
+        } else {
+            U256::from_str_radix(value, 10).map_err(ser::Error::custom)?
+        };
+        value.to_le_bytes::<32>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        let bytes = <[u8; 32]>::deserialize(deserializer)?;
+        Ok(U256::from_le_bytes(bytes).to_string())
+    }
+}
+
+/// Int256 columns are sent in RowBinary as 32 little endian bytes of the two's complement
+pub(crate) mod int256 {
+    use base_primitives::U256;
+    use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        let (negative, magnitude) = match value.strip_prefix('-') {
+            Some(magnitude) => (true, magnitude),
+            None => (false, value),
+        };
+        let magnitude = U256::from_str_radix(magnitude, 10).map_err(ser::Error::custom)?;
+        let value = if negative {
+            U256::ZERO.wrapping_sub(magnitude)
+        } else {
+            magnitude
+        };
+        value.to_le_bytes::<32>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        let value = U256::from_le_bytes(<[u8; 32]>::deserialize(deserializer)?);
+        if value.bit(255) {
+            Ok(format!("-{}", U256::ZERO.wrapping_sub(value)))
+        } else {
+            Ok(value.to_string())
+        }
+    }
+}
+
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct BlockRow {
+    pub number: i64,
+    pub hash: String,
+    pub parent_hash: String,
+    pub nonce: String,
+    pub sha3_uncles: String,
+    pub logs_bloom: String,
+    pub transactions_root: String,
+    pub state_root: String,
+    pub receipts_root: String,
+    pub miner: String,
+    pub difficulty: String,
+    pub total_difficulty: String,
+    pub extra_data: String,
+    pub energy_limit: i64,
+    pub energy_used: i64,
+    pub timestamp: i64,
+    pub transaction_count: i64,
+    pub matured: i64,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl BlockRow {
+    pub fn new(block: &Block, created_at: u32, version: u64) -> Self {
+        BlockRow {
+            number: block.number,
+            hash: block.hash.clone(),
+            parent_hash: block.parent_hash.clone(),
+            nonce: block.nonce.clone(),
+            sha3_uncles: block.sha3_uncles.clone(),
+            logs_bloom: block.logs_bloom.clone(),
+            transactions_root: block.transactions_root.clone(),
+            state_root: block.state_root.clone(),
+            receipts_root: block.receipts_root.clone(),
+            miner: block.miner.clone(),
+            difficulty: block.difficulty.clone(),
+            total_difficulty: block.total_difficulty.clone(),
+            extra_data: block.extra_data.clone(),
+            energy_limit: block.energy_limit,
+            energy_used: block.energy_used,
+            timestamp: block.timestamp,
+            transaction_count: block.transaction_count,
+            matured: block.matured,
+            created_at,
+            version,
+        }
+    }
+}
+
+impl From<BlockRow> for Block {
+    fn from(row: BlockRow) -> Self {
+        Block {
+            number: row.number,
+            hash: row.hash,
+            parent_hash: row.parent_hash,
+            nonce: row.nonce,
+            sha3_uncles: row.sha3_uncles,
+            logs_bloom: row.logs_bloom,
+            transactions_root: row.transactions_root,
+            state_root: row.state_root,
+            receipts_root: row.receipts_root,
+            miner: row.miner,
+            difficulty: row.difficulty,
+            total_difficulty: row.total_difficulty,
+            extra_data: row.extra_data,
+            energy_limit: row.energy_limit,
+            energy_used: row.energy_used,
+            timestamp: row.timestamp,
+            transaction_count: row.transaction_count,
+            matured: row.matured,
+        }
+    }
+}
+
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct TransactionRow {
+    pub hash: String,
+    pub nonce: String,
+    pub block_hash: String,
+    pub block_number: i64,
+    pub transaction_index: i64,
+    pub from_addr: String,
+    pub to_addr: String,
+    #[serde(with = "uint256")]
+    pub value: String,
+    pub energy: String,
+    #[serde(with = "uint256")]
+    pub energy_price: String,
+    pub input: String,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl TransactionRow {
+    pub fn new(transaction: &Transaction, created_at: u32, version: u64) -> Self {
+        TransactionRow {
+            hash: transaction.hash.clone(),
+            nonce: transaction.nonce.clone(),
+            block_hash: transaction.block_hash.clone(),
+            block_number: transaction.block_number,
+            transaction_index: transaction.transaction_index,
+            from_addr: transaction.from.clone(),
+            to_addr: transaction.to.clone(),
+            value: transaction.value.clone(),
+            energy: transaction.energy.clone(),
+            energy_price: transaction.energy_price.clone(),
+            input: transaction.input.clone(),
+            created_at,
+            version,
+        }
+    }
+}
+
+impl From<TransactionRow> for Transaction {
+    fn from(row: TransactionRow) -> Self {
+        Transaction {
+            hash: row.hash,
+            nonce: row.nonce,
+            block_hash: row.block_hash,
+            block_number: row.block_number,
+            transaction_index: row.transaction_index,
+            from: row.from_addr,
+            to: row.to_addr,
+            value: row.value,
+            energy: row.energy,
+            energy_price: row.energy_price,
+            input: row.input,
+        }
+    }
+}
+
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct ReceiptRow {
+    pub transaction_hash: String,
+    pub block_hash: String,
+    pub block_number: i64,
+    pub transaction_index: i64,
+    pub status: i64,
+    pub energy_used: i64,
+    pub cumulative_energy_used: i64,
+    pub effective_energy_price: String,
+    pub contract_address: String,
+    pub logs_bloom: String,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl ReceiptRow {
+    pub fn new(receipt: &Receipt, created_at: u32, version: u64) -> Self {
+        ReceiptRow {
+            transaction_hash: receipt.transaction_hash.clone(),
+            block_hash: receipt.block_hash.clone(),
+            block_number: receipt.block_number,
+            transaction_index: receipt.transaction_index,
+            status: receipt.status,
+            energy_used: receipt.energy_used,
+            cumulative_energy_used: receipt.cumulative_energy_used,
+            effective_energy_price: receipt.effective_energy_price.clone(),
+            contract_address: receipt.contract_address.clone(),
+            logs_bloom: receipt.logs_bloom.clone(),
+            created_at,
+            version,
+        }
+    }
+}
+
+impl From<ReceiptRow> for Receipt {
+    fn from(row: ReceiptRow) -> Self {
+        Receipt {
+            transaction_hash: row.transaction_hash,
+            block_hash: row.block_hash,
+            block_number: row.block_number,
+            transaction_index: row.transaction_index,
+            status: row.status,
+            energy_used: row.energy_used,
+            cumulative_energy_used: row.cumulative_energy_used,
+            effective_energy_price: row.effective_energy_price,
+            contract_address: row.contract_address,
+            logs_bloom: row.logs_bloom,
+        }
+    }
+}
+
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct LogRow {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub log_index: i64,
+    pub address: String,
+    pub topic0: Option<String>,
+    pub topic1: Option<String>,
+    pub topic2: Option<String>,
+    pub topic3: Option<String>,
+    pub data: String,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl LogRow {
+    pub fn new(log: &Log, created_at: u32, version: u64) -> Self {
+        LogRow {
+            block_number: log.block_number,
+            block_hash: log.block_hash.clone(),
+            tx_hash: log.tx_hash.clone(),
+            log_index: log.log_index,
+            address: log.address.clone(),
+            topic0: log.topic0.clone(),
+            topic1: log.topic1.clone(),
+            topic2: log.topic2.clone(),
+            topic3: log.topic3.clone(),
+            data: log.data.clone(),
+            created_at,
+            version,
+        }
+    }
+}
+
+impl From<LogRow> for Log {
+    fn from(row: LogRow) -> Self {
+        Log {
+            block_number: row.block_number,
+            block_hash: row.block_hash,
+            tx_hash: row.tx_hash,
+            log_index: row.log_index,
+            address: row.address,
+            topic0: row.topic0,
+            topic1: row.topic1,
+            topic2: row.topic2,
+            topic3: row.topic3,
+            data: row.data,
+        }
+    }
+}
+
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct TraceRow {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub trace_address: String,
+    pub depth: i64,
+    pub call_type: String,
+    pub from_addr: String,
+    pub to_addr: String,
+    #[serde(with = "uint256")]
+    pub value: String,
+    pub error: String,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl TraceRow {
+    pub fn new(trace: &Trace, created_at: u32, version: u64) -> Self {
+        TraceRow {
+            block_number: trace.block_number,
+            block_hash: trace.block_hash.clone(),
+            tx_hash: trace.tx_hash.clone(),
+            trace_address: trace.trace_address.clone(),
+            depth: trace.depth,
+            call_type: trace.call_type.clone(),
+            from_addr: trace.from.clone(),
+            to_addr: trace.to.clone(),
+            value: trace.value.clone(),
+            error: trace.error.clone(),
+            created_at,
+            version,
+        }
+    }
+}
+
+impl From<TraceRow> for Trace {
+    fn from(row: TraceRow) -> Self {
+        Trace {
+            block_number: row.block_number,
+            block_hash: row.block_hash,
+            tx_hash: row.tx_hash,
+            trace_address: row.trace_address,
+            depth: row.depth,
+            call_type: row.call_type,
+            from: row.from_addr,
+            to: row.to_addr,
+            value: row.value,
+            error: row.error,
+        }
+    }
+}
+
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct BalanceChangeRow {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub address: String,
+    #[serde(with = "int256")]
+    pub delta: String,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl BalanceChangeRow {
+    pub fn new(change: &BalanceChange, created_at: u32, version: u64) -> Self {
+        BalanceChangeRow {
+            block_number: change.block_number,
+            block_hash: change.block_hash.clone(),
+            address: change.address.clone(),
+            delta: change.delta.clone(),
+            created_at,
+            version,
+        }
+    }
+}
+
+impl From<BalanceChangeRow> for BalanceChange {
+    fn from(row: BalanceChangeRow) -> Self {
+        BalanceChange {
+            block_number: row.block_number,
+            block_hash: row.block_hash,
+            address: row.address,
+            delta: row.delta,
+        }
+    }
+}
+
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct ContractRow {
+    pub address: String,
+    pub creator: String,
+    pub tx_hash: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub bytecode_hash: String,
+    pub is_cbc20: i64,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: i64,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl ContractRow {
+    pub fn new(contract: &Contract, created_at: u32, version: u64) -> Self {
+        ContractRow {
+            address: contract.address.clone(),
+            creator: contract.creator.clone(),
+            tx_hash: contract.tx_hash.clone(),
+            block_number: contract.block_number,
+            block_hash: contract.block_hash.clone(),
+            bytecode_hash: contract.bytecode_hash.clone(),
+            is_cbc20: contract.is_cbc20,
+            name: contract.name.clone(),
+            symbol: contract.symbol.clone(),
+            decimals: contract.decimals,
+            created_at,
+            version,
+        }
+    }
+}
+
+impl From<ContractRow> for Contract {
+    fn from(row: ContractRow) -> Self {
+        Contract {
+            address: row.address,
+            creator: row.creator,
+            tx_hash: row.tx_hash,
+            block_number: row.block_number,
+            block_hash: row.block_hash,
+            bytecode_hash: row.bytecode_hash,
+            is_cbc20: row.is_cbc20,
+            name: row.name,
+            symbol: row.symbol,
+            decimals: row.decimals,
+        }
+    }
+}
+
+/// Transfer of the fungible or non-fungible token
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct TokenTransferRow {
+    pub block_number: i64,
+    pub from_addr: String,
+    pub to_addr: String,
+    #[serde(with = "uint256")]
+    pub value: String,
+    pub tx_hash: String,
+    pub address: String,
+    pub transfer_index: i64,
+    pub status: i64,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl TokenTransferRow {
+    pub fn new(transfer: &TokenTransfer, created_at: u32, version: u64) -> Self {
+        TokenTransferRow {
+            block_number: transfer.block_number,
+            from_addr: transfer.from.clone(),
+            to_addr: transfer.to.clone(),
+            value: transfer.value.clone(),
+            tx_hash: transfer.tx_hash.clone(),
+            address: transfer.address.clone(),
+            transfer_index: transfer.index,
+            status: transfer.status,
+            created_at,
+            version,
+        }
+    }
+}
+
+impl From<TokenTransferRow> for TokenTransfer {
+    fn from(row: TokenTransferRow) -> Self {
+        TokenTransfer {
+            block_number: row.block_number,
+            from: row.from_addr,
+            to: row.to_addr,
+            value: row.value,
+            tx_hash: row.tx_hash,
+            address: row.address,
+            index: row.transfer_index,
+            status: row.status,
+            token_id: "".to_string(),
+            batch_index: 0,
+        }
+    }
+}
+
+/// Transfer of the NFT or multi-token, which has an ID of the transferred token
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct MultiTokenTransferRow {
+    pub block_number: i64,
+    pub from_addr: String,
+    pub to_addr: String,
+    #[serde(with = "uint256")]
+    pub value: String,
+    pub tx_hash: String,
+    pub address: String,
+    pub transfer_index: i64,
+    pub status: i64,
+    #[serde(with = "uint256")]
+    pub token_id: String,
+    pub batch_index: i64,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl MultiTokenTransferRow {
+    pub fn new(transfer: &TokenTransfer, created_at: u32, version: u64) -> Self {
+        MultiTokenTransferRow {
+            block_number: transfer.block_number,
+            from_addr: transfer.from.clone(),
+            to_addr: transfer.to.clone(),
+            value: transfer.value.clone(),
+            tx_hash: transfer.tx_hash.clone(),
+            address: transfer.address.clone(),
+            transfer_index: transfer.index,
+            status: transfer.status,
+            token_id: transfer.token_id.clone(),
+            batch_index: transfer.batch_index,
+            created_at,
+            version,
+        }
+    }
+}
+
+impl From<MultiTokenTransferRow> for TokenTransfer {
+    fn from(row: MultiTokenTransferRow) -> Self {
+        TokenTransfer {
+            block_number: row.block_number,
+            from: row.from_addr,
+            to: row.to_addr,
+            value: row.value,
+            tx_hash: row.tx_hash,
+            address: row.address,
+            index: row.transfer_index,
+            status: row.status,
+            token_id: row.token_id,
+            batch_index: row.batch_index,
+        }
+    }
+}
+
+#[derive(Debug, Row, Serialize)]
+pub(crate) struct DecodeFailureRow {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub address: String,
+    pub log_index: i64,
+    pub topics: String,
+    pub data: String,
+    pub error: String,
+    pub created_at: u32,
+    pub version: u64,
+}
+
+impl DecodeFailureRow {
+    pub fn new(failure: &DecodeFailure, created_at: u32, version: u64) -> Self {
+        DecodeFailureRow {
+            block_number: failure.block_number,
+            block_hash: failure.block_hash.clone(),
+            tx_hash: failure.tx_hash.clone(),
+            address: failure.address.clone(),
+            log_index: failure.log_index,
+            topics: failure.topics.clone(),
+            data: failure.data.clone(),
+            error: failure.error.clone(),
+            created_at,
+            version,
+        }
+    }
+}
+
+#[derive(Debug, Row, Deserialize)]
+pub(crate) struct TableNameRow {
+    pub name: String,
+}
+
+#[derive(Debug, Row, Deserialize)]
+pub(crate) struct AddressRow {
+    pub address: String,
+}
+
+#[derive(Debug, Row, Deserialize)]
+pub(crate) struct NumberRow {
+    pub number: i64,
+}
+
+#[derive(Debug, Row, Deserialize)]
+pub(crate) struct ColumnTypeRow {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: String,
+}
+
+#[derive(Debug, Row, Serialize, Deserialize)]
+pub(crate) struct DiscoveredTokenRow {
+    pub address: String,
+    pub token: String,
+    pub block_number: i64,
+}
+
+#[derive(Debug, Row, Deserialize)]
+pub(crate) struct BalanceRow {
+    pub holder: String,
+    pub balance: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Unsigned(#[serde(with = "uint256")] String);
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Signed(#[serde(with = "int256")] String);
+
+    fn bytes(value: &impl Serialize) -> Vec<u8> {
+        serde_json::from_value(serde_json::to_value(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn uint256_is_little_endian() {
+        let encoded = bytes(&Unsigned("258".to_string()));
+        assert_eq!(encoded.len(), 32);
+        assert_eq!(&encoded[..3], &[2, 1, 0]);
+        assert!(encoded[3..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn uint256_round_trips_max_value() {
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        let json = serde_json::to_value(Unsigned(max.to_string())).unwrap();
+        let decoded: Unsigned = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.0, max);
+    }
+
+    #[test]
+    fn uint256_writes_empty_as_zero() {
+        assert_eq!(bytes(&Unsigned(String::new())), vec![0; 32]);
+    }
+
+    #[test]
+    fn uint256_rejects_invalid_values() {
+        assert!(serde_json::to_value(Unsigned("-1".to_string())).is_err());
+        assert!(serde_json::to_value(Unsigned("0x10".to_string())).is_err());
+    }
+
+    #[test]
+    fn int256_uses_twos_complement() {
+        assert_eq!(bytes(&Signed("-1".to_string())), vec![0xff; 32]);
+        for value in ["0", "42", "-42"] {
+            let json = serde_json::to_value(Signed(value.to_string())).unwrap();
+            let decoded: Signed = serde_json::from_value(json).unwrap();
+            assert_eq!(decoded.0, value);
+        }
+    }
+}
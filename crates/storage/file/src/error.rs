@@ -0,0 +1,27 @@
+use std::{error::Error, pin::Pin};
+
+use thiserror::Error as ThisError;
+
+#[derive(ThisError, Debug)]
+pub enum FileStorageError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+    #[error("failed to serialize the record or the state: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("block {0} is not found in the unmatured blocks")]
+    BlockNotFound(i64),
+    #[error("block {0} is not found in the unmatured blocks")]
+    BlockHashNotFound(String),
+    #[error("file task failed: {0}")]
+    TaskError(#[from] tokio::task::JoinError),
+    #[error("{0} is not supported by the file storage, read the files instead")]
+    NotSupported(&'static str),
+}
+
+impl From<FileStorageError> for Pin<Box<dyn Error + Send + Sync>> {
+    fn from(err: FileStorageError) -> Self {
+        Pin::from(Box::new(err))
+    }
+}
@@ -0,0 +1,775 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use async_trait::async_trait;
+use storage::{Migration, Storage};
+use tokio::time::{self, Duration};
+use tracing::{debug, error, info, warn};
+use types::{
+    BalanceChange, Batch, Block, Contract, DecodedTable, IcanAddress, Log, Receipt, TokenBalance,
+    TokenTransfer, Trace, Transaction, TransferType,
+};
+
+use crate::error::FileStorageError;
+use crate::state::{DiscoveredToken, State};
+use crate::writer::{EntityWriter, Op};
+
+type Result<T> = std::result::Result<T, Pin<Box<dyn Error + Send + Sync>>>;
+
+const ENTITIES: [&str; 3] = ["blocks", "transactions", "token_transfers"];
+
+/// Format of the written files
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    /// Comma separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl Format {
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Compression of the written files
+#[derive(Debug, Clone, Copy)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+        }
+    }
+}
+
+struct Inner {
+    state: State,
+    blocks: EntityWriter,
+    transactions: EntityWriter,
+    token_transfers: EntityWriter,
+}
+
+impl Inner {
+    /// Flushes the records of all entities and saves the state,
+    /// so a restart resumes after the flushed blocks
+    fn flush(&mut self, dir: &Path) -> std::result::Result<(), FileStorageError> {
+        for writer in [
+            &mut self.blocks,
+            &mut self.transactions,
+            &mut self.token_transfers,
+        ] {
+            writer.flush()?;
+            self.state
+                .sequences
+                .insert(writer.entity().to_string(), writer.sequence());
+        }
+        self.state.save(dir)
+    }
+
+    /// Paths of the files which are being written
+    fn open_files(&self) -> Vec<PathBuf> {
+        [&self.blocks, &self.transactions, &self.token_transfers]
+            .iter()
+            .filter_map(|writer| writer.path().map(Path::to_path_buf))
+            .collect()
+    }
+}
+
+/// Removes the files of the entities which were last written before the cutoff, except the open files
+fn remove_expired_files(
+    dir: &Path,
+    cutoff: SystemTime,
+    open_files: &[PathBuf],
+) -> std::io::Result<usize> {
+    let mut removed = 0;
+    for entity in ENTITIES {
+        let entity_dir = dir.join(entity);
+        if !entity_dir.exists() {
+            continue;
+        }
+        for entry in fs::read_dir(entity_dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.metadata()?.modified()? < cutoff && !open_files.contains(&path) {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// Streams blocks, transactions and token transfers into CSV or JSON lines files, one directory per entity.
+/// Every record starts with its operation: `insert`, or `retract` for the records of rolled back blocks,
+/// so the files are only appended. Records of the blocks which are not matured yet are kept in the state
+/// to be retracted, matured blocks are not expected to be rolled back.
+/// The latest flushed block is saved in `state.json` to resume from, records written after it
+/// by an interrupted run are written again, so consumers should expect duplicates
+#[derive(Clone)]
+pub struct FileStorage {
+    pub dir: PathBuf,
+    pub format: Format,
+    pub modules: Vec<String>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl FileStorage {
+    pub fn new(
+        dir: String,
+        format: Format,
+        compression: Compression,
+        rotate_size: u64,
+        modules: Vec<String>,
+    ) -> std::result::Result<Self, FileStorageError> {
+        let dir = PathBuf::from(dir);
+        fs::create_dir_all(&dir)?;
+        let state = State::load(&dir)?;
+        let writer = |entity: &'static str| {
+            let sequence = state.sequences.get(entity).copied().unwrap_or_default();
+            EntityWriter::new(&dir, entity, format, compression, rotate_size, sequence)
+        };
+        let inner = Inner {
+            blocks: writer("blocks"),
+            transactions: writer("transactions"),
+            token_transfers: writer("token_transfers"),
+            state,
+        };
+        Ok(Self {
+            dir,
+            format,
+            modules,
+            inner: Arc::new(Mutex::new(inner)),
+        })
+    }
+
+    /// Runs the file operation on the blocking thread pool, so the file I/O and the lock
+    /// held during it do not block the async workers
+    async fn blocking<T: Send + 'static>(
+        &self,
+        operation: impl FnOnce(&FileStorage) -> std::result::Result<T, FileStorageError>
+            + Send
+            + 'static,
+    ) -> std::result::Result<T, FileStorageError> {
+        let storage = self.clone();
+        tokio::task::spawn_blocking(move || operation(&storage)).await?
+    }
+
+    fn write_batch(&self, batch: &Batch) -> std::result::Result<(), FileStorageError> {
+        let mut inner = self.inner.lock().unwrap();
+        let inner = &mut *inner;
+        let unmatured: HashSet<i64> = batch
+            .blocks
+            .iter()
+            .filter(|block| block.matured == 0)
+            .map(|block| block.number)
+            .collect();
+
+        if self.modules.contains(&"blocks".to_string()) {
+            for block in batch.blocks.iter() {
+                inner.blocks.write(Op::Insert, None, block)?;
+                if unmatured.contains(&block.number) {
+                    inner.state.unmatured.blocks.push(block.clone());
+                }
+            }
+        }
+        if self.modules.contains(&"transactions".to_string()) {
+            for transaction in batch.transactions.iter() {
+                inner.transactions.write(Op::Insert, None, transaction)?;
+                if unmatured.contains(&transaction.block_number) {
+                    inner.state.unmatured.transactions.push(transaction.clone());
+                }
+            }
+        }
+        if self.modules.contains(&"token_transfers".to_string()) {
+            // table names of the batch start with the token type, e.g. cbc20_12ab34cd56ef7890_transfers
+            for (table_name, transfers) in batch.token_transfers.iter() {
+                let token = table_name.split('_').next().unwrap_or_default();
+                for transfer in transfers {
+                    inner
+                        .token_transfers
+                        .write(Op::Insert, Some(token), transfer)?;
+                    if unmatured.contains(&transfer.block_number) {
+                        inner
+                            .state
+                            .unmatured
+                            .token_transfers
+                            .push((token.to_string(), transfer.clone()));
+                    }
+                }
+            }
+        }
+        debug!(
+            "Written {} blocks, {} transactions and {} token transfers",
+            batch.blocks.len(),
+            batch.transactions.len(),
+            batch
+                .token_transfers
+                .values()
+                .map(|v| v.len())
+                .sum::<usize>()
+        );
+
+        if let Some(latest_block) = batch.blocks.iter().map(|block| block.number).max() {
+            inner.state.latest_block = inner.state.latest_block.max(latest_block);
+        }
+        inner.flush(&self.dir)
+    }
+
+    /// Writes retract records for the records of the blocks in the given range.
+    /// Only records of the blocks which are not matured are known, so they are the only ones retracted
+    fn retract_blocks(&self, from: i64, to: i64) -> std::result::Result<(), FileStorageError> {
+        let mut inner = self.inner.lock().unwrap();
+        let inner = &mut *inner;
+        let retracted = inner.state.unmatured.take(from, to);
+
+        // records are retracted in the reverse order of their inserts
+        for (token, transfer) in retracted.token_transfers.iter().rev() {
+            inner
+                .token_transfers
+                .write(Op::Retract, Some(token), transfer)?;
+        }
+        for transaction in retracted.transactions.iter().rev() {
+            inner.transactions.write(Op::Retract, None, transaction)?;
+        }
+        for block in retracted.blocks.iter().rev() {
+            inner.blocks.write(Op::Retract, None, block)?;
+        }
+
+        let latest_block = inner.state.latest_block;
+        let matured = (to.min(latest_block) - from + 1).max(0) - retracted.blocks.len() as i64;
+        if self.modules.contains(&"blocks".to_string()) && matured > 0 {
+            warn!(
+                "{} rolled back blocks are already matured, their records are not retracted",
+                matured
+            );
+        }
+        if latest_block >= from && latest_block <= to {
+            inner.state.latest_block = from - 1;
+        }
+        inner.flush(&self.dir)
+    }
+
+    fn find_unmatured_block(&self, predicate: impl Fn(&Block) -> bool) -> Option<Block> {
+        self.inner
+            .lock()
+            .unwrap()
+            .state
+            .unmatured
+            .blocks
+            .iter()
+            .find(|block| predicate(block))
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Storage for FileStorage {
+    async fn prepare_db(&self) -> Result<()> {
+        self.run_migrations(false).await?;
+        Ok(())
+    }
+
+    async fn schema_version(&self) -> Result<i64> {
+        Ok(self.inner.lock().unwrap().state.schema_version)
+    }
+
+    async fn apply_migration(&self, migration: Migration) -> Result<()> {
+        info!("Applying migration {}", migration);
+        // records are written with the fields of their types, there is no schema to change
+        let version = migration.version();
+        self.blocking(move |storage| {
+            let mut inner = storage.inner.lock().unwrap();
+            inner.state.schema_version = version;
+            inner.state.save(&storage.dir)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn create_indexes(&self) -> Result<()> {
+        // files are only appended, there are no indexes to create
+        Ok(())
+    }
+
+    async fn get_latest_block_number(&self) -> Result<i64> {
+        Ok(self.inner.lock().unwrap().state.latest_block)
+    }
+
+    async fn update_blocks_to_matured(&self, from: i64, to: i64) -> Result<()> {
+        // written records keep the matured flag they were written with,
+        // matured blocks are not rolled back anymore, so their records are not needed to retract them
+        self.blocking(move |storage| {
+            let mut inner = storage.inner.lock().unwrap();
+            let matured = inner.state.unmatured.take(from, to);
+            if !matured.is_empty() {
+                inner.state.save(&storage.dir)?;
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn create_token_transfers_tables(
+        &self,
+        _tokens: HashMap<String, HashSet<String>>,
+    ) -> Result<()> {
+        // transfers of all tokens are written into the token_transfers files
+        Ok(())
+    }
+
+    async fn create_decoded_tables(&self, _tables: Vec<DecodedTable>) -> Result<()> {
+        // only blocks, transactions and token transfers are written
+        Ok(())
+    }
+
+    async fn rollback_blocks(&self, from: i64) -> Result<()> {
+        self.blocking(move |storage| storage.retract_blocks(from, i64::MAX))
+            .await?;
+        Ok(())
+    }
+
+    async fn clean_last_blocks(&self, number: i64) -> Result<()> {
+        let from = self.get_latest_block_number().await? - number + 1;
+        self.blocking(move |storage| storage.retract_blocks(from, i64::MAX))
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_blocks_with_txs_and_token_transfers(
+        &self,
+        insert_all: bool,
+        batch: &mut Batch,
+    ) -> Result<()> {
+        if self.is_batch_ready(batch, insert_all) {
+            // the batch is moved to the blocking task and given back, so it is kept if the write fails
+            let taken = std::mem::take(batch);
+            let (taken, result) = self
+                .blocking(move |storage| {
+                    let result = storage.write_batch(&taken);
+                    Ok((taken, result))
+                })
+                .await?;
+            *batch = taken;
+            result?;
+            batch.clear();
+        }
+        Ok(())
+    }
+
+    async fn start_cleanup_task(&self, interval: Duration, retention_duration: Duration) {
+        let dir = self.dir.clone();
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(interval);
+            loop {
+                interval.tick().await;
+                let cutoff = SystemTime::now() - retention_duration;
+
+                // files are removed once they were last written before the retention duration
+                let open_files = inner.lock().unwrap().open_files();
+                let dir = dir.clone();
+                let res = tokio::task::spawn_blocking(move || {
+                    remove_expired_files(&dir, cutoff, &open_files)
+                })
+                .await;
+                match res.map_err(FileStorageError::from).and_then(|res| Ok(res?)) {
+                    Err(e) => error!("Failed to delete old files: {:?}", e),
+                    Ok(removed) => {
+                        debug!("Deleted old files. Number of removed files - {}", removed)
+                    }
+                }
+            }
+        });
+    }
+
+    async fn add_discovered_token(
+        &self,
+        token: String,
+        address: String,
+        block_number: i64,
+    ) -> Result<()> {
+        self.blocking(move |storage| {
+            let mut inner = storage.inner.lock().unwrap();
+            if inner
+                .state
+                .discovered_tokens
+                .iter()
+                .any(|discovered| discovered.address == address)
+            {
+                return Ok(());
+            }
+            inner.state.discovered_tokens.push(DiscoveredToken {
+                address,
+                token,
+                block_number,
+            });
+            inner.state.save(&storage.dir)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn get_discovered_tokens(&self) -> Result<HashMap<String, HashSet<String>>> {
+        let mut tokens: HashMap<String, HashSet<String>> = HashMap::new();
+        for discovered in &self.inner.lock().unwrap().state.discovered_tokens {
+            tokens
+                .entry(discovered.token.clone())
+                .or_default()
+                .insert(discovered.address.clone());
+        }
+        Ok(tokens)
+    }
+
+    // View functions
+
+    async fn get_block_by_number(&self, block_number: i64) -> Result<Block> {
+        let block = self
+            .find_unmatured_block(|block| block.number == block_number)
+            .ok_or(FileStorageError::BlockNotFound(block_number))?;
+        Ok(block)
+    }
+
+    async fn get_block_by_hash(&self, block_hash: String) -> Result<Block> {
+        let block = self
+            .find_unmatured_block(|block| block.hash == block_hash)
+            .ok_or(FileStorageError::BlockHashNotFound(block_hash))?;
+        Ok(block)
+    }
+
+    async fn get_all_blocks(&self) -> Result<Vec<Block>> {
+        Err(FileStorageError::NotSupported("get_all_blocks").into())
+    }
+
+    async fn get_blocks_in_range(&self, start: i64, end: i64) -> Result<Vec<Block>> {
+        // only the blocks which are not matured are known, like in `get_block_by_number`
+        let end = if end < 0 { i64::MAX } else { end };
+        let mut blocks: Vec<Block> = self
+            .inner
+            .lock()
+            .unwrap()
+            .state
+            .unmatured
+            .blocks
+            .iter()
+            .filter(|block| block.number >= start && block.number <= end)
+            .cloned()
+            .collect();
+        blocks.sort_by_key(|block| block.number);
+        Ok(blocks)
+    }
+
+    async fn get_block_transactions(&self, _block_number: i64) -> Result<Vec<Transaction>> {
+        Err(FileStorageError::NotSupported("get_block_transactions").into())
+    }
+
+    async fn get_transaction_by_hash(&self, _hash: String) -> Result<Transaction> {
+        Err(FileStorageError::NotSupported("get_transaction_by_hash").into())
+    }
+
+    async fn get_token_transfers(
+        &self,
+        _token_address: IcanAddress,
+        _from: Option<IcanAddress>,
+        _to: Option<IcanAddress>,
+    ) -> Result<Vec<TokenTransfer>> {
+        Err(FileStorageError::NotSupported("get_token_transfers").into())
+    }
+
+    async fn get_transaction_token_transfers(
+        &self,
+        _tx_hash: String,
+    ) -> Result<Vec<TokenTransfer>> {
+        Err(FileStorageError::NotSupported("get_transaction_token_transfers").into())
+    }
+
+    async fn get_address_token_transfers(
+        &self,
+        _address: IcanAddress,
+        _transfer_type: TransferType,
+    ) -> Result<Vec<TokenTransfer>> {
+        Err(FileStorageError::NotSupported("get_address_token_transfers").into())
+    }
+
+    async fn get_token_balance(
+        &self,
+        _token: IcanAddress,
+        _holder: IcanAddress,
+    ) -> Result<TokenBalance> {
+        Err(FileStorageError::NotSupported("get_token_balance").into())
+    }
+
+    async fn get_top_holders(&self, _token: IcanAddress, _limit: i64) -> Result<Vec<TokenBalance>> {
+        Err(FileStorageError::NotSupported("get_top_holders").into())
+    }
+
+    async fn get_transaction_receipt(&self, _tx_hash: String) -> Result<Receipt> {
+        Err(FileStorageError::NotSupported("get_transaction_receipt").into())
+    }
+
+    async fn get_transaction_logs(&self, _tx_hash: String) -> Result<Vec<Log>> {
+        Err(FileStorageError::NotSupported("get_transaction_logs").into())
+    }
+
+    async fn get_balance_changes(&self, _address: IcanAddress) -> Result<Vec<BalanceChange>> {
+        Err(FileStorageError::NotSupported("get_balance_changes").into())
+    }
+
+    async fn get_balance_at(&self, _address: IcanAddress, _block_number: i64) -> Result<String> {
+        Err(FileStorageError::NotSupported("get_balance_at").into())
+    }
+
+    async fn get_transaction_traces(&self, _tx_hash: String) -> Result<Vec<Trace>> {
+        Err(FileStorageError::NotSupported("get_transaction_traces").into())
+    }
+
+    async fn get_logs(
+        &self,
+        _address: Option<IcanAddress>,
+        _topic0: Option<String>,
+        _from_block: Option<i64>,
+        _to_block: Option<i64>,
+    ) -> Result<Vec<Log>> {
+        Err(FileStorageError::NotSupported("get_logs").into())
+    }
+
+    async fn get_contract(&self, _address: IcanAddress) -> Result<Contract> {
+        Err(FileStorageError::NotSupported("get_contract").into())
+    }
+
+    async fn get_contracts(&self, _only_cbc20: bool) -> Result<Vec<Contract>> {
+        Err(FileStorageError::NotSupported("get_contracts").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Directory of the test, removed when it is dropped
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("file-storage-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            TestDir(dir)
+        }
+
+        fn storage(&self, rotate_size: u64) -> FileStorage {
+            FileStorage::new(
+                self.0.to_string_lossy().to_string(),
+                Format::Jsonl,
+                Compression::None,
+                rotate_size,
+                ENTITIES.iter().map(|entity| entity.to_string()).collect(),
+            )
+            .unwrap()
+        }
+
+        /// Operation and number of the block of every record of the entity, in the order of the files
+        fn records(&self, entity: &str, number_field: &str) -> Vec<(String, i64)> {
+            let mut files: Vec<PathBuf> = fs::read_dir(self.0.join(entity))
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect();
+            files.sort();
+            files
+                .iter()
+                .flat_map(|path| {
+                    fs::read_to_string(path)
+                        .unwrap()
+                        .lines()
+                        .map(|line| {
+                            let record: serde_json::Value = serde_json::from_str(line).unwrap();
+                            (
+                                record["op"].as_str().unwrap().to_string(),
+                                record[number_field].as_i64().unwrap(),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        }
+
+        fn files(&self, entity: &str) -> Vec<String> {
+            let mut files: Vec<String> = fs::read_dir(self.0.join(entity))
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            files.sort();
+            files
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn block(number: i64, matured: i64) -> Block {
+        Block {
+            number,
+            hash: format!("b{}", number),
+            parent_hash: format!("b{}", number - 1),
+            nonce: "0".to_string(),
+            sha3_uncles: String::new(),
+            logs_bloom: String::new(),
+            transactions_root: String::new(),
+            state_root: String::new(),
+            receipts_root: String::new(),
+            miner: String::new(),
+            difficulty: "1".to_string(),
+            total_difficulty: "1".to_string(),
+            extra_data: String::new(),
+            energy_limit: 1,
+            energy_used: 1,
+            timestamp: 1_700_000_000 + number,
+            transaction_count: 1,
+            matured,
+        }
+    }
+
+    fn transaction(block_number: i64) -> Transaction {
+        Transaction {
+            hash: format!("t{}", block_number),
+            nonce: "0".to_string(),
+            block_hash: format!("b{}", block_number),
+            block_number,
+            transaction_index: 0,
+            from: "a".to_string(),
+            to: "b".to_string(),
+            value: "1".to_string(),
+            energy: "1".to_string(),
+            energy_price: "1".to_string(),
+            input: String::new(),
+        }
+    }
+
+    fn transfer(block_number: i64) -> TokenTransfer {
+        TokenTransfer {
+            block_number,
+            from: "a".to_string(),
+            to: "b".to_string(),
+            value: "1".to_string(),
+            tx_hash: format!("t{}", block_number),
+            address: "c".to_string(),
+            index: 0,
+            status: 1,
+            token_id: String::new(),
+            batch_index: 0,
+        }
+    }
+
+    fn batch(numbers: &[i64], matured: i64) -> Batch {
+        Batch {
+            blocks: numbers
+                .iter()
+                .map(|&number| block(number, matured))
+                .collect(),
+            transactions: numbers.iter().map(|&number| transaction(number)).collect(),
+            token_transfers: HashMap::from([(
+                "cbc20_c_transfers".to_string(),
+                numbers.iter().map(|&number| transfer(number)).collect(),
+            )]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn retracts_unmatured_records_in_reverse_order() {
+        let dir = TestDir::new("retract");
+        let storage = dir.storage(u64::MAX);
+        storage.write_batch(&batch(&[1, 2], 1)).unwrap();
+        storage.write_batch(&batch(&[3, 4], 0)).unwrap();
+        storage.retract_blocks(2, i64::MAX).unwrap();
+
+        // block 2 is matured, so only the records of blocks 3 and 4 are known to retract
+        let expected = vec![
+            ("insert".to_string(), 1),
+            ("insert".to_string(), 2),
+            ("insert".to_string(), 3),
+            ("insert".to_string(), 4),
+            ("retract".to_string(), 4),
+            ("retract".to_string(), 3),
+        ];
+        assert_eq!(dir.records("blocks", "number"), expected);
+        assert_eq!(dir.records("transactions", "block_number"), expected);
+        assert_eq!(dir.records("token_transfers", "block_number"), expected);
+
+        let inner = storage.inner.lock().unwrap();
+        assert_eq!(inner.state.latest_block, 1);
+        assert!(inner.state.unmatured.is_empty());
+    }
+
+    #[test]
+    fn rotates_files_after_the_flushed_batch() {
+        let dir = TestDir::new("rotate");
+        let storage = dir.storage(1);
+        storage.write_batch(&batch(&[1, 2], 0)).unwrap();
+        storage.write_batch(&batch(&[3], 0)).unwrap();
+
+        // records of a batch are never split between files
+        assert_eq!(
+            dir.files("blocks"),
+            vec!["blocks-000001.jsonl", "blocks-000002.jsonl"]
+        );
+        assert_eq!(dir.records("blocks", "number").len(), 3);
+        assert_eq!(storage.inner.lock().unwrap().state.sequences["blocks"], 2);
+    }
+
+    #[test]
+    fn resumes_from_the_saved_state() {
+        let dir = TestDir::new("resume");
+        {
+            let storage = dir.storage(u64::MAX);
+            storage.write_batch(&batch(&[1, 2], 0)).unwrap();
+        }
+        let storage = dir.storage(u64::MAX);
+        {
+            let inner = storage.inner.lock().unwrap();
+            assert_eq!(inner.state.latest_block, 2);
+            assert_eq!(inner.state.unmatured.blocks.len(), 2);
+        }
+        assert!(storage
+            .find_unmatured_block(|block| block.number == 2)
+            .is_some());
+
+        // the file of the previous run is not continued and unmatured blocks can still be retracted
+        storage.retract_blocks(2, i64::MAX).unwrap();
+        assert_eq!(
+            dir.files("blocks"),
+            vec!["blocks-000001.jsonl", "blocks-000002.jsonl"]
+        );
+        assert_eq!(
+            dir.records("blocks", "number"),
+            vec![
+                ("insert".to_string(), 1),
+                ("insert".to_string(), 2),
+                ("retract".to_string(), 2),
+            ]
+        );
+        assert_eq!(storage.inner.lock().unwrap().state.latest_block, 1);
+    }
+}
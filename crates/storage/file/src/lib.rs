@@ -0,0 +1,5 @@
+pub mod file;
+pub use crate::file::{Compression, FileStorage, Format};
+pub mod error;
+mod state;
+mod writer;
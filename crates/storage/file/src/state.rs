@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+use types::{Block, TokenTransfer, Transaction};
+
+use crate::error::FileStorageError;
+
+const STATE_FILE: &str = "state.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct DiscoveredToken {
+    pub address: String,
+    pub token: String,
+    pub block_number: i64,
+}
+
+/// Written records of the blocks which are not matured yet.
+/// They are written again as retract records when their blocks are rolled back
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Unmatured {
+    pub blocks: Vec<Block>,
+    pub transactions: Vec<Transaction>,
+    /// Transfers with the type of their token, e.g. cbc20
+    pub token_transfers: Vec<(String, TokenTransfer)>,
+}
+
+impl Unmatured {
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.transactions.is_empty() && self.token_transfers.is_empty()
+    }
+
+    /// Removes the records of the blocks in the given range and returns them
+    pub fn take(&mut self, from: i64, to: i64) -> Unmatured {
+        let in_range = |number: i64| number >= from && number <= to;
+        let (blocks, kept) = std::mem::take(&mut self.blocks)
+            .into_iter()
+            .partition(|block| in_range(block.number));
+        self.blocks = kept;
+        let (transactions, kept) = std::mem::take(&mut self.transactions)
+            .into_iter()
+            .partition(|transaction| in_range(transaction.block_number));
+        self.transactions = kept;
+        let (token_transfers, kept) = std::mem::take(&mut self.token_transfers)
+            .into_iter()
+            .partition(|(_, transfer)| in_range(transfer.block_number));
+        self.token_transfers = kept;
+        Unmatured {
+            blocks,
+            transactions,
+            token_transfers,
+        }
+    }
+}
+
+/// State of the storage directory, saved after the records of every batch are flushed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct State {
+    pub schema_version: i64,
+    pub latest_block: i64,
+    /// Sequence number of the latest file of every entity
+    pub sequences: BTreeMap<String, u64>,
+    pub unmatured: Unmatured,
+    pub discovered_tokens: Vec<DiscoveredToken>,
+}
+
+impl State {
+    pub fn load(dir: &Path) -> Result<Self, FileStorageError> {
+        let path = dir.join(STATE_FILE);
+        if !path.exists() {
+            return Ok(State::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Replaces the state with a rename, so it is never left half written
+    pub fn save(&self, dir: &Path) -> Result<(), FileStorageError> {
+        let tmp_path = dir.join(format!("{}.tmp", STATE_FILE));
+        fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        fs::rename(tmp_path, dir.join(STATE_FILE))?;
+        Ok(())
+    }
+}
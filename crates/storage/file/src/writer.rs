@@ -0,0 +1,240 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::GzEncoder, Compression as GzipLevel};
+use serde::Serialize;
+use tracing::{debug, error};
+
+use crate::error::FileStorageError;
+use crate::file::{Compression, Format};
+
+/// Operation of the record, retract records compensate the inserted records of rolled back blocks
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Op {
+    Insert,
+    Retract,
+}
+
+/// Record of a JSON line, the fields of the entity follow the operation
+#[derive(Serialize)]
+struct JsonRecord<'a, T> {
+    op: Op,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<&'a str>,
+    #[serde(flatten)]
+    record: &'a T,
+}
+
+enum Encoder {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl Encoder {
+    fn new(file: File, compression: Compression) -> io::Result<Self> {
+        let file = BufWriter::new(file);
+        Ok(match compression {
+            Compression::None => Encoder::Plain(file),
+            Compression::Gzip => Encoder::Gzip(GzEncoder::new(file, GzipLevel::default())),
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    /// Writes the end of the compressed stream
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(mut file) => file.flush(),
+            Encoder::Gzip(encoder) => encoder.finish()?.flush(),
+            Encoder::Zstd(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(file) => file.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(file) => file.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+enum Sink {
+    Csv(Box<csv::Writer<Encoder>>),
+    Jsonl(Encoder),
+}
+
+impl Sink {
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Csv(writer) => writer.flush(),
+            Sink::Jsonl(encoder) => encoder.flush(),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Sink::Csv(writer) => (*writer)
+                .into_inner()
+                .map_err(|err| err.into_error())?
+                .finish(),
+            Sink::Jsonl(encoder) => encoder.finish(),
+        }
+    }
+}
+
+/// Header of the CSV file, the operation and the token type are followed by the field names of the record
+fn csv_header<T: Serialize>(token: bool, record: &T) -> Result<csv::StringRecord, csv::Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.serialize(record)?;
+    let data = writer.into_inner().map_err(|err| err.into_error())?;
+    let mut header = csv::StringRecord::from(if token {
+        vec!["op", "token"]
+    } else {
+        vec!["op"]
+    });
+    header.extend(csv::Reader::from_reader(data.as_slice()).headers()?.iter());
+    Ok(header)
+}
+
+/// Writes the records of an entity into `<dir>/<entity>/<entity>-<sequence>.<format>[.gz|.zst]` files.
+/// A new file is started once the current one reaches the rotation size, which is checked
+/// when the records of a batch are flushed, so records of a block are never split between files
+pub(crate) struct EntityWriter {
+    entity: &'static str,
+    dir: PathBuf,
+    format: Format,
+    compression: Compression,
+    rotate_size: u64,
+    sequence: u64,
+    file: Option<(PathBuf, Sink)>,
+}
+
+impl EntityWriter {
+    pub fn new(
+        dir: &Path,
+        entity: &'static str,
+        format: Format,
+        compression: Compression,
+        rotate_size: u64,
+        sequence: u64,
+    ) -> Self {
+        Self {
+            entity,
+            dir: dir.join(entity),
+            format,
+            compression,
+            rotate_size,
+            sequence,
+            file: None,
+        }
+    }
+
+    pub fn entity(&self) -> &'static str {
+        self.entity
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Path of the file which is being written
+    pub fn path(&self) -> Option<&Path> {
+        self.file.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    fn open<T: Serialize>(
+        &mut self,
+        token: bool,
+        record: &T,
+    ) -> Result<&mut Sink, FileStorageError> {
+        if self.file.is_none() {
+            fs::create_dir_all(&self.dir)?;
+            // files of an interrupted run are not continued, the next sequence number starts a new file
+            self.sequence += 1;
+            let path = self.dir.join(format!(
+                "{}-{:06}.{}{}",
+                self.entity,
+                self.sequence,
+                self.format.extension(),
+                self.compression.extension()
+            ));
+            debug!("Writing {} to {:?}", self.entity, path);
+            let encoder = Encoder::new(File::create(&path)?, self.compression)?;
+            let sink = match self.format {
+                Format::Csv => {
+                    let mut writer = csv::WriterBuilder::new()
+                        .has_headers(false)
+                        .from_writer(encoder);
+                    writer.write_record(&csv_header(token, record)?)?;
+                    Sink::Csv(Box::new(writer))
+                }
+                Format::Jsonl => Sink::Jsonl(encoder),
+            };
+            self.file = Some((path, sink));
+        }
+        Ok(&mut self.file.as_mut().unwrap().1)
+    }
+
+    /// Writes the record with the type of its token, which is set for token transfers only
+    pub fn write<T: Serialize>(
+        &mut self,
+        op: Op,
+        token: Option<&str>,
+        record: &T,
+    ) -> Result<(), FileStorageError> {
+        match self.open(token.is_some(), record)? {
+            Sink::Csv(writer) => match token {
+                Some(token) => writer.serialize((op, token, record))?,
+                None => writer.serialize((op, record))?,
+            },
+            Sink::Jsonl(encoder) => {
+                serde_json::to_writer(&mut *encoder, &JsonRecord { op, token, record })?;
+                encoder.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the written records and finishes the file if it reached the rotation size
+    pub fn flush(&mut self) -> Result<(), FileStorageError> {
+        if let Some((path, sink)) = self.file.as_mut() {
+            sink.flush()?;
+            if fs::metadata(path)?.len() >= self.rotate_size {
+                self.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finishes the current file, the next record starts a new one
+    pub fn finish(&mut self) -> Result<(), FileStorageError> {
+        if let Some((path, sink)) = self.file.take() {
+            sink.finish()?;
+            debug!("Finished {:?}", path);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for EntityWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            error!("Failed to finish {} file: {:?}", self.entity, e);
+        }
+    }
+}
@@ -1,7 +1,15 @@
 use async_trait::async_trait;
-use std::{collections::HashSet, error::Error, fmt::Error as fmt_err, pin::Pin};
-use storage::Storage;
-use types::{Block, TokenTransfer, Transaction, TransferType};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Error as fmt_err,
+    pin::Pin,
+};
+use storage::{Migration, Storage};
+use types::{
+    BalanceChange, Batch, Block, Contract, DecodedTable, IcanAddress, Log, Receipt, TokenBalance,
+    TokenTransfer, Trace, Transaction, TransferType,
+};
 
 #[derive(Debug, Clone)]
 pub struct MockStorage {
@@ -30,6 +38,17 @@ impl Storage for MockStorage {
         Ok(())
     }
 
+    async fn schema_version(&self) -> Result<i64, Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(0)
+    }
+
+    async fn apply_migration(
+        &self,
+        _migration: Migration,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(())
+    }
+
     async fn get_block_by_number(
         &self,
         block_number: i64,
@@ -63,6 +82,8 @@ impl Storage for MockStorage {
         start: i64,
         end: i64,
     ) -> Result<Vec<Block>, Pin<Box<dyn Error + Send + Sync>>> {
+        // negative end means no upper bound, like in the other storages
+        let end = if end < 0 { i64::MAX } else { end };
         let mut blocks = vec![];
         for block in &self.blocks {
             if block.number >= start && block.number <= end {
@@ -105,11 +126,18 @@ impl Storage for MockStorage {
         Ok(())
     }
 
+    async fn create_decoded_tables(
+        &self,
+        _tables: Vec<DecodedTable>,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(())
+    }
+
     async fn get_token_transfers(
         &self,
-        _token_address: String,
-        _from: Option<String>,
-        _to: Option<String>,
+        _token_address: IcanAddress,
+        _from: Option<IcanAddress>,
+        _to: Option<IcanAddress>,
     ) -> Result<Vec<TokenTransfer>, Pin<Box<dyn Error + Send + Sync>>> {
         Ok(vec![])
     }
@@ -123,12 +151,103 @@ impl Storage for MockStorage {
 
     async fn get_address_token_transfers(
         &self,
-        _address: String,
+        _address: IcanAddress,
         _transfer_type: TransferType,
     ) -> Result<Vec<TokenTransfer>, Pin<Box<dyn Error + Send + Sync>>> {
         Ok(vec![])
     }
 
+    async fn get_token_balance(
+        &self,
+        _token: IcanAddress,
+        _holder: IcanAddress,
+    ) -> Result<TokenBalance, Pin<Box<dyn Error + Send + Sync>>> {
+        Err(Box::pin(fmt_err))
+    }
+
+    async fn get_top_holders(
+        &self,
+        _token: IcanAddress,
+        _limit: i64,
+    ) -> Result<Vec<TokenBalance>, Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(vec![])
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        _tx_hash: String,
+    ) -> Result<Receipt, Pin<Box<dyn Error + Send + Sync>>> {
+        Err(Box::pin(fmt_err))
+    }
+
+    async fn get_transaction_logs(
+        &self,
+        _tx_hash: String,
+    ) -> Result<Vec<Log>, Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(vec![])
+    }
+
+    async fn get_balance_changes(
+        &self,
+        _address: IcanAddress,
+    ) -> Result<Vec<BalanceChange>, Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(vec![])
+    }
+
+    async fn get_balance_at(
+        &self,
+        _address: IcanAddress,
+        _block_number: i64,
+    ) -> Result<String, Pin<Box<dyn Error + Send + Sync>>> {
+        Ok("0".to_string())
+    }
+
+    async fn get_transaction_traces(
+        &self,
+        _tx_hash: String,
+    ) -> Result<Vec<Trace>, Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(vec![])
+    }
+
+    async fn get_logs(
+        &self,
+        _address: Option<IcanAddress>,
+        _topic0: Option<String>,
+        _from_block: Option<i64>,
+        _to_block: Option<i64>,
+    ) -> Result<Vec<Log>, Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(vec![])
+    }
+
+    async fn add_discovered_token(
+        &self,
+        _token: String,
+        _address: String,
+        _block_number: i64,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(())
+    }
+
+    async fn get_discovered_tokens(
+        &self,
+    ) -> Result<HashMap<String, HashSet<String>>, Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(HashMap::new())
+    }
+
+    async fn get_contract(
+        &self,
+        _address: IcanAddress,
+    ) -> Result<Contract, Pin<Box<dyn Error + Send + Sync>>> {
+        Err(Box::pin(fmt_err))
+    }
+
+    async fn get_contracts(
+        &self,
+        _only_cbc20: bool,
+    ) -> Result<Vec<Contract>, Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(vec![])
+    }
+
     // async fn add_blocks(
     //     &mut self,
     //     blocks: Vec<Block>,
@@ -144,10 +263,7 @@ impl Storage for MockStorage {
         Ok(())
     }
 
-    async fn clean_block_data(
-        &self,
-        _block_number: i64,
-    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>
+    async fn rollback_blocks(&self, _from: i64) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>
     where
         Self: Sized,
     {
@@ -164,9 +280,7 @@ impl Storage for MockStorage {
     async fn insert_blocks_with_txs_and_token_transfers(
         &self,
         _insert_all: bool,
-        _blocks: &mut Vec<Block>,
-        _transactions: &mut Vec<Transaction>,
-        _token_transfers: &mut std::collections::HashMap<String, Vec<TokenTransfer>>,
+        _batch: &mut Batch,
     ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
         Ok(())
     }
@@ -0,0 +1,34 @@
+use std::{error::Error, pin::Pin};
+
+use thiserror::Error as ThisError;
+
+#[derive(ThisError, Debug)]
+pub enum ParquetStorageError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    ParquetError(#[from] parquet::errors::ParquetError),
+    #[error(transparent)]
+    ArrowError(#[from] arrow_schema::ArrowError),
+    #[error("invalid manifest: {0}")]
+    ManifestError(#[from] serde_json::Error),
+    #[error("column {0} is missing in the parquet file")]
+    MissingColumn(String),
+    #[error("block {0} is not found")]
+    BlockNotFound(i64),
+    /// Rows of the batch are stored with their block, so the block must be in the batch
+    #[error("no block of {0} in the batch")]
+    MissingBlock(String),
+    #[error("invalid block timestamp {0}")]
+    InvalidTimestamp(i64),
+    #[error("file task failed: {0}")]
+    TaskError(#[from] tokio::task::JoinError),
+    #[error("{0} is not supported by the parquet storage, query the files instead")]
+    NotSupported(&'static str),
+}
+
+impl From<ParquetStorageError> for Pin<Box<dyn Error + Send + Sync>> {
+    fn from(err: ParquetStorageError) -> Self {
+        Pin::from(Box::new(err))
+    }
+}
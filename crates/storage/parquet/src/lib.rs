@@ -0,0 +1,5 @@
+pub mod parquet;
+pub use crate::parquet::{ParquetStorage, Partition};
+pub mod error;
+mod manifest;
+mod table;
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::error::ParquetStorageError;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Parquet file written by the storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct FileEntry {
+    pub table: String,
+    /// Path relative to the storage directory
+    pub path: String,
+    pub from_block: i64,
+    pub to_block: i64,
+    pub rows: usize,
+    /// Timestamp of the latest block in the file, used by the cleanup task
+    pub max_timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct DiscoveredToken {
+    pub address: String,
+    pub token: String,
+    pub block_number: i64,
+}
+
+/// State of the storage directory. Files are only listed once they are completely written,
+/// so files missing in the manifest are left by an interrupted run and are removed on startup
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    pub schema_version: i64,
+    pub latest_block: i64,
+    pub files: Vec<FileEntry>,
+    pub discovered_tokens: Vec<DiscoveredToken>,
+}
+
+impl Manifest {
+    pub fn load(dir: &Path) -> Result<Self, ParquetStorageError> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Manifest::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Replaces the manifest with a rename, so it is never left half written
+    pub fn save(&self, dir: &Path) -> Result<(), ParquetStorageError> {
+        let tmp_path = dir.join(format!("{}.tmp", MANIFEST_FILE));
+        fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(tmp_path, dir.join(MANIFEST_FILE))?;
+        Ok(())
+    }
+
+    /// Adds the written file, a file written again for the same blocks replaces its entry
+    pub fn add_file(&mut self, entry: FileEntry) {
+        self.files.retain(|file| file.path != entry.path);
+        self.files.push(entry);
+    }
+}
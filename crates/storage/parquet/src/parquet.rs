@@ -0,0 +1,711 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use arrow_array::{BooleanArray, RecordBatch};
+use arrow_select::filter::filter_record_batch;
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    basic::Compression,
+    file::properties::WriterProperties,
+};
+use storage::{Migration, Storage};
+use tokio::time::{self, Duration};
+use tracing::{debug, error, info};
+use types::{
+    BalanceChange, Batch, Block, Contract, DecodedTable, IcanAddress, Log, Receipt, TokenBalance,
+    TokenTransfer, Trace, Transaction, TransferType,
+};
+
+use crate::error::ParquetStorageError;
+use crate::manifest::{DiscoveredToken, FileEntry, Manifest};
+use crate::table::{self, Table};
+
+type Result<T> = std::result::Result<T, Pin<Box<dyn Error + Send + Sync>>>;
+
+/// How the rows of every table are split into directories
+#[derive(Debug, Clone, Copy)]
+pub enum Partition {
+    /// Ranges of the given number of blocks, e.g. `blocks=000000100000-000000199999`
+    BlockRange(i64),
+    /// UTC days of the block timestamps, e.g. `date=2024-05-01`
+    Day,
+}
+
+impl Partition {
+    fn key(
+        &self,
+        block_number: i64,
+        timestamp: i64,
+    ) -> std::result::Result<String, ParquetStorageError> {
+        match self {
+            Partition::BlockRange(size) => {
+                let start = block_number / size * size;
+                Ok(format!("blocks={:012}-{:012}", start, start + size - 1))
+            }
+            Partition::Day => {
+                let date = Utc
+                    .timestamp_opt(timestamp, 0)
+                    .single()
+                    .ok_or(ParquetStorageError::InvalidTimestamp(timestamp))?;
+                Ok(format!("date={}", date.format("%Y-%m-%d")))
+            }
+        }
+    }
+}
+
+/// Number of rows after which the latest file of a partition is no longer compacted with
+/// the inserted rows. Compacting rewrites the file, so larger files make the inserts slower
+const COMPACTED_FILE_ROWS: usize = 100_000;
+
+fn read_file(path: &Path) -> std::result::Result<Vec<RecordBatch>, ParquetStorageError> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()?;
+    Ok(reader.collect::<std::result::Result<Vec<_>, _>>()?)
+}
+
+/// Writes the record batches into a temporary file which replaces the file once it is closed
+fn write_file(
+    path: &Path,
+    batches: &[RecordBatch],
+) -> std::result::Result<(), ParquetStorageError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("parquet.tmp");
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(
+        File::create(&tmp_path)?,
+        batches[0].schema(),
+        Some(properties),
+    )?;
+    for batch in batches {
+        writer.write(batch)?;
+    }
+    writer.close()?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Lists all files in the directory and its subdirectories
+fn list_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Removes the files of the manifest, e.g. expired by the retention duration
+fn remove_files(dir: &Path, entries: &[FileEntry]) -> std::io::Result<()> {
+    for entry in entries {
+        let path = dir.join(&entry.path);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes every table into Parquet files `<dir>/<table>/<partition>/part-<from>-<to>.parquet`.
+/// Parquet files can not be appended, so inserted batches are compacted with the latest file
+/// of their partitions until it has `COMPACTED_FILE_ROWS` rows, and then start a new file.
+/// Files are listed in the manifest, which keeps the latest block to resume from.
+/// Only the write side and the blocks used by the reorg detection are supported,
+/// data is queried with the data-lake tools, e.g. DuckDB or Spark
+#[derive(Debug, Clone)]
+pub struct ParquetStorage {
+    pub dir: PathBuf,
+    pub partition: Partition,
+    pub modules: Vec<String>,
+    manifest: Arc<Mutex<Manifest>>,
+}
+
+impl ParquetStorage {
+    pub fn new(
+        dir: String,
+        partition: Partition,
+        modules: Vec<String>,
+    ) -> std::result::Result<Self, ParquetStorageError> {
+        let dir = PathBuf::from(dir);
+        fs::create_dir_all(&dir)?;
+        let manifest = Manifest::load(&dir)?;
+        Ok(Self {
+            dir,
+            partition,
+            modules,
+            manifest: Arc::new(Mutex::new(manifest)),
+        })
+    }
+
+    /// Runs the file operation on the blocking thread pool, so the file I/O and the manifest lock
+    /// held during it do not block the async workers
+    async fn blocking<T: Send + 'static>(
+        &self,
+        operation: impl FnOnce(&ParquetStorage) -> std::result::Result<T, ParquetStorageError>
+            + Send
+            + 'static,
+    ) -> std::result::Result<T, ParquetStorageError> {
+        let storage = self.clone();
+        tokio::task::spawn_blocking(move || operation(&storage)).await?
+    }
+
+    /// Removes files which are not in the manifest, they are left by an interrupted run
+    /// and their blocks are written again after the latest block of the manifest
+    fn remove_unlisted_files(&self) -> std::result::Result<(), ParquetStorageError> {
+        let manifest = self.manifest.lock().unwrap();
+        let listed: HashSet<PathBuf> = manifest
+            .files
+            .iter()
+            .map(|file| self.dir.join(&file.path))
+            .collect();
+        let mut files = vec![];
+        list_files(&self.dir, &mut files)?;
+        for path in files {
+            let is_data_file = path
+                .extension()
+                .is_some_and(|extension| extension == "parquet" || extension == "tmp");
+            if is_data_file && !path.ends_with("manifest.json.tmp") && !listed.contains(&path) {
+                debug!("Removing unfinished file {:?}", path);
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the rows of the table, one file per partition. The latest file of the partition
+    /// is compacted with the rows while it has less than `COMPACTED_FILE_ROWS` rows, so every
+    /// flush does not leave a small file. Replaced files are returned, they are removed once
+    /// the manifest without them is saved
+    fn write_table(
+        &self,
+        manifest: &mut Manifest,
+        table: &Table,
+    ) -> std::result::Result<Vec<FileEntry>, ParquetStorageError> {
+        let mut partitions: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (row, (block_number, timestamp)) in table.row_blocks.iter().enumerate() {
+            partitions
+                .entry(self.partition.key(*block_number, *timestamp)?)
+                .or_default()
+                .push(row);
+        }
+        let mut replaced = vec![];
+        for (partition, rows) in partitions {
+            let mut from_block = rows
+                .iter()
+                .map(|&row| table.row_blocks[row].0)
+                .min()
+                .unwrap();
+            let to_block = rows
+                .iter()
+                .map(|&row| table.row_blocks[row].0)
+                .max()
+                .unwrap();
+            let mut max_timestamp = rows
+                .iter()
+                .map(|&row| table.row_blocks[row].1)
+                .max()
+                .unwrap();
+            let mut row_count = rows.len();
+            let mut batches = vec![table.record_batch(&rows)?];
+
+            let dir = format!("{}/{}/", table.name, partition);
+            let latest = manifest
+                .files
+                .iter()
+                .filter(|file| file.table == table.name && file.path.starts_with(&dir))
+                .max_by_key(|file| file.to_block)
+                .filter(|file| file.rows < COMPACTED_FILE_ROWS && file.to_block < from_block)
+                .cloned();
+            if let Some(latest) = latest {
+                let mut previous = read_file(&self.dir.join(&latest.path))?;
+                // files written before a schema change are kept as they are
+                let schema = batches[0].schema();
+                if previous
+                    .iter()
+                    .all(|batch| batch.schema().fields() == schema.fields())
+                {
+                    previous.append(&mut batches);
+                    batches = previous;
+                    from_block = latest.from_block;
+                    max_timestamp = max_timestamp.max(latest.max_timestamp);
+                    row_count += latest.rows;
+                    manifest.files.retain(|file| file.path != latest.path);
+                    replaced.push(latest);
+                }
+            }
+
+            let path = format!("{}part-{:012}-{:012}.parquet", dir, from_block, to_block);
+            write_file(&self.dir.join(&path), &batches)?;
+            manifest.add_file(FileEntry {
+                table: table.name.clone(),
+                path,
+                from_block,
+                to_block,
+                rows: row_count,
+                max_timestamp,
+            });
+        }
+        debug!("Inserted {}: {:?}", table.name, table.row_blocks.len());
+        Ok(replaced)
+    }
+
+    /// Removes rows of the blocks in the given range from all tables.
+    /// Files with other blocks are written again without the removed rows
+    fn remove_blocks(&self, from: i64, to: i64) -> std::result::Result<(), ParquetStorageError> {
+        let mut manifest = self.manifest.lock().unwrap();
+        let mut files = vec![];
+        for file in std::mem::take(&mut manifest.files) {
+            if file.to_block < from || file.from_block > to {
+                files.push(file);
+                continue;
+            }
+            let path = self.dir.join(&file.path);
+            if file.from_block < from || file.to_block > to {
+                let mut batches = vec![];
+                for batch in read_file(&path)? {
+                    let numbers = table::block_numbers(&file.table, &batch)?;
+                    let keep: BooleanArray = numbers
+                        .iter()
+                        .map(|number| number.map(|number| number < from || number > to))
+                        .collect();
+                    let batch = filter_record_batch(&batch, &keep)?;
+                    if batch.num_rows() > 0 {
+                        batches.push(batch);
+                    }
+                }
+                if !batches.is_empty() {
+                    write_file(&path, &batches)?;
+                    let blocks: Vec<i64> = batches
+                        .iter()
+                        .map(|batch| table::block_numbers(&file.table, batch))
+                        .collect::<std::result::Result<Vec<_>, _>>()?
+                        .into_iter()
+                        .flat_map(|numbers| numbers.iter().flatten())
+                        .collect();
+                    files.push(FileEntry {
+                        from_block: *blocks.iter().min().unwrap(),
+                        to_block: *blocks.iter().max().unwrap(),
+                        rows: blocks.len(),
+                        ..file
+                    });
+                    continue;
+                }
+            }
+            fs::remove_file(path)?;
+        }
+        manifest.files = files;
+        if manifest.latest_block >= from && manifest.latest_block <= to {
+            manifest.latest_block = from - 1;
+        }
+        manifest.save(&self.dir)
+    }
+
+    /// Reads the stored blocks in the given range ordered by number
+    fn read_blocks(
+        &self,
+        from: i64,
+        to: i64,
+    ) -> std::result::Result<Vec<Block>, ParquetStorageError> {
+        let files: Vec<FileEntry> = self
+            .manifest
+            .lock()
+            .unwrap()
+            .files
+            .iter()
+            .filter(|file| file.table == "blocks" && file.to_block >= from && file.from_block <= to)
+            .cloned()
+            .collect();
+        let mut blocks = vec![];
+        for file in files {
+            for batch in read_file(&self.dir.join(&file.path))? {
+                blocks.extend(
+                    table::blocks_from_record_batch(&batch)?
+                        .into_iter()
+                        .filter(|block| block.number >= from && block.number <= to),
+                );
+            }
+        }
+        blocks.sort_by_key(|block| block.number);
+        Ok(blocks)
+    }
+
+    fn write_batch(&self, batch: &Batch) -> std::result::Result<(), ParquetStorageError> {
+        let Batch {
+            blocks,
+            transactions,
+            token_transfers,
+            receipts,
+            logs,
+            traces,
+            balance_changes,
+            contracts,
+            decoded,
+            decode_errors,
+        } = batch;
+        // rows are partitioned by the timestamp of their block
+        let mut timestamp_map = HashMap::new();
+        let mut block_number_map = HashMap::new();
+        for block in blocks.iter() {
+            timestamp_map.insert(&block.hash, block.timestamp);
+        }
+        for transaction in transactions.iter() {
+            let timestamp = table::block_value(&timestamp_map, &transaction.block_hash)?;
+            timestamp_map.insert(&transaction.hash, timestamp);
+            block_number_map.insert(&transaction.hash, transaction.block_number);
+        }
+
+        let mut tables = vec![];
+        if !blocks.is_empty() && self.modules.contains(&"blocks".to_string()) {
+            tables.push(table::blocks_table(blocks));
+        }
+        if !transactions.is_empty() && self.modules.contains(&"transactions".to_string()) {
+            tables.push(table::transactions_table(transactions, &timestamp_map)?);
+        }
+        if self.modules.contains(&"token_transfers".to_string()) {
+            // table names of the batch start with the token type, e.g. cbc20_12ab34cd56ef7890_transfers
+            let transfers: Vec<(&str, &TokenTransfer)> = token_transfers
+                .iter()
+                .flat_map(|(table_name, transfers)| {
+                    let token = table_name.split('_').next().unwrap_or_default();
+                    transfers.iter().map(move |transfer| (token, transfer))
+                })
+                .collect();
+            if !transfers.is_empty() {
+                tables.push(table::token_transfers_table(
+                    &transfers,
+                    &block_number_map,
+                    &timestamp_map,
+                )?);
+            }
+        }
+        if !receipts.is_empty() && self.modules.contains(&"receipts".to_string()) {
+            tables.push(table::receipts_table(receipts, &timestamp_map)?);
+        }
+        if !logs.is_empty() && self.modules.contains(&"logs".to_string()) {
+            tables.push(table::logs_table(logs, &timestamp_map)?);
+        }
+        if !traces.is_empty() && self.modules.contains(&"traces".to_string()) {
+            tables.push(table::traces_table(traces, &timestamp_map)?);
+        }
+        if !balance_changes.is_empty() && self.modules.contains(&"balance_changes".to_string()) {
+            tables.push(table::balance_changes_table(
+                balance_changes,
+                &timestamp_map,
+            )?);
+        }
+        if !contracts.is_empty() && self.modules.contains(&"contracts".to_string()) {
+            tables.push(table::contracts_table(contracts, &timestamp_map)?);
+        }
+        for (table_name, rows) in decoded.iter() {
+            if !rows.is_empty() {
+                tables.push(table::decoded_table(table_name, rows, &timestamp_map)?);
+            }
+        }
+        if !decode_errors.is_empty() {
+            tables.push(table::decode_errors_table(decode_errors, &timestamp_map)?);
+        }
+
+        let mut manifest = self.manifest.lock().unwrap();
+        let mut replaced = vec![];
+        for table in &tables {
+            replaced.extend(self.write_table(&mut manifest, table)?);
+        }
+        // the latest block is saved with the files, so a restart resumes after the written blocks
+        if let Some(latest_block) = blocks.iter().map(|block| block.number).max() {
+            manifest.latest_block = manifest.latest_block.max(latest_block);
+        }
+        manifest.save(&self.dir)?;
+        // files left by an interruption here are not in the manifest and are removed on startup
+        remove_files(&self.dir, &replaced)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for ParquetStorage {
+    async fn prepare_db(&self) -> Result<()> {
+        self.run_migrations(false).await?;
+        self.blocking(|storage| storage.remove_unlisted_files())
+            .await?;
+        Ok(())
+    }
+
+    async fn schema_version(&self) -> Result<i64> {
+        Ok(self.manifest.lock().unwrap().schema_version)
+    }
+
+    async fn apply_migration(&self, migration: Migration) -> Result<()> {
+        info!("Applying migration {}", migration);
+        // directories of the tables are created with their first files,
+        // and values are written as decimal strings since the first version
+        let version = migration.version();
+        self.blocking(move |storage| {
+            let mut manifest = storage.manifest.lock().unwrap();
+            manifest.schema_version = version;
+            manifest.save(&storage.dir)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn create_indexes(&self) -> Result<()> {
+        // Parquet files keep min and max values of the columns, there are no indexes to create
+        Ok(())
+    }
+
+    async fn get_latest_block_number(&self) -> Result<i64> {
+        Ok(self.manifest.lock().unwrap().latest_block)
+    }
+
+    async fn update_blocks_to_matured(&self, _from: i64, _to: i64) -> Result<()> {
+        // files are not changed once written, so blocks keep the matured flag they were written with.
+        // Use the finalized-only mode to write matured blocks only
+        Ok(())
+    }
+
+    async fn create_token_transfers_tables(
+        &self,
+        _tokens: HashMap<String, HashSet<String>>,
+    ) -> Result<()> {
+        // transfers of all tokens are written into the token_transfers files
+        Ok(())
+    }
+
+    async fn create_decoded_tables(&self, _tables: Vec<DecodedTable>) -> Result<()> {
+        // schema of the decoded files is taken from the arguments of the rows
+        Ok(())
+    }
+
+    async fn rollback_blocks(&self, from: i64) -> Result<()> {
+        self.blocking(move |storage| storage.remove_blocks(from, i64::MAX))
+            .await?;
+        Ok(())
+    }
+
+    async fn clean_last_blocks(&self, number: i64) -> Result<()> {
+        let from = self.get_latest_block_number().await? - number + 1;
+        self.blocking(move |storage| storage.remove_blocks(from, i64::MAX))
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_blocks_with_txs_and_token_transfers(
+        &self,
+        insert_all: bool,
+        batch: &mut Batch,
+    ) -> Result<()> {
+        if self.is_batch_ready(batch, insert_all) {
+            // the batch is moved to the blocking task and given back, so it is kept if the write fails
+            let taken = std::mem::take(batch);
+            let (taken, result) = self
+                .blocking(move |storage| {
+                    let result = storage.write_batch(&taken);
+                    Ok((taken, result))
+                })
+                .await?;
+            *batch = taken;
+            result?;
+            batch.clear();
+        }
+        Ok(())
+    }
+
+    async fn start_cleanup_task(&self, interval: Duration, retention_duration: Duration) {
+        let dir = self.dir.clone();
+        let manifest = self.manifest.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(interval);
+            loop {
+                interval.tick().await;
+                let cutoff =
+                    chrono::Utc::now() - chrono::Duration::from_std(retention_duration).unwrap();
+                let cutoff_timestamp = cutoff.timestamp();
+
+                // files are removed once all their blocks are older than the retention duration.
+                // The manifest is locked while the files are removed, so it runs on the blocking pool
+                let (dir, manifest) = (dir.clone(), manifest.clone());
+                let res = tokio::task::spawn_blocking(move || {
+                    let mut state = manifest.lock().unwrap();
+                    let (expired, files): (Vec<FileEntry>, Vec<FileEntry>) = state
+                        .files
+                        .drain(..)
+                        .partition(|file| file.max_timestamp < cutoff_timestamp);
+                    state.files = files;
+                    if !expired.is_empty() {
+                        state.save(&dir)?;
+                        remove_files(&dir, &expired)?;
+                    }
+                    Ok::<_, ParquetStorageError>(expired.len())
+                })
+                .await;
+                match res.map_err(ParquetStorageError::from).and_then(|res| res) {
+                    Ok(0) => {}
+                    Ok(removed) => {
+                        debug!("Deleted old files. Number of removed files - {}", removed)
+                    }
+                    Err(e) => error!("Failed to delete old files: {:?}", e),
+                }
+            }
+        });
+    }
+
+    async fn add_discovered_token(
+        &self,
+        token: String,
+        address: String,
+        block_number: i64,
+    ) -> Result<()> {
+        self.blocking(move |storage| {
+            let mut manifest = storage.manifest.lock().unwrap();
+            if manifest
+                .discovered_tokens
+                .iter()
+                .any(|discovered| discovered.address == address)
+            {
+                return Ok(());
+            }
+            manifest.discovered_tokens.push(DiscoveredToken {
+                address,
+                token,
+                block_number,
+            });
+            manifest.save(&storage.dir)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn get_discovered_tokens(&self) -> Result<HashMap<String, HashSet<String>>> {
+        let mut tokens: HashMap<String, HashSet<String>> = HashMap::new();
+        for discovered in &self.manifest.lock().unwrap().discovered_tokens {
+            tokens
+                .entry(discovered.token.clone())
+                .or_default()
+                .insert(discovered.address.clone());
+        }
+        Ok(tokens)
+    }
+
+    // View functions
+
+    async fn get_block_by_number(&self, block_number: i64) -> Result<Block> {
+        let block = self
+            .blocking(move |storage| storage.read_blocks(block_number, block_number))
+            .await?
+            .pop()
+            .ok_or(ParquetStorageError::BlockNotFound(block_number))?;
+        Ok(block)
+    }
+
+    async fn get_block_by_hash(&self, _block_hash: String) -> Result<Block> {
+        Err(ParquetStorageError::NotSupported("get_block_by_hash").into())
+    }
+
+    async fn get_all_blocks(&self) -> Result<Vec<Block>> {
+        Ok(self
+            .blocking(|storage| storage.read_blocks(0, i64::MAX))
+            .await?)
+    }
+
+    async fn get_blocks_in_range(&self, start: i64, end: i64) -> Result<Vec<Block>> {
+        let end = if end < 0 { i64::MAX } else { end };
+        Ok(self
+            .blocking(move |storage| storage.read_blocks(start, end))
+            .await?)
+    }
+
+    async fn get_block_transactions(&self, _block_number: i64) -> Result<Vec<Transaction>> {
+        Err(ParquetStorageError::NotSupported("get_block_transactions").into())
+    }
+
+    async fn get_transaction_by_hash(&self, _hash: String) -> Result<Transaction> {
+        Err(ParquetStorageError::NotSupported("get_transaction_by_hash").into())
+    }
+
+    async fn get_token_transfers(
+        &self,
+        _token_address: IcanAddress,
+        _from: Option<IcanAddress>,
+        _to: Option<IcanAddress>,
+    ) -> Result<Vec<TokenTransfer>> {
+        Err(ParquetStorageError::NotSupported("get_token_transfers").into())
+    }
+
+    async fn get_transaction_token_transfers(
+        &self,
+        _tx_hash: String,
+    ) -> Result<Vec<TokenTransfer>> {
+        Err(ParquetStorageError::NotSupported("get_transaction_token_transfers").into())
+    }
+
+    async fn get_address_token_transfers(
+        &self,
+        _address: IcanAddress,
+        _transfer_type: TransferType,
+    ) -> Result<Vec<TokenTransfer>> {
+        Err(ParquetStorageError::NotSupported("get_address_token_transfers").into())
+    }
+
+    async fn get_token_balance(
+        &self,
+        _token: IcanAddress,
+        _holder: IcanAddress,
+    ) -> Result<TokenBalance> {
+        Err(ParquetStorageError::NotSupported("get_token_balance").into())
+    }
+
+    async fn get_top_holders(&self, _token: IcanAddress, _limit: i64) -> Result<Vec<TokenBalance>> {
+        Err(ParquetStorageError::NotSupported("get_top_holders").into())
+    }
+
+    async fn get_transaction_receipt(&self, _tx_hash: String) -> Result<Receipt> {
+        Err(ParquetStorageError::NotSupported("get_transaction_receipt").into())
+    }
+
+    async fn get_transaction_logs(&self, _tx_hash: String) -> Result<Vec<Log>> {
+        Err(ParquetStorageError::NotSupported("get_transaction_logs").into())
+    }
+
+    async fn get_balance_changes(&self, _address: IcanAddress) -> Result<Vec<BalanceChange>> {
+        Err(ParquetStorageError::NotSupported("get_balance_changes").into())
+    }
+
+    async fn get_balance_at(&self, _address: IcanAddress, _block_number: i64) -> Result<String> {
+        Err(ParquetStorageError::NotSupported("get_balance_at").into())
+    }
+
+    async fn get_transaction_traces(&self, _tx_hash: String) -> Result<Vec<Trace>> {
+        Err(ParquetStorageError::NotSupported("get_transaction_traces").into())
+    }
+
+    async fn get_logs(
+        &self,
+        _address: Option<IcanAddress>,
+        _topic0: Option<String>,
+        _from_block: Option<i64>,
+        _to_block: Option<i64>,
+    ) -> Result<Vec<Log>> {
+        Err(ParquetStorageError::NotSupported("get_logs").into())
+    }
+
+    async fn get_contract(&self, _address: IcanAddress) -> Result<Contract> {
+        Err(ParquetStorageError::NotSupported("get_contract").into())
+    }
+
+    async fn get_contracts(&self, _only_cbc20: bool) -> Result<Vec<Contract>> {
+        Err(ParquetStorageError::NotSupported("get_contracts").into())
+    }
+}
@@ -0,0 +1,436 @@
+use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use std::{collections::HashMap, sync::Arc};
+use types::{
+    BalanceChange, Block, Contract, DecodeFailure, DecodedRow, Log, Receipt, TokenTransfer, Trace,
+    Transaction,
+};
+
+use crate::error::ParquetStorageError;
+
+/// Returns the value of the block of the row, which is looked up by the block or transaction hash
+pub(crate) fn block_value<T: Copy>(
+    map: &HashMap<&String, T>,
+    hash: &String,
+) -> Result<T, ParquetStorageError> {
+    map.get(hash)
+        .copied()
+        .ok_or_else(|| ParquetStorageError::MissingBlock(hash.clone()))
+}
+
+/// Block number and timestamp of every row, the timestamp is looked up by the block hash of the row
+fn row_blocks<T>(
+    rows: &[T],
+    timestamps: &HashMap<&String, i64>,
+    block: impl Fn(&T) -> (i64, &String),
+) -> Result<Vec<(i64, i64)>, ParquetStorageError> {
+    rows.iter()
+        .map(|row| {
+            let (block_number, block_hash) = block(row);
+            Ok((block_number, block_value(timestamps, block_hash)?))
+        })
+        .collect()
+}
+
+/// Values of one column. Values and amounts are kept as decimal strings, the same as in the types
+pub(crate) enum Column {
+    Int64(Vec<i64>),
+    Utf8(Vec<String>),
+    NullableUtf8(Vec<Option<String>>),
+}
+
+impl Column {
+    fn field(&self, name: &str) -> Field {
+        match self {
+            Column::Int64(_) => Field::new(name, DataType::Int64, false),
+            Column::Utf8(_) => Field::new(name, DataType::Utf8, false),
+            Column::NullableUtf8(_) => Field::new(name, DataType::Utf8, true),
+        }
+    }
+
+    fn array(&self, rows: &[usize]) -> ArrayRef {
+        match self {
+            Column::Int64(values) => Arc::new(Int64Array::from(
+                rows.iter().map(|&row| values[row]).collect::<Vec<_>>(),
+            )),
+            Column::Utf8(values) => Arc::new(StringArray::from(
+                rows.iter()
+                    .map(|&row| values[row].as_str())
+                    .collect::<Vec<_>>(),
+            )),
+            Column::NullableUtf8(values) => Arc::new(StringArray::from(
+                rows.iter()
+                    .map(|&row| values[row].as_deref())
+                    .collect::<Vec<_>>(),
+            )),
+        }
+    }
+}
+
+/// Rows of one table of the batch stored column by column
+pub(crate) struct Table {
+    pub name: String,
+    columns: Vec<(String, Column)>,
+    /// Block number and timestamp of every row, rows are partitioned by them
+    pub row_blocks: Vec<(i64, i64)>,
+}
+
+impl Table {
+    fn new(name: &str, row_blocks: Vec<(i64, i64)>) -> Self {
+        Table {
+            name: name.to_string(),
+            columns: vec![],
+            row_blocks,
+        }
+    }
+
+    fn int<T>(mut self, name: &str, rows: &[T], value: impl Fn(&T) -> i64) -> Self {
+        let column = Column::Int64(rows.iter().map(value).collect());
+        self.columns.push((name.to_string(), column));
+        self
+    }
+
+    fn text<T>(mut self, name: &str, rows: &[T], value: impl Fn(&T) -> &str) -> Self {
+        let column = Column::Utf8(rows.iter().map(|row| value(row).to_string()).collect());
+        self.columns.push((name.to_string(), column));
+        self
+    }
+
+    fn optional_text<T>(
+        mut self,
+        name: &str,
+        rows: &[T],
+        value: impl Fn(&T) -> Option<&str>,
+    ) -> Self {
+        let column = Column::NullableUtf8(
+            rows.iter()
+                .map(|row| value(row).map(String::from))
+                .collect(),
+        );
+        self.columns.push((name.to_string(), column));
+        self
+    }
+
+    /// Builds the record batch of the given rows
+    pub fn record_batch(&self, rows: &[usize]) -> Result<RecordBatch, ArrowError> {
+        let schema = Schema::new(
+            self.columns
+                .iter()
+                .map(|(name, column)| column.field(name))
+                .collect::<Vec<_>>(),
+        );
+        RecordBatch::try_new(
+            Arc::new(schema),
+            self.columns
+                .iter()
+                .map(|(_, column)| column.array(rows))
+                .collect(),
+        )
+    }
+}
+
+pub(crate) fn blocks_table(blocks: &[Block]) -> Table {
+    Table::new(
+        "blocks",
+        blocks
+            .iter()
+            .map(|block| (block.number, block.timestamp))
+            .collect(),
+    )
+    .int("number", blocks, |block| block.number)
+    .text("hash", blocks, |block| &block.hash)
+    .text("parent_hash", blocks, |block| &block.parent_hash)
+    .text("nonce", blocks, |block| &block.nonce)
+    .text("sha3_uncles", blocks, |block| &block.sha3_uncles)
+    .text("logs_bloom", blocks, |block| &block.logs_bloom)
+    .text("transactions_root", blocks, |block| {
+        &block.transactions_root
+    })
+    .text("state_root", blocks, |block| &block.state_root)
+    .text("receipts_root", blocks, |block| &block.receipts_root)
+    .text("miner", blocks, |block| &block.miner)
+    .text("difficulty", blocks, |block| &block.difficulty)
+    .text("total_difficulty", blocks, |block| &block.total_difficulty)
+    .text("extra_data", blocks, |block| &block.extra_data)
+    .int("energy_limit", blocks, |block| block.energy_limit)
+    .int("energy_used", blocks, |block| block.energy_used)
+    .int("timestamp", blocks, |block| block.timestamp)
+    .int("transaction_count", blocks, |block| block.transaction_count)
+    .int("matured", blocks, |block| block.matured)
+}
+
+pub(crate) fn transactions_table(
+    transactions: &[Transaction],
+    timestamps: &HashMap<&String, i64>,
+) -> Result<Table, ParquetStorageError> {
+    let row_blocks = row_blocks(transactions, timestamps, |tx| {
+        (tx.block_number, &tx.block_hash)
+    })?;
+    Ok(Table::new("transactions", row_blocks)
+        .text("hash", transactions, |tx| &tx.hash)
+        .text("nonce", transactions, |tx| &tx.nonce)
+        .text("block_hash", transactions, |tx| &tx.block_hash)
+        .int("block_number", transactions, |tx| tx.block_number)
+        .int("transaction_index", transactions, |tx| tx.transaction_index)
+        .text("from_addr", transactions, |tx| &tx.from)
+        .text("to_addr", transactions, |tx| &tx.to)
+        .text("value", transactions, |tx| &tx.value)
+        .text("energy", transactions, |tx| &tx.energy)
+        .text("energy_price", transactions, |tx| &tx.energy_price)
+        .text("input", transactions, |tx| &tx.input))
+}
+
+/// Transfers of all watched tokens are stored together, `token` is the token type, e.g. cbc20
+pub(crate) fn token_transfers_table(
+    transfers: &[(&str, &TokenTransfer)],
+    block_numbers: &HashMap<&String, i64>,
+    timestamps: &HashMap<&String, i64>,
+) -> Result<Table, ParquetStorageError> {
+    // transfers are stored with the block of their transaction
+    let row_blocks = transfers
+        .iter()
+        .map(|(_, tt)| {
+            Ok((
+                block_value(block_numbers, &tt.tx_hash)?,
+                block_value(timestamps, &tt.tx_hash)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, ParquetStorageError>>()?;
+    Ok(Table::new("token_transfers", row_blocks.clone())
+        .int("block_number", &row_blocks, |(block_number, _)| {
+            *block_number
+        })
+        .text("token", transfers, |(token, _)| token)
+        .text("address", transfers, |(_, tt)| &tt.address)
+        .text("from_addr", transfers, |(_, tt)| &tt.from)
+        .text("to_addr", transfers, |(_, tt)| &tt.to)
+        .text("value", transfers, |(_, tt)| &tt.value)
+        .text("token_id", transfers, |(_, tt)| &tt.token_id)
+        .text("tx_hash", transfers, |(_, tt)| &tt.tx_hash)
+        .int("transfer_index", transfers, |(_, tt)| tt.index)
+        .int("batch_index", transfers, |(_, tt)| tt.batch_index)
+        .int("status", transfers, |(_, tt)| tt.status))
+}
+
+pub(crate) fn receipts_table(
+    receipts: &[Receipt],
+    timestamps: &HashMap<&String, i64>,
+) -> Result<Table, ParquetStorageError> {
+    let row_blocks = row_blocks(receipts, timestamps, |receipt| {
+        (receipt.block_number, &receipt.block_hash)
+    })?;
+    Ok(Table::new("receipts", row_blocks)
+        .text("transaction_hash", receipts, |receipt| {
+            &receipt.transaction_hash
+        })
+        .text("block_hash", receipts, |receipt| &receipt.block_hash)
+        .int("block_number", receipts, |receipt| receipt.block_number)
+        .int("transaction_index", receipts, |receipt| {
+            receipt.transaction_index
+        })
+        .int("status", receipts, |receipt| receipt.status)
+        .int("energy_used", receipts, |receipt| receipt.energy_used)
+        .int("cumulative_energy_used", receipts, |receipt| {
+            receipt.cumulative_energy_used
+        })
+        .text("effective_energy_price", receipts, |receipt| {
+            &receipt.effective_energy_price
+        })
+        .text("contract_address", receipts, |receipt| {
+            &receipt.contract_address
+        })
+        .text("logs_bloom", receipts, |receipt| &receipt.logs_bloom))
+}
+
+pub(crate) fn logs_table(
+    logs: &[Log],
+    timestamps: &HashMap<&String, i64>,
+) -> Result<Table, ParquetStorageError> {
+    let row_blocks = row_blocks(logs, timestamps, |log| (log.block_number, &log.block_hash))?;
+    Ok(Table::new("logs", row_blocks)
+        .int("block_number", logs, |log| log.block_number)
+        .text("block_hash", logs, |log| &log.block_hash)
+        .text("tx_hash", logs, |log| &log.tx_hash)
+        .int("log_index", logs, |log| log.log_index)
+        .text("address", logs, |log| &log.address)
+        .optional_text("topic0", logs, |log| log.topic0.as_deref())
+        .optional_text("topic1", logs, |log| log.topic1.as_deref())
+        .optional_text("topic2", logs, |log| log.topic2.as_deref())
+        .optional_text("topic3", logs, |log| log.topic3.as_deref())
+        .text("data", logs, |log| &log.data))
+}
+
+pub(crate) fn traces_table(
+    traces: &[Trace],
+    timestamps: &HashMap<&String, i64>,
+) -> Result<Table, ParquetStorageError> {
+    let row_blocks = row_blocks(traces, timestamps, |trace| {
+        (trace.block_number, &trace.block_hash)
+    })?;
+    Ok(Table::new("traces", row_blocks)
+        .int("block_number", traces, |trace| trace.block_number)
+        .text("block_hash", traces, |trace| &trace.block_hash)
+        .text("tx_hash", traces, |trace| &trace.tx_hash)
+        .text("trace_address", traces, |trace| &trace.trace_address)
+        .int("depth", traces, |trace| trace.depth)
+        .text("call_type", traces, |trace| &trace.call_type)
+        .text("from_addr", traces, |trace| &trace.from)
+        .text("to_addr", traces, |trace| &trace.to)
+        .text("value", traces, |trace| &trace.value)
+        .text("error", traces, |trace| &trace.error))
+}
+
+pub(crate) fn balance_changes_table(
+    changes: &[BalanceChange],
+    timestamps: &HashMap<&String, i64>,
+) -> Result<Table, ParquetStorageError> {
+    let row_blocks = row_blocks(changes, timestamps, |change| {
+        (change.block_number, &change.block_hash)
+    })?;
+    Ok(Table::new("balance_changes", row_blocks)
+        .int("block_number", changes, |change| change.block_number)
+        .text("block_hash", changes, |change| &change.block_hash)
+        .text("address", changes, |change| &change.address)
+        .text("delta", changes, |change| &change.delta))
+}
+
+pub(crate) fn contracts_table(
+    contracts: &[Contract],
+    timestamps: &HashMap<&String, i64>,
+) -> Result<Table, ParquetStorageError> {
+    let row_blocks = row_blocks(contracts, timestamps, |contract| {
+        (contract.block_number, &contract.block_hash)
+    })?;
+    Ok(Table::new("contracts", row_blocks)
+        .text("address", contracts, |contract| &contract.address)
+        .text("creator", contracts, |contract| &contract.creator)
+        .text("tx_hash", contracts, |contract| &contract.tx_hash)
+        .int("block_number", contracts, |contract| contract.block_number)
+        .text("block_hash", contracts, |contract| &contract.block_hash)
+        .text("bytecode_hash", contracts, |contract| {
+            &contract.bytecode_hash
+        })
+        .int("is_cbc20", contracts, |contract| contract.is_cbc20)
+        .text("name", contracts, |contract| &contract.name)
+        .text("symbol", contracts, |contract| &contract.symbol)
+        .int("decimals", contracts, |contract| contract.decimals))
+}
+
+/// Calls or events decoded with the contract ABI, every argument is a column with the value as text
+pub(crate) fn decoded_table(
+    name: &str,
+    rows: &[DecodedRow],
+    timestamps: &HashMap<&String, i64>,
+) -> Result<Table, ParquetStorageError> {
+    let row_blocks = row_blocks(rows, timestamps, |row| (row.block_number, &row.block_hash))?;
+    let mut table = Table::new(name, row_blocks)
+        .int("block_number", rows, |row| row.block_number)
+        .text("block_hash", rows, |row| &row.block_hash)
+        .text("tx_hash", rows, |row| &row.tx_hash)
+        .text("address", rows, |row| &row.address)
+        .int("log_index", rows, |row| row.log_index)
+        .int("status", rows, |row| row.status);
+    // rows of a table have the same arguments in the same order
+    if let Some(first) = rows.first() {
+        for (index, (arg, _)) in first.args.iter().enumerate() {
+            table = table.text(arg, rows, |row| &row.args[index].1);
+        }
+    }
+    Ok(table)
+}
+
+pub(crate) fn decode_errors_table(
+    failures: &[DecodeFailure],
+    timestamps: &HashMap<&String, i64>,
+) -> Result<Table, ParquetStorageError> {
+    let row_blocks = row_blocks(failures, timestamps, |failure| {
+        (failure.block_number, &failure.block_hash)
+    })?;
+    Ok(Table::new("decode_errors", row_blocks)
+        .int("block_number", failures, |failure| failure.block_number)
+        .text("block_hash", failures, |failure| &failure.block_hash)
+        .text("tx_hash", failures, |failure| &failure.tx_hash)
+        .text("address", failures, |failure| &failure.address)
+        .int("log_index", failures, |failure| failure.log_index)
+        .text("topics", failures, |failure| &failure.topics)
+        .text("data", failures, |failure| &failure.data)
+        .text("error", failures, |failure| &failure.error))
+}
+
+fn int_column<'a>(
+    batch: &'a RecordBatch,
+    name: &str,
+) -> Result<&'a Int64Array, ParquetStorageError> {
+    batch
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<Int64Array>())
+        .ok_or_else(|| ParquetStorageError::MissingColumn(name.to_string()))
+}
+
+fn text_column<'a>(
+    batch: &'a RecordBatch,
+    name: &str,
+) -> Result<&'a StringArray, ParquetStorageError> {
+    batch
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+        .ok_or_else(|| ParquetStorageError::MissingColumn(name.to_string()))
+}
+
+/// Block number of every row of the record batch read from the table files
+pub(crate) fn block_numbers<'a>(
+    table: &str,
+    batch: &'a RecordBatch,
+) -> Result<&'a Int64Array, ParquetStorageError> {
+    if table == "blocks" {
+        int_column(batch, "number")
+    } else {
+        int_column(batch, "block_number")
+    }
+}
+
+/// Reads blocks back from the record batch of the blocks files
+pub(crate) fn blocks_from_record_batch(
+    batch: &RecordBatch,
+) -> Result<Vec<Block>, ParquetStorageError> {
+    let number = int_column(batch, "number")?;
+    let hash = text_column(batch, "hash")?;
+    let parent_hash = text_column(batch, "parent_hash")?;
+    let nonce = text_column(batch, "nonce")?;
+    let sha3_uncles = text_column(batch, "sha3_uncles")?;
+    let logs_bloom = text_column(batch, "logs_bloom")?;
+    let transactions_root = text_column(batch, "transactions_root")?;
+    let state_root = text_column(batch, "state_root")?;
+    let receipts_root = text_column(batch, "receipts_root")?;
+    let miner = text_column(batch, "miner")?;
+    let difficulty = text_column(batch, "difficulty")?;
+    let total_difficulty = text_column(batch, "total_difficulty")?;
+    let extra_data = text_column(batch, "extra_data")?;
+    let energy_limit = int_column(batch, "energy_limit")?;
+    let energy_used = int_column(batch, "energy_used")?;
+    let timestamp = int_column(batch, "timestamp")?;
+    let transaction_count = int_column(batch, "transaction_count")?;
+    let matured = int_column(batch, "matured")?;
+    Ok((0..batch.num_rows())
+        .map(|row| Block {
+            number: number.value(row),
+            hash: hash.value(row).to_string(),
+            parent_hash: parent_hash.value(row).to_string(),
+            nonce: nonce.value(row).to_string(),
+            sha3_uncles: sha3_uncles.value(row).to_string(),
+            logs_bloom: logs_bloom.value(row).to_string(),
+            transactions_root: transactions_root.value(row).to_string(),
+            state_root: state_root.value(row).to_string(),
+            receipts_root: receipts_root.value(row).to_string(),
+            miner: miner.value(row).to_string(),
+            difficulty: difficulty.value(row).to_string(),
+            total_difficulty: total_difficulty.value(row).to_string(),
+            extra_data: extra_data.value(row).to_string(),
+            energy_limit: energy_limit.value(row),
+            energy_used: energy_used.value(row),
+            timestamp: timestamp.value(row),
+            transaction_count: transaction_count.value(row),
+            matured: matured.value(row),
+        })
+        .collect())
+}
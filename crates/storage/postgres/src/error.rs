@@ -6,6 +6,11 @@ use thiserror::Error as ThisError;
 pub enum PostgresStorageError {
     #[error(transparent)]
     DatabaseError(#[from] sqlx::Error),
+    /// Rows of the batch are stored with their block, so the block must be in the batch
+    #[error("no block of {0} in the batch")]
+    MissingBlock(String),
+    #[error("invalid timestamp {1} of block {0}")]
+    InvalidTimestamp(i64, i64),
 }
 
 impl From<PostgresStorageError> for Pin<Box<dyn Error + Send + Sync>> {
@@ -2,24 +2,182 @@ use std::{
     collections::{HashMap, HashSet},
     error::Error,
     pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use async_trait::async_trait;
 use chrono::{TimeZone, Utc};
-use sqlx::{postgres::PgPoolOptions, PgPool, Row};
-use storage::Storage;
+use sqlx::{
+    postgres::PgPoolOptions, query_builder::Separated, PgPool, Postgres, QueryBuilder, Row,
+};
+use storage::{has_token_ids, legacy_transfers_table, Migration, Storage, TOKEN_ID_TYPES};
 use tokio::time::{self, Duration};
-use tracing::{debug, error};
-use types::{Block, TokenTransfer, Transaction, TransferType};
+use tracing::{debug, error, info, warn};
+use types::{
+    BalanceChange, Batch, Block, Contract, DecodedTable, IcanAddress, Log, Receipt, TokenBalance,
+    TokenTransfer, Trace, Transaction, TransferType,
+};
 
 use crate::error::PostgresStorageError;
 
+// numeric columns are selected as text, as the types keep values and amounts as decimal strings
+const BLOCK_COLUMNS: &str = "number, hash, parent_hash, nonce, sha3_uncles, logs_bloom, transactions_root, state_root, receipts_root, miner, difficulty::TEXT AS difficulty, total_difficulty::TEXT AS total_difficulty, extra_data, energy_limit, energy_used, timestamp, transaction_count, matured";
+const TRANSACTION_COLUMNS: &str = "hash, nonce, block_hash, block_number, transaction_index, from_addr, to_addr, value::TEXT AS value, energy, energy_price::TEXT AS energy_price, input";
+const TRACE_COLUMNS: &str = "block_number, block_hash, tx_hash, trace_address, depth, call_type, from_addr, to_addr, value::TEXT AS value, error";
+const BALANCE_CHANGE_COLUMNS: &str = "block_number, block_hash, address, delta::TEXT AS delta";
+
+// Secondary indexes created by `create_indexes` as (name, table, columns), which are dropped during the bulk load
+const INDEXES: [(&str, &str, &str); 20] = [
+    ("blocks_hash", "blocks", "hash"),
+    ("blocks_number", "blocks", "number"),
+    ("blocks_matured", "blocks", "matured"),
+    ("transactions_block_hash", "transactions", "block_hash"),
+    ("transactions_from_addr", "transactions", "from_addr"),
+    ("transactions_to_addr", "transactions", "to_addr"),
+    ("receipts_block_number", "receipts", "block_number"),
+    ("receipts_contract_address", "receipts", "contract_address"),
+    ("logs_address", "logs", "address"),
+    ("logs_topic0", "logs", "topic0"),
+    ("logs_block_number", "logs", "block_number"),
+    ("traces_from_addr", "traces", "from_addr"),
+    ("traces_to_addr", "traces", "to_addr"),
+    ("traces_block_number", "traces", "block_number"),
+    (
+        "balance_changes_address",
+        "balance_changes",
+        "address, block_number",
+    ),
+    ("contracts_creator", "contracts", "creator"),
+    ("contracts_block_number", "contracts", "block_number"),
+    (
+        "decode_errors_block_number",
+        "decode_errors",
+        "block_number",
+    ),
+    ("orphaned_blocks_number", "orphaned_blocks", "number"),
+    (
+        "orphaned_transactions_block_hash",
+        "orphaned_transactions",
+        "block_hash",
+    ),
+];
+
+// Postgres allows up to 65535 bind parameters in a single statement
+const BIND_LIMIT: usize = 65535;
+
+/// Inserts the rows with bound parameters. Rows are split into statements which fit into the bind limit.
+/// The statement is not executed if any of its rows fails to be bound
+async fn insert_rows<'a, T>(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    table: &str,
+    columns: &str,
+    rows: &'a [T],
+    push_row: impl Fn(
+        &mut Separated<'_, 'a, Postgres, &'static str>,
+        &'a T,
+    ) -> Result<(), PostgresStorageError>,
+) -> Result<(), PostgresStorageError> {
+    let chunk_size = BIND_LIMIT / columns.split(',').count();
+    for chunk in rows.chunks(chunk_size) {
+        let mut query = QueryBuilder::new(format!("INSERT INTO {} ({}) ", table, columns));
+        let mut result = Ok(());
+        query.push_values(chunk, |mut row, value| {
+            if result.is_ok() {
+                result = push_row(&mut row, value);
+            }
+        });
+        result?;
+        query.build().execute(&mut *tx).await?;
+    }
+    Ok(())
+}
+
+/// Returns the value of the block of the row, which is looked up by the block or transaction hash
+fn block_value<T: Copy>(
+    map: &HashMap<&String, T>,
+    hash: &String,
+) -> Result<T, PostgresStorageError> {
+    map.get(hash)
+        .copied()
+        .ok_or_else(|| PostgresStorageError::MissingBlock(hash.clone()))
+}
+
+// rows loaded with COPY are sent to the server in chunks of about 1 MB
+const COPY_CHUNK_SIZE: usize = 1 << 20;
+
+/// Quotes the column name which comes from the ABI
+fn quoted(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quotes the text value for the CSV rows loaded with COPY
+fn csv(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// Loads the rows with `COPY FROM STDIN`, which is much faster than inserts for big batches.
+/// `format_row` formats the row as a CSV line, text values must be quoted with `csv`.
+/// The copy is aborted if any of the rows fails to be formatted
+async fn copy_rows<T>(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    table: &str,
+    columns: &str,
+    rows: &[T],
+    format_row: impl Fn(&T) -> Result<String, PostgresStorageError>,
+) -> Result<(), PostgresStorageError> {
+    let mut copy = tx
+        .copy_in_raw(&format!(
+            "COPY {} ({}) FROM STDIN WITH (FORMAT csv)",
+            table, columns
+        ))
+        .await?;
+    let mut data = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        match format_row(row) {
+            Ok(line) => data.push_str(&line),
+            Err(e) => {
+                copy.abort(e.to_string()).await.ok();
+                return Err(e);
+            }
+        }
+        data.push('\n');
+        if data.len() >= COPY_CHUNK_SIZE || i + 1 == rows.len() {
+            if let Err(e) = copy.send(data.as_bytes()).await {
+                copy.abort(e.to_string()).await.ok();
+                return Err(e.into());
+            }
+            data.clear();
+        }
+    }
+    copy.finish().await?;
+    Ok(())
+}
+
+/// Columns of the token transfers table with numeric columns selected as text.
+/// Only NFT and multi-token tables have token IDs and batch indexes, so the columns are added to keep unions of the tables valid
+fn transfer_columns(table: &str) -> String {
+    let token_id_column = if has_token_ids(table) {
+        "token_id::TEXT AS token_id, batch_index"
+    } else {
+        "'' AS token_id, 0::BIGINT AS batch_index"
+    };
+    format!(
+        "block_number, from_addr, to_addr, value::TEXT AS value, tx_hash, address, transfer_index, status, {}",
+        token_id_column
+    )
+}
+
 #[derive(Debug, Clone)]
 pub struct PostgresStorage {
     pub db_dsn: String,
     pub pool: PgPool,
     pub tables_prefix: String,
     pub modules: Vec<String>,
+    // set during the bulk load of historical blocks, see `set_bulk_mode`
+    bulk_mode: Arc<AtomicBool>,
 }
 
 impl PostgresStorage {
@@ -39,11 +197,16 @@ impl PostgresStorage {
             pool,
             tables_prefix,
             modules,
+            bulk_mode: Default::default(),
         })
     }
 
-    pub async fn migrate(&self) -> Result<(), sqlx::Error> {
-        debug!("Migrating database tables");
+    /// Creates tables of all modules, the first migration
+    async fn create_tables(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        debug!("Creating database tables");
         let block_hash_foreign_key = if self.modules.contains(&"blocks".to_string()) {
             format!(
                 "REFERENCES {0}_blocks(hash) ON DELETE CASCADE ON UPDATE CASCADE",
@@ -65,8 +228,8 @@ impl PostgresStorage {
                 state_root VARCHAR(64),
                 receipts_root VARCHAR(64),
                 miner VARCHAR(44),
-                difficulty VARCHAR(64),
-                total_difficulty VARCHAR(64),
+                difficulty NUMERIC(78, 0),
+                total_difficulty NUMERIC(78, 0),
                 extra_data TEXT,
                 energy_limit BIGINT,
                 energy_used BIGINT,
@@ -89,9 +252,9 @@ impl PostgresStorage {
                 transaction_index BIGINT,
                 from_addr VARCHAR(44),
                 to_addr VARCHAR(44),
-                value VARCHAR(64),
+                value NUMERIC(78, 0),
                 energy VARCHAR(64),
-                energy_price VARCHAR(64),
+                energy_price NUMERIC(78, 0),
                 input TEXT,
                 created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
             );
@@ -99,39 +262,610 @@ impl PostgresStorage {
             self.tables_prefix, block_hash_foreign_key
         );
 
-        sqlx::query(&create_blocks_table)
-            .execute(&self.pool)
-            .await?;
+        let tx_hash_foreign_key = if self.modules.contains(&"transactions".to_string()) {
+            format!(
+                "REFERENCES {0}_transactions(hash) ON DELETE CASCADE ON UPDATE CASCADE",
+                self.tables_prefix
+            )
+        } else {
+            "".to_string()
+        };
+        let create_receipts_table = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {0}_receipts (
+                transaction_hash VARCHAR(64) PRIMARY KEY {1},
+                block_hash VARCHAR(64),
+                block_number BIGINT,
+                transaction_index BIGINT,
+                status BIGINT,
+                energy_used BIGINT,
+                cumulative_energy_used BIGINT,
+                effective_energy_price VARCHAR(64),
+                contract_address VARCHAR(44),
+                logs_bloom TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+        "#,
+            self.tables_prefix, tx_hash_foreign_key
+        );
+        let create_logs_table = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {0}_logs (
+                block_number BIGINT,
+                block_hash VARCHAR(64),
+                tx_hash VARCHAR(64) NOT NULL {1},
+                log_index BIGINT NOT NULL,
+                address VARCHAR(44),
+                topic0 VARCHAR(64),
+                topic1 VARCHAR(64),
+                topic2 VARCHAR(64),
+                topic3 VARCHAR(64),
+                data TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (tx_hash, log_index)
+            );
+        "#,
+            self.tables_prefix, tx_hash_foreign_key
+        );
+        let create_traces_table = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {0}_traces (
+                block_number BIGINT,
+                block_hash VARCHAR(64),
+                tx_hash VARCHAR(64) NOT NULL {1},
+                trace_address TEXT NOT NULL,
+                depth BIGINT,
+                call_type VARCHAR(16),
+                from_addr VARCHAR(44),
+                to_addr VARCHAR(44),
+                value NUMERIC(78, 0),
+                error TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (tx_hash, trace_address)
+            );
+        "#,
+            self.tables_prefix, tx_hash_foreign_key
+        );
+        let create_balance_changes_table = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}_balance_changes (
+                block_number BIGINT NOT NULL,
+                block_hash VARCHAR(64),
+                address VARCHAR(44) NOT NULL,
+                delta NUMERIC(78, 0) NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (block_number, address)
+            );
+        "#,
+            self.tables_prefix
+        );
+
+        let create_contracts_table = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}_contracts (
+                address VARCHAR(44) PRIMARY KEY,
+                creator VARCHAR(44) NOT NULL,
+                tx_hash VARCHAR(64) NOT NULL,
+                block_number BIGINT NOT NULL,
+                block_hash VARCHAR(64),
+                bytecode_hash VARCHAR(64),
+                is_cbc20 BIGINT NOT NULL DEFAULT 0,
+                name TEXT,
+                symbol TEXT,
+                decimals BIGINT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+        "#,
+            self.tables_prefix
+        );
+
+        let create_decode_errors_table = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}_decode_errors (
+                block_number BIGINT NOT NULL,
+                block_hash VARCHAR(64),
+                tx_hash VARCHAR(64) NOT NULL,
+                address VARCHAR(44) NOT NULL,
+                log_index BIGINT NOT NULL,
+                topics TEXT,
+                data TEXT,
+                error TEXT NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+        "#,
+            self.tables_prefix
+        );
+
+        // discovered tokens are kept on rollbacks, as a token stays a token on any chain
+        let create_discovered_tokens_table = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}_discovered_tokens (
+                address VARCHAR(44) PRIMARY KEY,
+                token VARCHAR(32) NOT NULL,
+                block_number BIGINT NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+        "#,
+            self.tables_prefix
+        );
+
+        let create_orphaned_blocks_table = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}_orphaned_blocks (
+                number BIGINT NOT NULL,
+                hash VARCHAR(64),
+                parent_hash VARCHAR(64),
+                nonce VARCHAR(64),
+                sha3_uncles VARCHAR(64),
+                logs_bloom TEXT,
+                transactions_root VARCHAR(64),
+                state_root VARCHAR(64),
+                receipts_root VARCHAR(64),
+                miner VARCHAR(44),
+                difficulty NUMERIC(78, 0),
+                total_difficulty NUMERIC(78, 0),
+                extra_data TEXT,
+                energy_limit BIGINT,
+                energy_used BIGINT,
+                timestamp BIGINT,
+                transaction_count BIGINT,
+                matured BIGINT,
+                created_at TIMESTAMP,
+                reorg_depth BIGINT NOT NULL,
+                detected_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+        "#,
+            self.tables_prefix
+        );
+
+        let create_orphaned_transactions_table = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}_orphaned_transactions (
+                hash VARCHAR(64) NOT NULL,
+                nonce VARCHAR(64),
+                block_hash VARCHAR(64),
+                block_number BIGINT,
+                transaction_index BIGINT,
+                from_addr VARCHAR(44),
+                to_addr VARCHAR(44),
+                value NUMERIC(78, 0),
+                energy VARCHAR(64),
+                energy_price NUMERIC(78, 0),
+                input TEXT,
+                created_at TIMESTAMP,
+                reorg_depth BIGINT NOT NULL,
+                detected_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+        "#,
+            self.tables_prefix
+        );
+
+        sqlx::query(&create_blocks_table).execute(&mut *tx).await?;
         sqlx::query(&create_transactions_table)
-            .execute(&self.pool)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&create_receipts_table)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&create_logs_table).execute(&mut *tx).await?;
+        sqlx::query(&create_traces_table).execute(&mut *tx).await?;
+        sqlx::query(&create_balance_changes_table)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&create_contracts_table)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&create_decode_errors_table)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&create_discovered_tokens_table)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&create_orphaned_blocks_table)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&create_orphaned_transactions_table)
+            .execute(&mut *tx)
             .await?;
         Ok(())
     }
+
+    /// Converts values and amounts stored as text by the previous versions to NUMERIC columns.
+    /// Only blocks, transactions and CBC20 transfers were stored by them, tables of the other modules are
+    /// created with NUMERIC columns. Token values were stored as zero padded hex, the others as decimal strings
+    async fn migrate_numeric_columns(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        let mut columns = vec![];
+        for column in ["difficulty", "total_difficulty"] {
+            columns.push((format!("{}_blocks", self.tables_prefix), column, false));
+        }
+        for column in ["value", "energy_price"] {
+            columns.push((
+                format!("{}_transactions", self.tables_prefix),
+                column,
+                false,
+            ));
+        }
+        let token_tables = sqlx::query(
+            "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE $1",
+        )
+        .bind(format!("{}_cbc20_%_transfers", self.tables_prefix))
+        .fetch_all(&mut *tx)
+        .await?;
+        for row in token_tables {
+            columns.push((row.get::<String, _>("table_name"), "value", true));
+        }
+
+        let mut pending = vec![];
+        for (table, column, hex) in columns {
+            let row = sqlx::query(
+                "SELECT data_type FROM information_schema.columns WHERE table_schema = 'public' AND table_name = $1 AND column_name = $2",
+            )
+            .bind(&table)
+            .bind(column)
+            .fetch_optional(&mut *tx)
+            .await?;
+            if let Some(row) = row {
+                if row.get::<String, _>("data_type") != "numeric" {
+                    pending.push((table, column, hex));
+                }
+            }
+        }
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        // the function is temporary, so it is only visible in the migration transaction
+        sqlx::query(
+            r#"
+            CREATE OR REPLACE FUNCTION pg_temp.hex_to_numeric(hex TEXT) RETURNS NUMERIC AS $$
+                SELECT COALESCE(SUM(('x' || SUBSTR(padded, i, 8))::BIT(32)::BIGINT * POWER(2::NUMERIC, 4 * (LENGTH(padded) - i - 7))), 0)::NUMERIC(78, 0)
+                FROM (SELECT LPAD(hex, (LENGTH(hex) + 7) / 8 * 8, '0') AS padded) AS p, GENERATE_SERIES(1, LENGTH(padded), 8) AS i
+            $$ LANGUAGE SQL IMMUTABLE;
+        "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+        for (table, column, hex) in pending {
+            info!("Migrating {}.{} to a numeric column", table, column);
+            let value = if hex {
+                format!("pg_temp.hex_to_numeric({})", column)
+            } else {
+                format!("{}::NUMERIC(78, 0)", column)
+            };
+            sqlx::query(
+                format!(
+                    "ALTER TABLE {0} ALTER COLUMN {1} TYPE NUMERIC(78, 0) USING {2}",
+                    table, column, value
+                )
+                .as_str(),
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Renames token tables which were named by the first 8 chars of the token address.
+    /// Empty tables are dropped, as they are created with the new names at start.
+    /// Tables with transfers of several tokens are kept, so these tokens have to be extracted again
+    async fn migrate_token_table_names(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        let tables = sqlx::query(
+            format!("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE '{}_%_transfers';", self.tables_prefix).as_str(),
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for row in tables {
+            let table = row.get::<String, _>("table_name");
+            let Some((token, id)) = legacy_transfers_table(&self.tables_prefix, &table) else {
+                continue;
+            };
+            let addresses = sqlx::query(format!("SELECT DISTINCT address FROM {}", table).as_str())
+                .fetch_all(&mut *tx)
+                .await?
+                .into_iter()
+                .map(|row| row.get::<String, _>("address"))
+                .collect::<Vec<_>>();
+            if addresses.len() > 1 {
+                warn!(
+                    "Table {} has transfers of tokens {:?}, they have to be extracted again",
+                    table, addresses
+                );
+                continue;
+            }
+            let mut suffixes = vec!["transfers"];
+            if token == "cbc20" {
+                suffixes.push("balances");
+                // the index is created again with the name of the renamed table
+                sqlx::query(
+                    format!(
+                        "DROP INDEX IF EXISTS idx_{}_cbc20_{}_balances_balance;",
+                        self.tables_prefix, id
+                    )
+                    .as_str(),
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            for suffix in suffixes {
+                let old_table = format!("{}_{}_{}_{}", self.tables_prefix, token, id, suffix);
+                let query = match addresses.first() {
+                    Some(address) => format!(
+                        "ALTER TABLE IF EXISTS {} RENAME TO {}_{}_{}_{}",
+                        old_table,
+                        self.tables_prefix,
+                        token,
+                        IcanAddress::table_id(address),
+                        suffix
+                    ),
+                    None => format!("DROP TABLE IF EXISTS {}", old_table),
+                };
+                info!("Migrating token table {}", old_table);
+                sqlx::query(&query).execute(&mut *tx).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the latest block number seen by the transaction, so it matches the rows changed in it.
+    /// Only blocks and transactions are archived, so the number is read from their tables
+    async fn latest_archived_block(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+    ) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            "SELECT COALESCE((SELECT MAX(number) FROM {0}_blocks), (SELECT MAX(block_number) FROM {0}_transactions), 0) AS number",
+            self.tables_prefix
+        ))
+        .fetch_one(&mut *tx)
+        .await?;
+        Ok(row.get::<i64, _>("number"))
+    }
+
+    /// Copies blocks and transactions in the given range to the orphaned tables
+    /// so they are not lost when the blocks are removed after a reorg.
+    async fn archive_orphaned_blocks(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        from: i64,
+        to: i64,
+        reorg_depth: i64,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        let block_columns = "number, hash, parent_hash, nonce, sha3_uncles, logs_bloom, transactions_root, state_root, receipts_root, miner, difficulty, total_difficulty, extra_data, energy_limit, energy_used, timestamp, transaction_count, matured, created_at";
+        let archive_blocks_query = format!(
+            "INSERT INTO {0}_orphaned_blocks ({1}, reorg_depth) SELECT {1}, $1 FROM {0}_blocks WHERE number >= $2 AND number <= $3",
+            self.tables_prefix, block_columns
+        );
+        let result = sqlx::query(&archive_blocks_query)
+            .bind(reorg_depth)
+            .bind(from)
+            .bind(to)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        debug!("Archived orphaned blocks: {:?}", result.rows_affected());
+
+        let transaction_columns = "hash, nonce, block_hash, block_number, transaction_index, from_addr, to_addr, value, energy, energy_price, input, created_at";
+        let archive_txs_query = format!(
+            "INSERT INTO {0}_orphaned_transactions ({1}, reorg_depth) SELECT {1}, $1 FROM {0}_transactions WHERE block_number >= $2 AND block_number <= $3",
+            self.tables_prefix, transaction_columns
+        );
+        let result = sqlx::query(&archive_txs_query)
+            .bind(reorg_depth)
+            .bind(from)
+            .bind(to)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        debug!(
+            "Archived orphaned transactions: {:?}",
+            result.rows_affected()
+        );
+
+        Ok(())
+    }
+
+    /// Adds values of the token transfers to the balances of the holders.
+    /// If `revert` is set, transfers are subtracted instead, e.g. when their blocks are removed
+    async fn update_token_balances(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        balances_table: &str,
+        transfers: &[TokenTransfer],
+        revert: bool,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        let changes = TokenBalance::changes(transfers)
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        for (holder, (received, sent)) in changes {
+            let (received, sent) = if revert {
+                (sent, received)
+            } else {
+                (received, sent)
+            };
+            let row = sqlx::query(
+                format!(
+                    "SELECT balance::TEXT AS balance FROM {} WHERE holder = $1 FOR UPDATE",
+                    balances_table
+                )
+                .as_str(),
+            )
+            .bind(&holder)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            let balance = TokenBalance::apply(
+                row.map(|row| row.get::<String, _>("balance")).as_deref(),
+                received,
+                sent,
+            )
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            let upsert_query = format!(
+                "INSERT INTO {} (holder, balance) VALUES ($1, $2::NUMERIC) ON CONFLICT (holder) DO UPDATE SET balance = EXCLUDED.balance",
+                balances_table
+            );
+            sqlx::query(&upsert_query)
+                .bind(&holder)
+                .bind(&balance)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        }
+        Ok(())
+    }
+
+    /// Reverts balances changes made by the transfers of the watched CBC20 tokens in the blocks range.
+    /// Must be called before the transfers are removed
+    async fn revert_token_balances(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        from: i64,
+        to: i64,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        let stmt = sqlx::query(
+            format!("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE '{}_cbc20_%_balances';", self.tables_prefix).as_str(),
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let table_names: Vec<String> = stmt
+            .iter()
+            .map(|row| row.get::<String, _>("table_name"))
+            .collect();
+        for balances_table in table_names {
+            let transfers = sqlx::query_as::<_, TokenTransfer>(
+                format!(
+                    "SELECT {} FROM {}_transfers WHERE block_number >= $1 AND block_number <= $2",
+                    transfer_columns(&balances_table),
+                    balances_table.trim_end_matches("_balances"),
+                )
+                .as_str(),
+            )
+            .bind(from)
+            .bind(to)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            self.update_token_balances(tx, &balances_table, &transfers, true)
+                .await?;
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl Storage for PostgresStorage {
     async fn prepare_db(&self) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
-        self.migrate().await.map_err(PostgresStorageError::from)?;
+        self.run_migrations(false).await?;
         self.create_indexes().await?;
         Ok(())
     }
 
+    async fn schema_version(&self) -> Result<i64, Pin<Box<dyn Error + Send + Sync>>> {
+        let table = sqlx::query(
+            format!("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name = '{}_schema_version';", self.tables_prefix).as_str(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(PostgresStorageError::from)?;
+        if table.is_none() {
+            return Ok(0);
+        }
+        let row = sqlx::query(
+            format!(
+                "SELECT COALESCE(MAX(version), 0) AS version FROM {}_schema_version",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(PostgresStorageError::from)?;
+        Ok(row.get::<i64, _>("version"))
+    }
+
+    async fn apply_migration(
+        &self,
+        migration: Migration,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        info!("Applying migration {}", migration);
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(PostgresStorageError::from)?;
+        sqlx::query(
+            format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS {}_schema_version (
+                    version BIGINT PRIMARY KEY,
+                    description TEXT NOT NULL,
+                    applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                );
+            "#,
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(PostgresStorageError::from)?;
+        match migration {
+            Migration::CreateTables => self.create_tables(&mut tx).await,
+            Migration::NumericValues => self.migrate_numeric_columns(&mut tx).await,
+            Migration::TokenTableNames => self.migrate_token_table_names(&mut tx).await,
+        }
+        .map_err(PostgresStorageError::from)?;
+        sqlx::query(
+            format!(
+                "INSERT INTO {}_schema_version (version, description) VALUES ({}, '{}')",
+                self.tables_prefix,
+                migration.version(),
+                migration.description()
+            )
+            .as_str(),
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(PostgresStorageError::from)?;
+        tx.commit().await.map_err(PostgresStorageError::from)?;
+        Ok(())
+    }
+
     async fn create_indexes(&self) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
-        let indexes = vec![
-            format!("CREATE INDEX IF NOT EXISTS idx_{0}_blocks_hash ON {0}_blocks (hash);", self.tables_prefix),
-            format!("CREATE INDEX IF NOT EXISTS idx_{0}_blocks_number ON {0}_blocks (number);", self.tables_prefix),
-            format!("CREATE INDEX IF NOT EXISTS idx_{0}_blocks_matured ON {0}_blocks (matured);", self.tables_prefix),
-            format!("CREATE INDEX IF NOT EXISTS idx_{0}_transactions_block_hash ON {0}_transactions(block_hash);", self.tables_prefix),
-            format!("CREATE INDEX IF NOT EXISTS idx_{0}_transactions_from_addr ON {0}_transactions(from_addr);", self.tables_prefix),
-            format!("CREATE INDEX IF NOT EXISTS idx_{0}_transactions_to_addr ON {0}_transactions(to_addr);", self.tables_prefix),
-        ];
-
-        for index in indexes {
-            sqlx::query(&index)
+        for (name, table, columns) in INDEXES {
+            sqlx::query(&format!(
+                "CREATE INDEX IF NOT EXISTS idx_{0}_{1} ON {0}_{2} ({3});",
+                self.tables_prefix, name, table, columns
+            ))
+            .execute(&self.pool)
+            .await
+            .map_err(PostgresStorageError::from)?;
+        }
+        Ok(())
+    }
+
+    async fn set_bulk_mode(&self, enabled: bool) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        if enabled {
+            // indexes are built once after the load, which is faster than updating them on every batch
+            for (name, _, _) in INDEXES {
+                sqlx::query(&format!(
+                    "DROP INDEX IF EXISTS idx_{}_{};",
+                    self.tables_prefix, name
+                ))
                 .execute(&self.pool)
                 .await
                 .map_err(PostgresStorageError::from)?;
+            }
+            self.bulk_mode.store(true, Ordering::Relaxed);
+        } else {
+            self.bulk_mode.store(false, Ordering::Relaxed);
+            self.create_indexes().await?;
         }
         Ok(())
     }
@@ -139,8 +873,8 @@ impl Storage for PostgresStorage {
     async fn get_latest_block_number(&self) -> Result<i64, Pin<Box<dyn Error + Send + Sync>>> {
         let result = sqlx::query_as::<_, Block>(
             format!(
-                "SELECT * FROM {}_blocks ORDER BY number DESC LIMIT 1",
-                self.tables_prefix
+                "SELECT {} FROM {}_blocks ORDER BY number DESC LIMIT 1",
+                BLOCK_COLUMNS, self.tables_prefix
             )
             .as_str(),
         )
@@ -153,8 +887,8 @@ impl Storage for PostgresStorage {
                 // fetch block number from transactions table
                 let result = sqlx::query_as::<_, Transaction>(
                     format!(
-                        "SELECT * FROM {}_transactions ORDER BY block_number DESC LIMIT 1",
-                        self.tables_prefix
+                        "SELECT {} FROM {}_transactions ORDER BY block_number DESC LIMIT 1",
+                        TRANSACTION_COLUMNS, self.tables_prefix
                     )
                     .as_str(),
                 )
@@ -176,7 +910,8 @@ impl Storage for PostgresStorage {
                             Some(table_name) => {
                                 let result = sqlx::query_as::<_, TokenTransfer>(
                                     format!(
-                                        "SELECT * FROM {} ORDER BY block_number DESC LIMIT 1",
+                                        "SELECT {} FROM {} ORDER BY block_number DESC LIMIT 1",
+                                        transfer_columns(&table_name),
                                         table_name
                                     )
                                     .as_str(),
@@ -206,11 +941,13 @@ impl Storage for PostgresStorage {
     ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
         let result = sqlx::query(
             format!(
-                "UPDATE {}_blocks SET matured = 1 WHERE number >= {} AND number <= {} AND matured = 0",
-                self.tables_prefix, from, to
+                "UPDATE {}_blocks SET matured = 1 WHERE number >= $1 AND number <= $2 AND matured = 0",
+                self.tables_prefix
             )
             .as_str(),
         )
+        .bind(from)
+        .bind(to)
         .execute(&self.pool)
         .await
         .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
@@ -229,7 +966,7 @@ impl Storage for PostgresStorage {
                     "{}_{}_{}_transfers",
                     self.tables_prefix,
                     token,
-                    &address[..8]
+                    IcanAddress::table_id(&address)
                 );
                 let tx_hash_foreign_key = if self.modules.contains(&"transactions".to_string()) {
                     format!(
@@ -239,21 +976,33 @@ impl Storage for PostgresStorage {
                 } else {
                     "".to_string()
                 };
+                // NFT and multi-token transfers have an ID of the transferred token,
+                // rows of a multi-token batch share the transfer index and differ by the batch index
+                let (token_id_column, unique_constraint) =
+                    if TOKEN_ID_TYPES.contains(&token.as_str()) {
+                        (
+                        "token_id NUMERIC(78, 0) NOT NULL, batch_index BIGINT NOT NULL DEFAULT 0,",
+                        ", UNIQUE (tx_hash, transfer_index, batch_index)",
+                    )
+                    } else {
+                        ("", ", UNIQUE (tx_hash, transfer_index)")
+                    };
                 let create_table_query = format!(
                     "CREATE TABLE IF NOT EXISTS {table_name} (
                     id SERIAL PRIMARY KEY,
                     block_number BIGINT,
                     from_addr VARCHAR(44) NOT NULL,
                     to_addr VARCHAR(44) NOT NULL,
-                    value VARCHAR(64) NOT NULL,
+                    value NUMERIC(78, 0) NOT NULL,
                     tx_hash VARCHAR(64) {0},
                     address VARCHAR(44) NOT NULL,
                     transfer_index BIGINT NOT NULL,
                     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                    status BIGINT DEFAULT 0,
-                    UNIQUE (tx_hash, transfer_index)
+                    {1}
+                    status BIGINT DEFAULT 0
+                    {2}
                 );",
-                    tx_hash_foreign_key
+                    tx_hash_foreign_key, token_id_column, unique_constraint
                 );
 
                 let result = sqlx::query(&create_table_query)
@@ -265,43 +1014,190 @@ impl Storage for PostgresStorage {
                     "Create {:?} token transfers table result: {:?}",
                     table_name, result
                 );
+
+                if token == "cbc20" {
+                    let balances_table_name = format!(
+                        "{}_{}_{}_balances",
+                        self.tables_prefix,
+                        token,
+                        IcanAddress::table_id(&address)
+                    );
+                    let create_table_query = format!(
+                        "CREATE TABLE IF NOT EXISTS {balances_table_name} (
+                        holder VARCHAR(44) PRIMARY KEY,
+                        balance NUMERIC(78, 0) NOT NULL
+                    );"
+                    );
+                    sqlx::query(&create_table_query)
+                        .execute(&self.pool)
+                        .await
+                        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                    sqlx::query(
+                        format!(
+                            "CREATE INDEX IF NOT EXISTS idx_{0}_balance ON {0}(balance);",
+                            balances_table_name
+                        )
+                        .as_str(),
+                    )
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                    debug!("Created token balances table: {}", balances_table_name);
+                }
             }
         }
         Ok(())
     }
 
-    async fn clean_block_data(
+    async fn create_decoded_tables(
         &self,
-        block_number: i64,
+        tables: Vec<DecodedTable>,
     ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        for table in tables {
+            let table_name = format!("{}_{}", self.tables_prefix, table.name);
+            let column_type = |abi_type: &str| {
+                if abi_type.contains('[') || abi_type.contains('(') {
+                    "TEXT"
+                } else if abi_type.starts_with("uint") || abi_type.starts_with("int") {
+                    "NUMERIC(78, 0)"
+                } else if abi_type == "bool" {
+                    "BOOLEAN"
+                } else if abi_type == "address" {
+                    "VARCHAR(44)"
+                } else {
+                    "TEXT"
+                }
+            };
+            // argument columns are quoted, as their names come from the ABI
+            let query = format!(
+                "CREATE TABLE IF NOT EXISTS {table_name} (
+                block_number BIGINT NOT NULL,
+                block_hash VARCHAR(64),
+                tx_hash VARCHAR(64) NOT NULL,
+                address VARCHAR(44) NOT NULL,
+                log_index BIGINT NOT NULL,
+                status BIGINT DEFAULT 0,
+                {}
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+                table
+                    .columns
+                    .iter()
+                    .map(|(name, abi_type)| format!("{} {},", quoted(name), column_type(abi_type)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+            sqlx::query(&query)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            sqlx::query(
+                format!(
+                    "CREATE INDEX IF NOT EXISTS idx_{0}_block_number ON {0} (block_number);",
+                    table_name
+                )
+                .as_str(),
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            debug!("Created decoded table: {}", table_name);
+        }
+        Ok(())
+    }
+
+    async fn rollback_blocks(&self, from: i64) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
         let mut tx = self
             .pool
             .begin()
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        // the depth is read in the transaction, so blocks inserted meanwhile are counted
+        let reorg_depth = self
+            .latest_archived_block(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?
+            - from
+            + 1;
+
+        self.archive_orphaned_blocks(&mut tx, from, i64::MAX, reorg_depth)
+            .await?;
+        self.revert_token_balances(&mut tx, from, i64::MAX).await?;
 
         let delete_blocks_query = format!(
-            "DELETE FROM {}_blocks WHERE number = {}",
-            self.tables_prefix, block_number
+            "DELETE FROM {}_blocks WHERE number >= $1",
+            self.tables_prefix
         );
         sqlx::query(&delete_blocks_query)
+            .bind(from)
             .execute(&mut tx)
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
         let delete_txs_query = format!(
-            "DELETE FROM {}_transactions WHERE block_number = {}",
-            self.tables_prefix, block_number
+            "DELETE FROM {}_transactions WHERE block_number >= $1",
+            self.tables_prefix
         );
         sqlx::query(&delete_txs_query)
+            .bind(from)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let delete_receipts_query = format!(
+            "DELETE FROM {}_receipts WHERE block_number >= $1",
+            self.tables_prefix
+        );
+        sqlx::query(&delete_receipts_query)
+            .bind(from)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let delete_logs_query = format!(
+            "DELETE FROM {}_logs WHERE block_number >= $1",
+            self.tables_prefix
+        );
+        sqlx::query(&delete_logs_query)
+            .bind(from)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let delete_traces_query = format!(
+            "DELETE FROM {}_traces WHERE block_number >= $1",
+            self.tables_prefix
+        );
+        sqlx::query(&delete_traces_query)
+            .bind(from)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let delete_balance_changes_query = format!(
+            "DELETE FROM {}_balance_changes WHERE block_number >= $1",
+            self.tables_prefix
+        );
+        sqlx::query(&delete_balance_changes_query)
+            .bind(from)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let delete_contracts_query = format!(
+            "DELETE FROM {}_contracts WHERE block_number >= $1",
+            self.tables_prefix
+        );
+        sqlx::query(&delete_contracts_query)
+            .bind(from)
             .execute(&mut tx)
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
         let stmt = sqlx::query(
-                format!("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE '{}_%_transfers';", self.tables_prefix).as_str(),
+                format!("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND (table_name LIKE '{0}_%_transfers' OR table_name LIKE '{0}_%_calls' OR table_name LIKE '{0}_%_events' OR table_name = '{0}_decode_errors');", self.tables_prefix).as_str(),
             )
-            .fetch_all(&self.pool)
+            .fetch_all(&mut tx)
             .await
             .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
 
@@ -310,11 +1206,9 @@ impl Storage for PostgresStorage {
             .map(|row| row.get::<String, _>("table_name"))
             .collect();
         for table in table_names {
-            let delete_transfers_query = format!(
-                "DELETE FROM {} WHERE block_number = {}",
-                table, block_number
-            );
+            let delete_transfers_query = format!("DELETE FROM {} WHERE block_number >= $1", table);
             sqlx::query(&delete_transfers_query)
+                .bind(from)
                 .execute(&mut tx)
                 .await
                 .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
@@ -324,39 +1218,87 @@ impl Storage for PostgresStorage {
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
-        Ok(())
-    }
+        Ok(())
+    }
+
+    async fn clean_last_blocks(
+        &self,
+        number: i64,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        let from = self.get_latest_block_number().await? - number + 1;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        self.revert_token_balances(&mut tx, from, i64::MAX).await?;
+
+        let delete_blocks_query = format!(
+            "DELETE FROM {}_blocks WHERE number > (SELECT max(number) FROM {}_blocks) - {}",
+            self.tables_prefix, self.tables_prefix, number
+        );
+        sqlx::query(&delete_blocks_query)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let delete_txs_query = format!(
+            "DELETE FROM {}_transactions WHERE block_number > (SELECT max(block_number) FROM {}_transactions) - {}",
+            self.tables_prefix, self.tables_prefix, number
+        );
+        sqlx::query(&delete_txs_query)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let delete_receipts_query = format!(
+            "DELETE FROM {}_receipts WHERE block_number > (SELECT max(block_number) FROM {}_receipts) - {}",
+            self.tables_prefix, self.tables_prefix, number
+        );
+        sqlx::query(&delete_receipts_query)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let delete_logs_query = format!(
+            "DELETE FROM {}_logs WHERE block_number > (SELECT max(block_number) FROM {}_logs) - {}",
+            self.tables_prefix, self.tables_prefix, number
+        );
+        sqlx::query(&delete_logs_query)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
-    async fn clean_last_blocks(
-        &self,
-        number: i64,
-    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
-        let mut tx = self
-            .pool
-            .begin()
+        let delete_traces_query = format!(
+            "DELETE FROM {}_traces WHERE block_number > (SELECT max(block_number) FROM {}_traces) - {}",
+            self.tables_prefix, self.tables_prefix, number
+        );
+        sqlx::query(&delete_traces_query)
+            .execute(&mut tx)
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
-        let delete_blocks_query = format!(
-            "DELETE FROM {}_blocks WHERE number > (SELECT max(number) FROM {}_blocks) - {}",
+        let delete_balance_changes_query = format!(
+            "DELETE FROM {}_balance_changes WHERE block_number > (SELECT max(block_number) FROM {}_balance_changes) - {}",
             self.tables_prefix, self.tables_prefix, number
         );
-        sqlx::query(&delete_blocks_query)
+        sqlx::query(&delete_balance_changes_query)
             .execute(&mut tx)
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
-        let delete_txs_query = format!(
-            "DELETE FROM {}_transactions WHERE block_number > (SELECT max(block_number) FROM {}_transactions) - {}",
+        let delete_contracts_query = format!(
+            "DELETE FROM {}_contracts WHERE block_number > (SELECT max(block_number) FROM {}_contracts) - {}",
             self.tables_prefix, self.tables_prefix, number
         );
-        sqlx::query(&delete_txs_query)
+        sqlx::query(&delete_contracts_query)
             .execute(&mut tx)
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
         let stmt = sqlx::query(
-                format!("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE '{}_%_transfers';", self.tables_prefix).as_str(),
+                format!("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND (table_name LIKE '{0}_%_transfers' OR table_name LIKE '{0}_%_calls' OR table_name LIKE '{0}_%_events' OR table_name = '{0}_decode_errors');", self.tables_prefix).as_str(),
             )
             .fetch_all(&self.pool)
             .await
@@ -387,86 +1329,421 @@ impl Storage for PostgresStorage {
     async fn insert_blocks_with_txs_and_token_transfers(
         &self,
         insert_all: bool,
-        blocks: &mut Vec<Block>,
-        transactions: &mut Vec<Transaction>,
-        token_transfers: &mut HashMap<String, Vec<TokenTransfer>>,
+        batch: &mut Batch,
     ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
-        if blocks.len() > 500 || transactions.len() > 500 || insert_all {
+        let ready = self.is_batch_ready(batch, insert_all);
+        let Batch {
+            blocks,
+            transactions,
+            token_transfers,
+            receipts,
+            logs,
+            traces,
+            balance_changes,
+            contracts,
+            decoded,
+            decode_errors,
+        } = batch;
+        if ready {
             let mut tx = self
                 .pool
                 .begin()
                 .await
                 .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            // rows are stored with the timestamp of their block
             let mut timestamp_map = HashMap::new();
             let mut block_number_map = HashMap::new();
-            if !blocks.is_empty() {
-                let query = format!(
-                    "INSERT INTO {}_blocks (number, hash, parent_hash, nonce, sha3_uncles, logs_bloom, transactions_root, state_root, receipts_root, miner, difficulty, total_difficulty, extra_data, energy_limit, energy_used, timestamp, transaction_count, matured, created_at) VALUES {}",
-                    self.tables_prefix,
-                    blocks.iter().map(|block| {
-                        let created_at = Utc.timestamp_opt(block.timestamp, 0).unwrap().format("%Y-%m-%d %H:%M:%S").to_string();
-                        timestamp_map.insert(&block.hash, created_at.clone()); // Save timestamp for use in transactions
-                        format!(
-                        "({}, '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', {}, {}, {}, {}, {}, '{}')",
-                        block.number, block.hash, block.parent_hash, block.nonce, block.sha3_uncles, block.logs_bloom, block.transactions_root, block.state_root, block.receipts_root, block.miner, block.difficulty, block.total_difficulty, block.extra_data, block.energy_limit, block.energy_used, block.timestamp, block.transaction_count, block.matured, created_at
-                    )}).collect::<Vec<_>>().join(", ")
-                );
-                if self.modules.contains(&"blocks".to_string()) {
-                    sqlx::query(&query)
-                        .execute(&mut tx)
-                        .await
-                        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                    debug!("Inserted blocks: {:?}", blocks.len());
-                }
+            for block in blocks.iter() {
+                let created_at = Utc
+                    .timestamp_opt(block.timestamp, 0)
+                    .single()
+                    .ok_or(PostgresStorageError::InvalidTimestamp(
+                        block.number,
+                        block.timestamp,
+                    ))?
+                    .naive_utc();
+                timestamp_map.insert(&block.hash, created_at);
+            }
+            for transaction in transactions.iter() {
+                let created_at = block_value(&timestamp_map, &transaction.block_hash)?;
+                timestamp_map.insert(&transaction.hash, created_at);
+                block_number_map.insert(&transaction.hash, transaction.block_number);
             }
 
-            if !transactions.is_empty() {
-                let query = format!(
-                    "INSERT INTO {}_transactions (hash, nonce, block_hash, block_number, transaction_index, from_addr, to_addr, value, energy, energy_price, input, created_at) VALUES {}",
-                    self.tables_prefix,
-                    transactions.iter().map(|tx|{
-                        timestamp_map.insert(&tx.hash, timestamp_map.get(&tx.block_hash).unwrap().to_string()); // Save timestamp for use in token transfers
-                        block_number_map.insert(&tx.hash, tx.block_number); // Save block number for use in token transfers
-                        format!(
-                        "('{}', '{}', '{}', {}, {}, '{}', '{}', '{}', '{}', '{}', '{}', '{}')",
-                        tx.hash, tx.nonce, tx.block_hash, tx.block_number, tx.transaction_index, tx.from, tx.to, tx.value, tx.energy, tx.energy_price, tx.input, timestamp_map.get(&tx.block_hash).unwrap()
-                    )}).collect::<Vec<_>>().join(", ")
-                );
-                if self.modules.contains(&"transactions".to_string()) {
-                    sqlx::query(&query)
-                        .execute(&mut tx)
-                        .await
-                        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                    debug!("Inserted transactions: {:?}", transactions.len());
-                }
+            let bulk_mode = self.bulk_mode.load(Ordering::Relaxed);
+
+            if !blocks.is_empty() && self.modules.contains(&"blocks".to_string()) {
+                let table = format!("{}_blocks", self.tables_prefix);
+                let columns = "number, hash, parent_hash, nonce, sha3_uncles, logs_bloom, transactions_root, state_root, receipts_root, miner, difficulty, total_difficulty, extra_data, energy_limit, energy_used, timestamp, transaction_count, matured, created_at";
+                let result = if bulk_mode {
+                    copy_rows(&mut tx, &table, columns, blocks, |block| {
+                        Ok(format!(
+                            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                            block.number,
+                            csv(&block.hash),
+                            csv(&block.parent_hash),
+                            csv(&block.nonce),
+                            csv(&block.sha3_uncles),
+                            csv(&block.logs_bloom),
+                            csv(&block.transactions_root),
+                            csv(&block.state_root),
+                            csv(&block.receipts_root),
+                            csv(&block.miner),
+                            block.difficulty,
+                            block.total_difficulty,
+                            csv(&block.extra_data),
+                            block.energy_limit,
+                            block.energy_used,
+                            block.timestamp,
+                            block.transaction_count,
+                            block.matured,
+                            block_value(&timestamp_map, &block.hash)?
+                        ))
+                    })
+                    .await
+                } else {
+                    insert_rows(&mut tx, &table, columns, blocks, |row, block| {
+                        row.push_bind(block.number)
+                            .push_bind(&block.hash)
+                            .push_bind(&block.parent_hash)
+                            .push_bind(&block.nonce)
+                            .push_bind(&block.sha3_uncles)
+                            .push_bind(&block.logs_bloom)
+                            .push_bind(&block.transactions_root)
+                            .push_bind(&block.state_root)
+                            .push_bind(&block.receipts_root)
+                            .push_bind(&block.miner)
+                            .push_bind(&block.difficulty)
+                            .push_unseparated("::NUMERIC")
+                            .push_bind(&block.total_difficulty)
+                            .push_unseparated("::NUMERIC")
+                            .push_bind(&block.extra_data)
+                            .push_bind(block.energy_limit)
+                            .push_bind(block.energy_used)
+                            .push_bind(block.timestamp)
+                            .push_bind(block.transaction_count)
+                            .push_bind(block.matured)
+                            .push_bind(block_value(&timestamp_map, &block.hash)?);
+                        Ok(())
+                    })
+                    .await
+                };
+                result.map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted blocks: {:?}", blocks.len());
+            }
+
+            if !transactions.is_empty() && self.modules.contains(&"transactions".to_string()) {
+                let table = format!("{}_transactions", self.tables_prefix);
+                let columns = "hash, nonce, block_hash, block_number, transaction_index, from_addr, to_addr, value, energy, energy_price, input, created_at";
+                let result = if bulk_mode {
+                    copy_rows(&mut tx, &table, columns, transactions, |transaction| {
+                        Ok(format!(
+                            "{},{},{},{},{},{},{},{},{},{},{},{}",
+                            csv(&transaction.hash),
+                            csv(&transaction.nonce),
+                            csv(&transaction.block_hash),
+                            transaction.block_number,
+                            transaction.transaction_index,
+                            csv(&transaction.from),
+                            csv(&transaction.to),
+                            transaction.value,
+                            csv(&transaction.energy),
+                            transaction.energy_price,
+                            csv(&transaction.input),
+                            block_value(&timestamp_map, &transaction.block_hash)?
+                        ))
+                    })
+                    .await
+                } else {
+                    insert_rows(
+                        &mut tx,
+                        &table,
+                        columns,
+                        transactions,
+                        |row, transaction| {
+                            row.push_bind(&transaction.hash)
+                                .push_bind(&transaction.nonce)
+                                .push_bind(&transaction.block_hash)
+                                .push_bind(transaction.block_number)
+                                .push_bind(transaction.transaction_index)
+                                .push_bind(&transaction.from)
+                                .push_bind(&transaction.to)
+                                .push_bind(&transaction.value)
+                                .push_unseparated("::NUMERIC")
+                                .push_bind(&transaction.energy)
+                                .push_bind(&transaction.energy_price)
+                                .push_unseparated("::NUMERIC")
+                                .push_bind(&transaction.input)
+                                .push_bind(block_value(&timestamp_map, &transaction.block_hash)?);
+                            Ok(())
+                        },
+                    )
+                    .await
+                };
+                result.map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted transactions: {:?}", transactions.len());
             }
 
             for (table_name, transfers) in token_transfers.clone() {
                 if !transfers.is_empty() && self.modules.contains(&"token_transfers".to_string()) {
-                    let query = format!(
-                        "INSERT INTO {}_{} (block_number, from_addr, to_addr, value, tx_hash, address, transfer_index, created_at, status) VALUES {}",
-                        self.tables_prefix,
-                        table_name,
-                        transfers.iter().map(|tt| format!(
-                            "({}, '{}', '{}', '{}', '{}', '{}', {}, '{}', {})",
-                            block_number_map.get(&tt.tx_hash).unwrap(),  tt.from, tt.to, tt.value, tt.tx_hash, tt.address, tt.index, timestamp_map.get(&tt.tx_hash).unwrap(), tt.status
-                        )).collect::<Vec<_>>().join(", ")
+                    let with_token_id = has_token_ids(&table_name);
+                    let table = format!("{}_{}", self.tables_prefix, table_name);
+                    let columns = format!(
+                        "block_number, from_addr, to_addr, value, tx_hash, address, transfer_index, created_at, status{}",
+                        if with_token_id { ", token_id, batch_index" } else { "" }
                     );
-                    sqlx::query(&query)
-                        .execute(&mut tx)
+                    let result = if bulk_mode {
+                        copy_rows(&mut tx, &table, &columns, &transfers, |tt| {
+                            Ok(format!(
+                                "{},{},{},{},{},{},{},{},{}{}",
+                                block_value(&block_number_map, &tt.tx_hash)?,
+                                csv(&tt.from),
+                                csv(&tt.to),
+                                tt.value,
+                                csv(&tt.tx_hash),
+                                csv(&tt.address),
+                                tt.index,
+                                block_value(&timestamp_map, &tt.tx_hash)?,
+                                tt.status,
+                                if with_token_id {
+                                    format!(",{},{}", tt.token_id, tt.batch_index)
+                                } else {
+                                    "".to_string()
+                                }
+                            ))
+                        })
                         .await
-                        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                    } else {
+                        insert_rows(&mut tx, &table, &columns, &transfers, |row, tt| {
+                            row.push_bind(block_value(&block_number_map, &tt.tx_hash)?)
+                                .push_bind(&tt.from)
+                                .push_bind(&tt.to)
+                                .push_bind(&tt.value)
+                                .push_unseparated("::NUMERIC")
+                                .push_bind(&tt.tx_hash)
+                                .push_bind(&tt.address)
+                                .push_bind(tt.index)
+                                .push_bind(block_value(&timestamp_map, &tt.tx_hash)?)
+                                .push_bind(tt.status);
+                            if with_token_id {
+                                row.push_bind(&tt.token_id)
+                                    .push_unseparated("::NUMERIC")
+                                    .push_bind(tt.batch_index);
+                            }
+                            Ok(())
+                        })
+                        .await
+                    };
+                    result.map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
                     debug!("Inserted token transfers: {:?}", transfers.len());
+
+                    if table_name.starts_with("cbc20_") {
+                        let balances_table = format!(
+                            "{}_{}_balances",
+                            self.tables_prefix,
+                            table_name.trim_end_matches("_transfers")
+                        );
+                        self.update_token_balances(&mut tx, &balances_table, &transfers, false)
+                            .await?;
+                    }
+                }
+            }
+            if !receipts.is_empty() && self.modules.contains(&"receipts".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_receipts", self.tables_prefix),
+                    "transaction_hash, block_hash, block_number, transaction_index, status, energy_used, cumulative_energy_used, effective_energy_price, contract_address, logs_bloom, created_at",
+                    receipts,
+                    |row, r| {
+                        row.push_bind(&r.transaction_hash)
+                            .push_bind(&r.block_hash)
+                            .push_bind(r.block_number)
+                            .push_bind(r.transaction_index)
+                            .push_bind(r.status)
+                            .push_bind(r.energy_used)
+                            .push_bind(r.cumulative_energy_used)
+                            .push_bind(&r.effective_energy_price)
+                            .push_bind(&r.contract_address)
+                            .push_bind(&r.logs_bloom)
+                            .push_bind(block_value(&timestamp_map, &r.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted receipts: {:?}", receipts.len());
+            }
+            if !logs.is_empty() && self.modules.contains(&"logs".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_logs", self.tables_prefix),
+                    "block_number, block_hash, tx_hash, log_index, address, topic0, topic1, topic2, topic3, data, created_at",
+                    logs,
+                    |row, log| {
+                        row.push_bind(log.block_number)
+                            .push_bind(&log.block_hash)
+                            .push_bind(&log.tx_hash)
+                            .push_bind(log.log_index)
+                            .push_bind(&log.address)
+                            .push_bind(&log.topic0)
+                            .push_bind(&log.topic1)
+                            .push_bind(&log.topic2)
+                            .push_bind(&log.topic3)
+                            .push_bind(&log.data)
+                            .push_bind(block_value(&timestamp_map, &log.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted logs: {:?}", logs.len());
+            }
+            if !traces.is_empty() && self.modules.contains(&"traces".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_traces", self.tables_prefix),
+                    "block_number, block_hash, tx_hash, trace_address, depth, call_type, from_addr, to_addr, value, error, created_at",
+                    traces,
+                    |row, trace| {
+                        row.push_bind(trace.block_number)
+                            .push_bind(&trace.block_hash)
+                            .push_bind(&trace.tx_hash)
+                            .push_bind(&trace.trace_address)
+                            .push_bind(trace.depth)
+                            .push_bind(&trace.call_type)
+                            .push_bind(&trace.from)
+                            .push_bind(&trace.to)
+                            .push_bind(&trace.value)
+                            .push_unseparated("::NUMERIC")
+                            .push_bind(&trace.error)
+                            .push_bind(block_value(&timestamp_map, &trace.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted traces: {:?}", traces.len());
+            }
+            if !balance_changes.is_empty() && self.modules.contains(&"balance_changes".to_string())
+            {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_balance_changes", self.tables_prefix),
+                    "block_number, block_hash, address, delta, created_at",
+                    balance_changes,
+                    |row, change| {
+                        row.push_bind(change.block_number)
+                            .push_bind(&change.block_hash)
+                            .push_bind(&change.address)
+                            .push_bind(&change.delta)
+                            .push_unseparated("::NUMERIC")
+                            .push_bind(block_value(&timestamp_map, &change.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted balance changes: {:?}", balance_changes.len());
+            }
+            if !contracts.is_empty() && self.modules.contains(&"contracts".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_contracts", self.tables_prefix),
+                    "address, creator, tx_hash, block_number, block_hash, bytecode_hash, is_cbc20, name, symbol, decimals, created_at",
+                    contracts,
+                    |row, contract| {
+                        row.push_bind(&contract.address)
+                            .push_bind(&contract.creator)
+                            .push_bind(&contract.tx_hash)
+                            .push_bind(contract.block_number)
+                            .push_bind(&contract.block_hash)
+                            .push_bind(&contract.bytecode_hash)
+                            .push_bind(contract.is_cbc20)
+                            .push_bind(&contract.name)
+                            .push_bind(&contract.symbol)
+                            .push_bind(contract.decimals)
+                            .push_bind(block_value(&timestamp_map, &contract.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted contracts: {:?}", contracts.len());
+            }
+            for (table_name, rows) in decoded.iter() {
+                if rows.is_empty() {
+                    continue;
                 }
+                // values are bound as text, so they are cast to the type of the argument column
+                let column_types: HashMap<String, String> = sqlx::query(
+                    "SELECT column_name, data_type FROM information_schema.columns WHERE table_schema = 'public' AND table_name = $1",
+                )
+                .bind(format!("{}_{}", self.tables_prefix, table_name))
+                .fetch_all(&mut tx)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?
+                .iter()
+                .map(|row| (row.get("column_name"), row.get("data_type")))
+                .collect();
+                // argument columns are quoted, as their names come from the ABI
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_{}", self.tables_prefix, table_name),
+                    &format!(
+                        "block_number, block_hash, tx_hash, address, log_index, status, {}created_at",
+                        rows[0].args.iter().map(|(name, _)| format!("{}, ", quoted(name))).collect::<String>()
+                    ),
+                    rows,
+                    |row, decoded_row| {
+                        row.push_bind(decoded_row.block_number)
+                            .push_bind(&decoded_row.block_hash)
+                            .push_bind(&decoded_row.tx_hash)
+                            .push_bind(&decoded_row.address)
+                            .push_bind(decoded_row.log_index)
+                            .push_bind(decoded_row.status);
+                        for (name, value) in &decoded_row.args {
+                            row.push_bind(value).push_unseparated(format!(
+                                "::{}",
+                                column_types.get(name).map_or("TEXT", |t| t.as_str())
+                            ));
+                        }
+                        row.push_bind(block_value(&timestamp_map, &decoded_row.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted decoded {}: {:?}", table_name, rows.len());
+            }
+            if !decode_errors.is_empty() {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_decode_errors", self.tables_prefix),
+                    "block_number, block_hash, tx_hash, address, log_index, topics, data, error, created_at",
+                    decode_errors,
+                    |row, failure| {
+                        row.push_bind(failure.block_number)
+                            .push_bind(&failure.block_hash)
+                            .push_bind(&failure.tx_hash)
+                            .push_bind(&failure.address)
+                            .push_bind(failure.log_index)
+                            .push_bind(&failure.topics)
+                            .push_bind(&failure.data)
+                            .push_bind(&failure.error)
+                            .push_bind(block_value(&timestamp_map, &failure.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted decode errors: {:?}", decode_errors.len());
             }
             debug!("Committing transaction");
             tx.commit()
                 .await
                 .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
             timestamp_map.clear();
-            blocks.clear();
-            transactions.clear();
-            token_transfers.values_mut().for_each(|v| v.clear());
+            batch.clear();
         }
         Ok(())
     }
@@ -483,7 +1760,7 @@ impl Storage for PostgresStorage {
                 let cutoff_timestamp = cutoff.timestamp();
 
                 let stmt = sqlx::query(format!(
-                    "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE '{}_%_transfers';", tables_prefix).as_str(),
+                    "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND (table_name LIKE '{0}_%_transfers' OR table_name LIKE '{0}_%_calls' OR table_name LIKE '{0}_%_events' OR table_name = '{0}_decode_errors');", tables_prefix).as_str(),
                 )
                 .fetch_all(&pool)
                 .await
@@ -504,6 +1781,10 @@ impl Storage for PostgresStorage {
 
                 table_names.push(format!("{}_blocks", tables_prefix));
                 table_names.push(format!("{}_transactions", tables_prefix));
+                table_names.push(format!("{}_receipts", tables_prefix));
+                table_names.push(format!("{}_logs", tables_prefix));
+                table_names.push(format!("{}_traces", tables_prefix));
+                // balance changes are not removed, balances are calculated from all of them
 
                 for table in table_names {
                     let delete_query = format!(
@@ -530,36 +1811,41 @@ impl Storage for PostgresStorage {
 
     async fn get_token_transfers(
         &self,
-        token_address: String,
-        from: Option<String>,
-        to: Option<String>,
+        token_address: IcanAddress,
+        from: Option<IcanAddress>,
+        to: Option<IcanAddress>,
     ) -> Result<Vec<TokenTransfer>, Pin<Box<dyn Error + Send + Sync>>> {
-        let selector = &token_address[..8];
+        let token_address = token_address.to_string();
+        let from = from.map(|from| from.to_string());
+        let to = to.map(|to| to.to_string());
+        let selector = IcanAddress::table_id(&token_address);
         let prefix = self.tables_prefix.clone();
         let stmt = sqlx::query(
-            format!(
-                "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE '{prefix}_%_{selector}_transfers'"
-            )
-            .as_str(),
+            "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE $1",
         )
+        .bind(format!("{prefix}_%_{selector}_transfers"))
         .fetch_all(&self.pool)
         .await
         .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
 
-        let table_name: String = stmt
-            .first()
-            .map(|row| row.get::<String, _>("table_name"))
-            .unwrap();
+        // tokens which are not watched have no transfers table
+        let Some(table_name) = stmt.first().map(|row| row.get::<String, _>("table_name")) else {
+            return Ok(vec![]);
+        };
 
-        let mut query = format!("SELECT * FROM {table_name} WHERE 1 = 1");
+        let mut query = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {} FROM {table_name} WHERE 1 = 1",
+            transfer_columns(&table_name)
+        ));
         if let Some(from) = from {
-            query += &format!(" AND from_addr = '{}'", from);
+            query.push(" AND from_addr = ").push_bind(from);
         }
         if let Some(to) = to {
-            query += &format!(" AND to_addr = '{}'", to);
+            query.push(" AND to_addr = ").push_bind(to);
         }
 
-        let token_transfers = sqlx::query_as::<_, TokenTransfer>(&query)
+        let token_transfers = query
+            .build_query_as::<TokenTransfer>()
             .fetch_all(&self.pool)
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
@@ -586,13 +1872,15 @@ impl Storage for PostgresStorage {
         let mut query_parts = Vec::new();
         for table in &table_names {
             query_parts.push(format!(
-                "SELECT from_addr, to_addr, value, tx_hash, address FROM {} WHERE tx_hash = '{}'",
-                table, tx_hash
+                "SELECT {} FROM {} WHERE tx_hash = $1",
+                transfer_columns(table),
+                table
             ));
         }
         let query = query_parts.join(" UNION ALL ");
 
         let token_transfers = sqlx::query_as::<_, TokenTransfer>(&query)
+            .bind(tx_hash)
             .fetch_all(&self.pool)
             .await
             .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
@@ -602,9 +1890,10 @@ impl Storage for PostgresStorage {
 
     async fn get_address_token_transfers(
         &self,
-        address: String,
+        address: IcanAddress,
         transfer_type: TransferType,
     ) -> Result<Vec<TokenTransfer>, Pin<Box<dyn Error + Send + Sync>>> {
+        let address = address.to_string();
         let stmt = sqlx::query( format!(
             "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE '{}_%_transfers';", self.tables_prefix).as_str(),
         )
@@ -619,13 +1908,11 @@ impl Storage for PostgresStorage {
 
         let mut query_parts = Vec::new();
         for table in &table_names {
-            let mut subquery = format!("SELECT * FROM {} WHERE ", table);
+            let mut subquery = format!("SELECT {} FROM {} WHERE ", transfer_columns(table), table);
             match transfer_type {
-                TransferType::From => subquery += &format!("from_addr = '{}'", address),
-                TransferType::To => subquery += &format!("to_addr = '{}'", address),
-                TransferType::All => {
-                    subquery += &format!("from_addr = '{}' OR to_addr = '{}'", address, address)
-                }
+                TransferType::From => subquery += "from_addr = $1",
+                TransferType::To => subquery += "to_addr = $1",
+                TransferType::All => subquery += "from_addr = $1 OR to_addr = $1",
             }
             query_parts.push(subquery);
         }
@@ -640,17 +1927,288 @@ impl Storage for PostgresStorage {
         Ok(token_transfers)
     }
 
+    async fn get_token_balance(
+        &self,
+        token: IcanAddress,
+        holder: IcanAddress,
+    ) -> Result<TokenBalance, Pin<Box<dyn Error + Send + Sync>>> {
+        let token = token.to_string();
+        let holder = holder.to_string();
+        let balance = sqlx::query_as::<_, TokenBalance>(
+            format!(
+                "SELECT holder, balance::TEXT AS balance FROM {}_cbc20_{}_balances WHERE holder = $1",
+                self.tables_prefix,
+                IcanAddress::table_id(&token)
+            )
+            .as_str(),
+        )
+        .bind(&holder)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(balance.unwrap_or(TokenBalance {
+            holder,
+            balance: "0".to_string(),
+        }))
+    }
+
+    async fn get_top_holders(
+        &self,
+        token: IcanAddress,
+        limit: i64,
+    ) -> Result<Vec<TokenBalance>, Pin<Box<dyn Error + Send + Sync>>> {
+        let token = token.to_string();
+        // ordered by the numeric column, as the selected balance is text
+        let balances = sqlx::query_as::<_, TokenBalance>(
+            format!(
+                "SELECT holder, balance::TEXT AS balance FROM {0}_cbc20_{1}_balances ORDER BY {0}_cbc20_{1}_balances.balance DESC LIMIT $1",
+                self.tables_prefix,
+                IcanAddress::table_id(&token)
+            )
+            .as_str(),
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(balances)
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        tx_hash: String,
+    ) -> Result<Receipt, Pin<Box<dyn Error + Send + Sync>>> {
+        let receipt = sqlx::query_as::<_, Receipt>(
+            format!(
+                "SELECT * FROM {}_receipts WHERE transaction_hash = $1",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(tx_hash)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(receipt)
+    }
+
+    async fn get_transaction_logs(
+        &self,
+        tx_hash: String,
+    ) -> Result<Vec<Log>, Pin<Box<dyn Error + Send + Sync>>> {
+        let logs = sqlx::query_as::<_, Log>(
+            format!(
+                "SELECT * FROM {}_logs WHERE tx_hash = $1 ORDER BY log_index",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(tx_hash)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(logs)
+    }
+
+    async fn get_balance_changes(
+        &self,
+        address: IcanAddress,
+    ) -> Result<Vec<BalanceChange>, Pin<Box<dyn Error + Send + Sync>>> {
+        let address = address.to_string();
+        let balance_changes = sqlx::query_as::<_, BalanceChange>(
+            format!(
+                "SELECT {} FROM {}_balance_changes WHERE address = $1 ORDER BY block_number",
+                BALANCE_CHANGE_COLUMNS, self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(address)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(balance_changes)
+    }
+
+    async fn get_balance_at(
+        &self,
+        address: IcanAddress,
+        block_number: i64,
+    ) -> Result<String, Pin<Box<dyn Error + Send + Sync>>> {
+        let address = address.to_string();
+        let row = sqlx::query(
+            format!(
+                "SELECT COALESCE(SUM(delta), 0)::TEXT AS balance FROM {}_balance_changes WHERE address = $1 AND block_number <= $2",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(address)
+        .bind(block_number)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(row.get::<String, _>("balance"))
+    }
+
+    async fn get_transaction_traces(
+        &self,
+        tx_hash: String,
+    ) -> Result<Vec<Trace>, Pin<Box<dyn Error + Send + Sync>>> {
+        let mut traces = sqlx::query_as::<_, Trace>(
+            format!(
+                "SELECT {} FROM {}_traces WHERE tx_hash = $1",
+                TRACE_COLUMNS, self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(tx_hash)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        traces.sort_by_key(|trace| trace.call_path());
+
+        Ok(traces)
+    }
+
+    async fn get_logs(
+        &self,
+        address: Option<IcanAddress>,
+        topic0: Option<String>,
+        from_block: Option<i64>,
+        to_block: Option<i64>,
+    ) -> Result<Vec<Log>, Pin<Box<dyn Error + Send + Sync>>> {
+        let address = address.map(|address| address.to_string());
+        let mut query = QueryBuilder::<Postgres>::new(format!(
+            "SELECT * FROM {}_logs WHERE 1 = 1",
+            self.tables_prefix
+        ));
+        if let Some(address) = address {
+            query.push(" AND address = ").push_bind(address);
+        }
+        if let Some(topic0) = topic0 {
+            query.push(" AND topic0 = ").push_bind(topic0);
+        }
+        if let Some(from_block) = from_block {
+            query.push(" AND block_number >= ").push_bind(from_block);
+        }
+        if let Some(to_block) = to_block {
+            query.push(" AND block_number <= ").push_bind(to_block);
+        }
+        query.push(" ORDER BY block_number, log_index");
+
+        let logs = query
+            .build_query_as::<Log>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(logs)
+    }
+
+    async fn add_discovered_token(
+        &self,
+        token: String,
+        address: String,
+        block_number: i64,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        sqlx::query(
+            format!(
+                "INSERT INTO {}_discovered_tokens (address, token, block_number) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(address)
+        .bind(token)
+        .bind(block_number)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(())
+    }
+
+    async fn get_discovered_tokens(
+        &self,
+    ) -> Result<HashMap<String, HashSet<String>>, Pin<Box<dyn Error + Send + Sync>>> {
+        let stmt = sqlx::query(
+            format!(
+                "SELECT token, address FROM {}_discovered_tokens",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let mut tokens: HashMap<String, HashSet<String>> = HashMap::new();
+        for row in stmt {
+            tokens
+                .entry(row.get("token"))
+                .or_default()
+                .insert(row.get("address"));
+        }
+
+        Ok(tokens)
+    }
+
+    async fn get_contract(
+        &self,
+        address: IcanAddress,
+    ) -> Result<Contract, Pin<Box<dyn Error + Send + Sync>>> {
+        let address = address.to_string();
+        let contract = sqlx::query_as::<_, Contract>(
+            format!(
+                "SELECT * FROM {}_contracts WHERE address = $1",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(address)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(contract)
+    }
+
+    async fn get_contracts(
+        &self,
+        only_cbc20: bool,
+    ) -> Result<Vec<Contract>, Pin<Box<dyn Error + Send + Sync>>> {
+        let mut query = format!("SELECT * FROM {}_contracts", self.tables_prefix);
+        if only_cbc20 {
+            query += " WHERE is_cbc20 = 1";
+        }
+        query += " ORDER BY block_number";
+
+        let contracts = sqlx::query_as::<_, Contract>(&query)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(contracts)
+    }
+
     async fn get_block_transactions(
         &self,
         block_number: i64,
     ) -> Result<Vec<Transaction>, Pin<Box<dyn Error + Send + Sync>>> {
         let transactions = sqlx::query_as::<_, Transaction>(
             format!(
-                "SELECT * FROM {}_transactions WHERE block_number = {}",
-                self.tables_prefix, block_number
+                "SELECT {} FROM {}_transactions WHERE block_number = $1",
+                TRANSACTION_COLUMNS, self.tables_prefix
             )
             .as_str(),
         )
+        .bind(block_number)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
@@ -664,11 +2222,12 @@ impl Storage for PostgresStorage {
     ) -> Result<Transaction, Pin<Box<dyn Error + Send + Sync>>> {
         let transaction = sqlx::query_as::<_, Transaction>(
             format!(
-                "SELECT * FROM {}_transactions WHERE hash = '{}'",
-                self.tables_prefix, hash
+                "SELECT {} FROM {}_transactions WHERE hash = $1",
+                TRANSACTION_COLUMNS, self.tables_prefix
             )
             .as_str(),
         )
+        .bind(hash)
         .fetch_one(&self.pool)
         .await
         .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
@@ -678,7 +2237,11 @@ impl Storage for PostgresStorage {
 
     async fn get_all_blocks(&self) -> Result<Vec<Block>, Pin<Box<dyn Error + Send + Sync>>> {
         let blocks = sqlx::query_as::<_, Block>(
-            format!("SELECT * FROM {}_blocks", self.tables_prefix).as_str(),
+            format!(
+                "SELECT {} FROM {}_blocks",
+                BLOCK_COLUMNS, self.tables_prefix
+            )
+            .as_str(),
         )
         .fetch_all(&self.pool)
         .await
@@ -691,19 +2254,15 @@ impl Storage for PostgresStorage {
         start: i64,
         end: i64,
     ) -> Result<Vec<Block>, Pin<Box<dyn Error + Send + Sync>>> {
-        let query = if end < 0 {
-            format!(
-                "SELECT * FROM {}_blocks WHERE number >= {}",
-                self.tables_prefix, start
-            )
-        } else {
-            format!(
-                "SELECT * FROM {}_blocks WHERE number >= {} AND number <= {}",
-                self.tables_prefix, start, end
-            )
-        };
+        // a negative end means the range is open
+        let query = format!(
+            "SELECT {} FROM {}_blocks WHERE number >= $1 AND ($2 < 0 OR number <= $2)",
+            BLOCK_COLUMNS, self.tables_prefix
+        );
 
         let blocks = sqlx::query_as::<_, Block>(&query)
+            .bind(start)
+            .bind(end)
             .fetch_all(&self.pool)
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
@@ -716,11 +2275,12 @@ impl Storage for PostgresStorage {
     ) -> Result<Block, Pin<Box<dyn Error + Send + Sync>>> {
         let block = sqlx::query_as::<_, Block>(
             format!(
-                "SELECT * FROM {}_blocks WHERE number = {}",
-                self.tables_prefix, block_number
+                "SELECT {} FROM {}_blocks WHERE number = $1",
+                BLOCK_COLUMNS, self.tables_prefix
             )
             .as_str(),
         )
+        .bind(block_number)
         .fetch_one(&self.pool)
         .await
         .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
@@ -733,11 +2293,12 @@ impl Storage for PostgresStorage {
     ) -> Result<Block, Pin<Box<dyn Error + Send + Sync>>> {
         let block = sqlx::query_as::<_, Block>(
             format!(
-                "SELECT * FROM {}_blocks WHERE hash = '{}'",
-                self.tables_prefix, block_hash
+                "SELECT {} FROM {}_blocks WHERE hash = $1",
+                BLOCK_COLUMNS, self.tables_prefix
             )
             .as_str(),
         )
+        .bind(block_hash)
         .fetch_one(&self.pool)
         .await
         .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
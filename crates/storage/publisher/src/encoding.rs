@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use apache_avro::{Schema, Writer};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::error::PublisherError;
+
+const BLOCK_FIELDS: [(&str, &str); 18] = [
+    ("number", "long"),
+    ("hash", "string"),
+    ("parent_hash", "string"),
+    ("nonce", "string"),
+    ("sha3_uncles", "string"),
+    ("logs_bloom", "string"),
+    ("transactions_root", "string"),
+    ("state_root", "string"),
+    ("receipts_root", "string"),
+    ("miner", "string"),
+    ("difficulty", "string"),
+    ("total_difficulty", "string"),
+    ("extra_data", "string"),
+    ("energy_limit", "long"),
+    ("energy_used", "long"),
+    ("timestamp", "long"),
+    ("transaction_count", "long"),
+    ("matured", "long"),
+];
+
+const TRANSACTION_FIELDS: [(&str, &str); 11] = [
+    ("hash", "string"),
+    ("nonce", "string"),
+    ("block_hash", "string"),
+    ("block_number", "long"),
+    ("transaction_index", "long"),
+    ("from", "string"),
+    ("to", "string"),
+    ("value", "string"),
+    ("energy", "string"),
+    ("energy_price", "string"),
+    ("input", "string"),
+];
+
+const TOKEN_TRANSFER_FIELDS: [(&str, &str); 10] = [
+    ("block_number", "long"),
+    ("from", "string"),
+    ("to", "string"),
+    ("value", "string"),
+    ("tx_hash", "string"),
+    ("address", "string"),
+    ("index", "long"),
+    ("status", "long"),
+    ("token_id", "string"),
+    ("batch_index", "long"),
+];
+
+/// Payload format of the published messages
+#[derive(Debug, Clone, Copy)]
+pub enum Encoding {
+    Json,
+    /// Avro object container with the schema of the event, so it is read without a schema registry
+    Avro,
+}
+
+/// Type of the published event
+#[derive(Debug, Clone, Copy)]
+pub enum Event {
+    /// Record of an imported block
+    Insert,
+    /// Block is not in the canonical chain anymore, so all its published records are reverted
+    Revert,
+}
+
+impl Event {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Event::Insert => "insert",
+            Event::Revert => "revert",
+        }
+    }
+}
+
+/// Payload of the message. Revert events have no data, they revert all records of the block
+#[derive(Serialize)]
+pub(crate) struct Envelope<'a, T> {
+    pub event: &'static str,
+    pub block_number: i64,
+    pub block_hash: &'a str,
+    /// Type of the token of token transfers, e.g. cbc20
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<&'a str>,
+    pub data: Option<&'a T>,
+}
+
+/// Avro schema of the events of the entity with its record as the optional data
+fn event_schema(name: &str, fields: &[(&str, &str)], token: bool) -> String {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|(name, kind)| json!({"name": name, "type": kind}))
+        .collect();
+    let mut event_fields = vec![
+        json!({"name": "event", "type": "string"}),
+        json!({"name": "block_number", "type": "long"}),
+        json!({"name": "block_hash", "type": "string"}),
+    ];
+    if token {
+        event_fields.push(json!({"name": "token", "type": ["null", "string"], "default": null}));
+    }
+    event_fields.push(json!({
+        "name": "data",
+        "type": ["null", {"type": "record", "name": name, "fields": fields}],
+        "default": null
+    }));
+    json!({
+        "type": "record",
+        "name": format!("{}Event", name),
+        "namespace": "core_etl",
+        "fields": event_fields
+    })
+    .to_string()
+}
+
+/// Encodes the events of the entities, Avro schemas are parsed once
+pub(crate) struct Encoder {
+    encoding: Encoding,
+    schemas: HashMap<&'static str, Schema>,
+}
+
+impl Encoder {
+    pub fn new(encoding: Encoding) -> Result<Self, PublisherError> {
+        let mut schemas = HashMap::new();
+        if let Encoding::Avro = encoding {
+            for (entity, name, fields, token) in [
+                ("blocks", "Block", &BLOCK_FIELDS[..], false),
+                (
+                    "transactions",
+                    "Transaction",
+                    &TRANSACTION_FIELDS[..],
+                    false,
+                ),
+                (
+                    "token_transfers",
+                    "TokenTransfer",
+                    &TOKEN_TRANSFER_FIELDS[..],
+                    true,
+                ),
+            ] {
+                schemas.insert(
+                    entity,
+                    Schema::parse_str(&event_schema(name, fields, token))?,
+                );
+            }
+        }
+        Ok(Self { encoding, schemas })
+    }
+
+    pub fn encode<T: Serialize>(
+        &self,
+        entity: &str,
+        envelope: &Envelope<T>,
+    ) -> Result<Vec<u8>, PublisherError> {
+        match self.encoding {
+            Encoding::Json => Ok(serde_json::to_vec(envelope)?),
+            Encoding::Avro => {
+                let schema = &self.schemas[entity];
+                let mut writer = Writer::new(schema, Vec::new());
+                writer.append(apache_avro::to_value(envelope)?.resolve(schema)?)?;
+                Ok(writer.into_inner()?)
+            }
+        }
+    }
+}
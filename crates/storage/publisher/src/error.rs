@@ -0,0 +1,19 @@
+use std::{error::Error, pin::Pin};
+
+use thiserror::Error as ThisError;
+
+#[derive(ThisError, Debug)]
+pub enum PublisherError {
+    #[error(transparent)]
+    KafkaError(#[from] rdkafka::error::KafkaError),
+    #[error(transparent)]
+    AvroError(#[from] apache_avro::Error),
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+}
+
+impl From<PublisherError> for Pin<Box<dyn Error + Send + Sync>> {
+    fn from(err: PublisherError) -> Self {
+        Pin::from(Box::new(err))
+    }
+}
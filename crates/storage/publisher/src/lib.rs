@@ -0,0 +1,7 @@
+pub mod publishing;
+pub use crate::publishing::PublishingStorage;
+pub mod encoding;
+pub use crate::encoding::Encoding;
+pub mod error;
+pub mod publisher;
+pub use crate::publisher::{KafkaPublisher, MemoryPublisher, Message, Publisher};
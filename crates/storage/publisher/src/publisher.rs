@@ -0,0 +1,81 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use rdkafka::{
+    message::{Header, OwnedHeaders},
+    producer::{FutureProducer, FutureRecord},
+    util::Timeout,
+    ClientConfig,
+};
+
+use crate::error::PublisherError;
+
+/// Message with an event of a block, published to the topic of its entity
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub topic: String,
+    /// Hash of the block, so inserts and reverts of a block are kept in order in one partition
+    pub key: String,
+    /// `insert` or `revert`, sent as the `event` header
+    pub event: &'static str,
+    pub payload: Vec<u8>,
+}
+
+/// Message bus which receives the events of the stored blocks.
+/// Other buses, e.g. NATS or Redis Streams, are added by implementing it
+#[async_trait]
+pub trait Publisher: Send + Sync {
+    /// Publishes the messages in order and waits until all of them are acknowledged
+    async fn publish(&self, messages: Vec<Message>) -> Result<(), PublisherError>;
+}
+
+/// Publishes the messages to Kafka with an idempotent producer,
+/// so retried messages are neither duplicated nor reordered
+pub struct KafkaPublisher {
+    producer: FutureProducer,
+}
+
+impl KafkaPublisher {
+    pub fn new(brokers: &str) -> Result<Self, PublisherError> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .set("message.timeout.ms", "30000")
+            .create()?;
+        Ok(Self { producer })
+    }
+}
+
+#[async_trait]
+impl Publisher for KafkaPublisher {
+    async fn publish(&self, messages: Vec<Message>) -> Result<(), PublisherError> {
+        // messages are queued in order when the sends are polled for the first time
+        let deliveries = messages.iter().map(|message| {
+            let record = FutureRecord::to(&message.topic)
+                .key(message.key.as_str())
+                .payload(message.payload.as_slice())
+                .headers(OwnedHeaders::new().insert(Header {
+                    key: "event",
+                    value: Some(message.event),
+                }));
+            self.producer.send(record, Timeout::Never)
+        });
+        try_join_all(deliveries).await.map_err(|(err, _)| err)?;
+        Ok(())
+    }
+}
+
+/// Keeps the published messages in memory, stands in for a broker in tests
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPublisher {
+    pub messages: Arc<Mutex<Vec<Message>>>,
+}
+
+#[async_trait]
+impl Publisher for MemoryPublisher {
+    async fn publish(&self, messages: Vec<Message>) -> Result<(), PublisherError> {
+        self.messages.lock().unwrap().extend(messages);
+        Ok(())
+    }
+}
@@ -0,0 +1,525 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    pin::Pin,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use storage::{Migration, Storage};
+use tokio::time::Duration;
+use tracing::debug;
+use types::{
+    BalanceChange, Batch, Block, Contract, DecodedTable, IcanAddress, Log, Receipt, TokenBalance,
+    TokenTransfer, Trace, Transaction, TransferType,
+};
+
+use crate::encoding::{Encoder, Encoding, Envelope, Event};
+use crate::error::PublisherError;
+use crate::publisher::{Message, Publisher};
+
+type Result<T> = std::result::Result<T, Pin<Box<dyn Error + Send + Sync>>>;
+
+const ENTITIES: [&str; 3] = ["blocks", "transactions", "token_transfers"];
+
+/// Storage which publishes blocks, transactions and token transfers to `<topic_prefix>.<entity>` topics
+/// when the wrapped storage stores them, keyed by the block hash.
+/// Rolled back blocks are published as revert events to the topics of all entities.
+/// Events are published before the storage is changed, so a change is not made until its events
+/// are acknowledged. Events of a change which then fails are published again when it is retried,
+/// so delivery is at least once and consumers should expect duplicates
+pub struct PublishingStorage {
+    storage: Arc<dyn Storage + Send + Sync>,
+    publisher: Arc<dyn Publisher>,
+    encoder: Encoder,
+    pub topic_prefix: String,
+    pub modules: Vec<String>,
+}
+
+impl PublishingStorage {
+    pub fn new(
+        storage: Arc<dyn Storage + Send + Sync>,
+        publisher: Arc<dyn Publisher>,
+        encoding: Encoding,
+        topic_prefix: String,
+        modules: Vec<String>,
+    ) -> std::result::Result<Self, PublisherError> {
+        Ok(Self {
+            storage,
+            publisher,
+            encoder: Encoder::new(encoding)?,
+            topic_prefix,
+            modules,
+        })
+    }
+
+    fn message<T: Serialize>(
+        &self,
+        entity: &'static str,
+        envelope: Envelope<T>,
+    ) -> std::result::Result<Message, PublisherError> {
+        Ok(Message {
+            topic: format!("{}.{}", self.topic_prefix, entity),
+            key: envelope.block_hash.to_string(),
+            event: envelope.event,
+            payload: self.encoder.encode(entity, &envelope)?,
+        })
+    }
+
+    /// Builds the insert events of the blocks, transactions and token transfers of the batch
+    fn insert_messages(&self, batch: &Batch) -> std::result::Result<Vec<Message>, PublisherError> {
+        let block_hashes: HashMap<i64, &str> = batch
+            .blocks
+            .iter()
+            .map(|block| (block.number, block.hash.as_str()))
+            .collect();
+        let mut messages = vec![];
+        if self.modules.contains(&"blocks".to_string()) {
+            for block in batch.blocks.iter() {
+                messages.push(self.message(
+                    "blocks",
+                    Envelope {
+                        event: Event::Insert.as_str(),
+                        block_number: block.number,
+                        block_hash: &block.hash,
+                        token: None,
+                        data: Some(block),
+                    },
+                )?);
+            }
+        }
+        if self.modules.contains(&"transactions".to_string()) {
+            for transaction in batch.transactions.iter() {
+                messages.push(self.message(
+                    "transactions",
+                    Envelope {
+                        event: Event::Insert.as_str(),
+                        block_number: transaction.block_number,
+                        block_hash: &transaction.block_hash,
+                        token: None,
+                        data: Some(transaction),
+                    },
+                )?);
+            }
+        }
+        if self.modules.contains(&"token_transfers".to_string()) {
+            // table names of the batch start with the token type, e.g. cbc20_12ab34cd56ef7890_transfers
+            for (table_name, transfers) in batch.token_transfers.iter() {
+                let token = table_name.split('_').next().unwrap_or_default();
+                for transfer in transfers {
+                    messages.push(
+                        self.message(
+                            "token_transfers",
+                            Envelope {
+                                event: Event::Insert.as_str(),
+                                block_number: transfer.block_number,
+                                block_hash: block_hashes
+                                    .get(&transfer.block_number)
+                                    .copied()
+                                    .unwrap_or_default(),
+                                token: Some(token),
+                                data: Some(transfer),
+                            },
+                        )?,
+                    );
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Builds the revert events of the stored blocks in the range, the latest block first
+    async fn revert_messages(&self, from: i64, to: i64) -> Result<Vec<Message>> {
+        let end = if to == i64::MAX { -1 } else { to };
+        let mut blocks = self.storage.get_blocks_in_range(from, end).await?;
+        blocks.sort_by_key(|block| std::cmp::Reverse(block.number));
+
+        let mut messages = vec![];
+        for block in blocks.iter() {
+            for entity in ENTITIES {
+                if !self.modules.contains(&entity.to_string()) {
+                    continue;
+                }
+                messages.push(self.message(
+                    entity,
+                    Envelope::<Block> {
+                        event: Event::Revert.as_str(),
+                        block_number: block.number,
+                        block_hash: &block.hash,
+                        token: None,
+                        data: None,
+                    },
+                )?);
+            }
+        }
+        Ok(messages)
+    }
+
+    async fn publish(&self, messages: Vec<Message>) -> Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let count = messages.len();
+        self.publisher.publish(messages).await?;
+        debug!("Published {} messages", count);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for PublishingStorage {
+    async fn prepare_db(&self) -> Result<()> {
+        self.storage.prepare_db().await
+    }
+
+    async fn create_indexes(&self) -> Result<()> {
+        self.storage.create_indexes().await
+    }
+
+    async fn schema_version(&self) -> Result<i64> {
+        self.storage.schema_version().await
+    }
+
+    async fn apply_migration(&self, migration: Migration) -> Result<()> {
+        self.storage.apply_migration(migration).await
+    }
+
+    async fn run_migrations(&self, dry_run: bool) -> Result<Vec<Migration>> {
+        self.storage.run_migrations(dry_run).await
+    }
+
+    async fn set_bulk_mode(&self, enabled: bool) -> Result<()> {
+        self.storage.set_bulk_mode(enabled).await
+    }
+
+    async fn get_latest_block_number(&self) -> Result<i64> {
+        self.storage.get_latest_block_number().await
+    }
+
+    async fn update_blocks_to_matured(&self, from: i64, to: i64) -> Result<()> {
+        self.storage.update_blocks_to_matured(from, to).await
+    }
+
+    async fn create_token_transfers_tables(
+        &self,
+        tokens: HashMap<String, HashSet<String>>,
+    ) -> Result<()> {
+        self.storage.create_token_transfers_tables(tokens).await
+    }
+
+    async fn create_decoded_tables(&self, tables: Vec<DecodedTable>) -> Result<()> {
+        self.storage.create_decoded_tables(tables).await
+    }
+
+    async fn rollback_blocks(&self, from: i64) -> Result<()> {
+        let messages = self.revert_messages(from, i64::MAX).await?;
+        self.publish(messages).await?;
+        self.storage.rollback_blocks(from).await
+    }
+
+    async fn clean_last_blocks(&self, number: i64) -> Result<()> {
+        let from = self.storage.get_latest_block_number().await? - number + 1;
+        let messages = self.revert_messages(from, i64::MAX).await?;
+        self.publish(messages).await?;
+        self.storage.clean_last_blocks(number).await
+    }
+
+    fn insert_threshold(&self) -> usize {
+        self.storage.insert_threshold()
+    }
+
+    async fn insert_blocks_with_txs_and_token_transfers(
+        &self,
+        insert_all: bool,
+        batch: &mut Batch,
+    ) -> Result<()> {
+        // the wrapped storage inserts the batch with the same threshold
+        if self.is_batch_ready(batch, insert_all) {
+            self.publish(self.insert_messages(batch)?).await?;
+        }
+        self.storage
+            .insert_blocks_with_txs_and_token_transfers(insert_all, batch)
+            .await
+    }
+
+    async fn start_cleanup_task(&self, interval: Duration, retention_duration: Duration) {
+        self.storage
+            .start_cleanup_task(interval, retention_duration)
+            .await
+    }
+
+    // View functions
+
+    async fn get_block_by_number(&self, block_number: i64) -> Result<Block> {
+        self.storage.get_block_by_number(block_number).await
+    }
+
+    async fn get_block_by_hash(&self, block_hash: String) -> Result<Block> {
+        self.storage.get_block_by_hash(block_hash).await
+    }
+
+    async fn get_all_blocks(&self) -> Result<Vec<Block>> {
+        self.storage.get_all_blocks().await
+    }
+
+    async fn get_blocks_in_range(&self, start: i64, end: i64) -> Result<Vec<Block>> {
+        self.storage.get_blocks_in_range(start, end).await
+    }
+
+    async fn get_block_transactions(&self, block_number: i64) -> Result<Vec<Transaction>> {
+        self.storage.get_block_transactions(block_number).await
+    }
+
+    async fn get_transaction_by_hash(&self, hash: String) -> Result<Transaction> {
+        self.storage.get_transaction_by_hash(hash).await
+    }
+
+    async fn get_token_transfers(
+        &self,
+        token_address: IcanAddress,
+        from: Option<IcanAddress>,
+        to: Option<IcanAddress>,
+    ) -> Result<Vec<TokenTransfer>> {
+        self.storage
+            .get_token_transfers(token_address, from, to)
+            .await
+    }
+
+    async fn get_transaction_token_transfers(&self, tx_hash: String) -> Result<Vec<TokenTransfer>> {
+        self.storage.get_transaction_token_transfers(tx_hash).await
+    }
+
+    async fn get_address_token_transfers(
+        &self,
+        address: IcanAddress,
+        transfer_type: TransferType,
+    ) -> Result<Vec<TokenTransfer>> {
+        self.storage
+            .get_address_token_transfers(address, transfer_type)
+            .await
+    }
+
+    async fn get_token_balance(
+        &self,
+        token: IcanAddress,
+        holder: IcanAddress,
+    ) -> Result<TokenBalance> {
+        self.storage.get_token_balance(token, holder).await
+    }
+
+    async fn get_top_holders(&self, token: IcanAddress, limit: i64) -> Result<Vec<TokenBalance>> {
+        self.storage.get_top_holders(token, limit).await
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: String) -> Result<Receipt> {
+        self.storage.get_transaction_receipt(tx_hash).await
+    }
+
+    async fn get_transaction_logs(&self, tx_hash: String) -> Result<Vec<Log>> {
+        self.storage.get_transaction_logs(tx_hash).await
+    }
+
+    async fn get_balance_changes(&self, address: IcanAddress) -> Result<Vec<BalanceChange>> {
+        self.storage.get_balance_changes(address).await
+    }
+
+    async fn get_balance_at(&self, address: IcanAddress, block_number: i64) -> Result<String> {
+        self.storage.get_balance_at(address, block_number).await
+    }
+
+    async fn get_transaction_traces(&self, tx_hash: String) -> Result<Vec<Trace>> {
+        self.storage.get_transaction_traces(tx_hash).await
+    }
+
+    async fn get_logs(
+        &self,
+        address: Option<IcanAddress>,
+        topic0: Option<String>,
+        from_block: Option<i64>,
+        to_block: Option<i64>,
+    ) -> Result<Vec<Log>> {
+        self.storage
+            .get_logs(address, topic0, from_block, to_block)
+            .await
+    }
+
+    async fn add_discovered_token(
+        &self,
+        token: String,
+        address: String,
+        block_number: i64,
+    ) -> Result<()> {
+        self.storage
+            .add_discovered_token(token, address, block_number)
+            .await
+    }
+
+    async fn get_discovered_tokens(&self) -> Result<HashMap<String, HashSet<String>>> {
+        self.storage.get_discovered_tokens().await
+    }
+
+    async fn get_contract(&self, address: IcanAddress) -> Result<Contract> {
+        self.storage.get_contract(address).await
+    }
+
+    async fn get_contracts(&self, only_cbc20: bool) -> Result<Vec<Contract>> {
+        self.storage.get_contracts(only_cbc20).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::publisher::MemoryPublisher;
+    use futures::executor::block_on;
+    use mock_storage::MockStorage;
+
+    fn block(number: i64) -> Block {
+        Block {
+            number,
+            hash: format!("b{}", number),
+            parent_hash: format!("b{}", number - 1),
+            nonce: "0".to_string(),
+            sha3_uncles: String::new(),
+            logs_bloom: String::new(),
+            transactions_root: String::new(),
+            state_root: String::new(),
+            receipts_root: String::new(),
+            miner: String::new(),
+            difficulty: "1".to_string(),
+            total_difficulty: "1".to_string(),
+            extra_data: String::new(),
+            energy_limit: 1,
+            energy_used: 1,
+            timestamp: 1_700_000_000 + number,
+            transaction_count: 1,
+            matured: 0,
+        }
+    }
+
+    fn transaction(block_number: i64) -> Transaction {
+        Transaction {
+            hash: format!("t{}", block_number),
+            nonce: "0".to_string(),
+            block_hash: format!("b{}", block_number),
+            block_number,
+            transaction_index: 0,
+            from: "a".to_string(),
+            to: "b".to_string(),
+            value: "1".to_string(),
+            energy: "1".to_string(),
+            energy_price: "1".to_string(),
+            input: String::new(),
+        }
+    }
+
+    fn transfer(block_number: i64) -> TokenTransfer {
+        TokenTransfer {
+            block_number,
+            from: "a".to_string(),
+            to: "b".to_string(),
+            value: "1".to_string(),
+            tx_hash: format!("t{}", block_number),
+            address: "c".to_string(),
+            index: 0,
+            status: 1,
+            token_id: String::new(),
+            batch_index: 0,
+        }
+    }
+
+    fn storage(blocks: Vec<Block>, modules: &[&str]) -> (PublishingStorage, MemoryPublisher) {
+        let publisher = MemoryPublisher::default();
+        let storage = PublishingStorage::new(
+            Arc::new(MockStorage {
+                blocks,
+                transactions: vec![],
+            }),
+            Arc::new(publisher.clone()),
+            Encoding::Json,
+            "etl".to_string(),
+            modules.iter().map(|module| module.to_string()).collect(),
+        )
+        .unwrap();
+        (storage, publisher)
+    }
+
+    /// Topic, key and event of the published messages
+    fn published(publisher: &MemoryPublisher) -> Vec<(String, String, &'static str)> {
+        publisher
+            .messages
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|message| (message.topic.clone(), message.key.clone(), message.event))
+            .collect()
+    }
+
+    fn message(topic: &str, key: &str, event: &'static str) -> (String, String, &'static str) {
+        (topic.to_string(), key.to_string(), event)
+    }
+
+    #[test]
+    fn publishes_inserts_by_entity_keyed_by_block_hash() {
+        let (storage, publisher) = storage(vec![], &ENTITIES);
+        let mut batch = Batch {
+            blocks: vec![block(1), block(2)],
+            transactions: vec![transaction(1), transaction(2)],
+            token_transfers: HashMap::from([("cbc20_c_transfers".to_string(), vec![transfer(2)])]),
+            ..Default::default()
+        };
+        block_on(storage.insert_blocks_with_txs_and_token_transfers(true, &mut batch)).unwrap();
+
+        assert_eq!(
+            published(&publisher),
+            vec![
+                message("etl.blocks", "b1", "insert"),
+                message("etl.blocks", "b2", "insert"),
+                message("etl.transactions", "b1", "insert"),
+                message("etl.transactions", "b2", "insert"),
+                message("etl.token_transfers", "b2", "insert"),
+            ]
+        );
+        let messages = publisher.messages.lock().unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(&messages[4].payload).unwrap();
+        assert_eq!(payload["event"], "insert");
+        assert_eq!(payload["block_number"], 2);
+        assert_eq!(payload["token"], "cbc20");
+        assert_eq!(payload["data"]["tx_hash"], "t2");
+    }
+
+    #[test]
+    fn waits_for_the_batch_to_be_inserted() {
+        let (storage, publisher) = storage(vec![], &ENTITIES);
+        let mut batch = Batch {
+            blocks: vec![block(1)],
+            ..Default::default()
+        };
+        block_on(storage.insert_blocks_with_txs_and_token_transfers(false, &mut batch)).unwrap();
+        assert!(published(&publisher).is_empty());
+    }
+
+    #[test]
+    fn publishes_reverts_latest_block_first() {
+        let (storage, publisher) = storage(
+            vec![block(1), block(2), block(3)],
+            &["blocks", "transactions"],
+        );
+        block_on(storage.rollback_blocks(2)).unwrap();
+
+        assert_eq!(
+            published(&publisher),
+            vec![
+                message("etl.blocks", "b3", "revert"),
+                message("etl.transactions", "b3", "revert"),
+                message("etl.blocks", "b2", "revert"),
+                message("etl.transactions", "b2", "revert"),
+            ]
+        );
+        let messages = publisher.messages.lock().unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(&messages[0].payload).unwrap();
+        assert_eq!(payload["block_hash"], "b3");
+        assert!(payload["data"].is_null());
+    }
+}
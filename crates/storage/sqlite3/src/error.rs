@@ -0,0 +1,20 @@
+use std::{error::Error, pin::Pin};
+
+use thiserror::Error as ThisError;
+
+#[derive(ThisError, Debug)]
+pub enum Sqlite3StorageError {
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    /// Rows of the batch are stored with their block, so the block must be in the batch
+    #[error("no block of {0} in the batch")]
+    MissingBlock(String),
+    #[error("invalid timestamp {1} of block {0}")]
+    InvalidTimestamp(i64, i64),
+}
+
+impl From<Sqlite3StorageError> for Pin<Box<dyn Error + Send + Sync>> {
+    fn from(err: Sqlite3StorageError) -> Self {
+        Pin::from(Box::new(err))
+    }
+}
@@ -1,2 +1,3 @@
 pub mod sqlite3;
 pub use sqlite3::Sqlite3Storage;
+pub mod error;
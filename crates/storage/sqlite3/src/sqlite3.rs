@@ -1,18 +1,120 @@
 use async_trait::async_trait;
+use base_primitives::U256;
 use chrono::{TimeZone, Utc};
-use sqlx::{migrate::MigrateDatabase, sqlite::SqlitePoolOptions, Row, Sqlite, SqlitePool};
+use sqlx::{
+    migrate::MigrateDatabase, query_builder::Separated, sqlite::SqlitePoolOptions, QueryBuilder,
+    Row, Sqlite, SqlitePool,
+};
 use std::{
     collections::{HashMap, HashSet},
     error::Error,
     pin::Pin,
 };
-use storage::Storage;
+use storage::{has_token_ids, legacy_transfers_table, Migration, Storage, TOKEN_ID_TYPES};
 use tokio::time::{self, Duration};
-use tracing::{debug, error};
-use types::{Block, TokenTransfer, Transaction, TransferType};
+use tracing::{debug, error, info, warn};
+use types::{
+    BalanceChange, Batch, Block, Contract, DecodedTable, IcanAddress, Log, Receipt, TokenBalance,
+    TokenTransfer, Trace, Transaction, TransferType,
+};
+
+use crate::error::Sqlite3StorageError;
 
 type Result<T> = std::result::Result<T, Pin<Box<dyn Error + Send + Sync>>>;
 
+// SQLite allows up to 32766 bind parameters in a single statement
+const BIND_LIMIT: usize = 32766;
+
+/// Inserts the rows with bound parameters. Rows are split into statements which fit into the bind limit.
+/// The statement is not executed if any of its rows fails to be bound
+async fn insert_rows<'a, T>(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    table: &str,
+    columns: &str,
+    rows: &'a [T],
+    push_row: impl Fn(
+        &mut Separated<'_, 'a, Sqlite, &'static str>,
+        &'a T,
+    ) -> std::result::Result<(), Sqlite3StorageError>,
+) -> std::result::Result<(), Sqlite3StorageError> {
+    let chunk_size = BIND_LIMIT / columns.split(',').count();
+    for chunk in rows.chunks(chunk_size) {
+        let mut query = QueryBuilder::new(format!("INSERT INTO {} ({}) ", table, columns));
+        let mut result = Ok(());
+        query.push_values(chunk, |mut row, value| {
+            if result.is_ok() {
+                result = push_row(&mut row, value);
+            }
+        });
+        result?;
+        query.build().execute(&mut *tx).await?;
+    }
+    Ok(())
+}
+
+/// Returns the value of the block of the row, which is looked up by the block or transaction hash
+fn block_value<T: Copy>(
+    map: &HashMap<&String, T>,
+    hash: &String,
+) -> std::result::Result<T, Sqlite3StorageError> {
+    map.get(hash)
+        .copied()
+        .ok_or_else(|| Sqlite3StorageError::MissingBlock(hash.clone()))
+}
+
+/// Quotes the column name which comes from the ABI
+fn quoted(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Values and amounts are stored as decimal strings zero padded to the length of the max uint256,
+/// so they are sorted and compared correctly as text
+fn sortable(value: &str) -> String {
+    format!("{:0>78}", value)
+}
+
+/// Selects the zero padded column as the decimal string
+fn unpadded(column: &str) -> String {
+    format!("COALESCE(NULLIF(LTRIM({0}, '0'), ''), '0') AS {0}", column)
+}
+
+fn block_columns() -> String {
+    format!(
+        "number, hash, parent_hash, nonce, sha3_uncles, logs_bloom, transactions_root, state_root, receipts_root, miner, {}, {}, extra_data, energy_limit, energy_used, timestamp, transaction_count, matured",
+        unpadded("difficulty"),
+        unpadded("total_difficulty")
+    )
+}
+
+fn transaction_columns() -> String {
+    format!(
+        "hash, nonce, block_hash, block_number, transaction_index, from_addr, to_addr, {}, energy, {}, input",
+        unpadded("value"),
+        unpadded("energy_price")
+    )
+}
+
+fn trace_columns() -> String {
+    format!(
+        "block_number, block_hash, tx_hash, trace_address, depth, call_type, from_addr, to_addr, {}, error",
+        unpadded("value")
+    )
+}
+
+/// Only NFT and multi-token tables have token IDs and batch indexes, so the columns are added to keep unions of the tables valid
+fn transfer_columns(table: &str) -> String {
+    let token_id_column = if has_token_ids(table) {
+        format!("{}, batch_index", unpadded("token_id"))
+    } else {
+        "'' AS token_id, 0 AS batch_index".to_string()
+    };
+    format!(
+        "block_number, from_addr, to_addr, {}, tx_hash, address, transfer_index, status, {}",
+        unpadded("value"),
+        token_id_column
+    )
+}
+
 #[derive(Debug, Clone)]
 pub struct Sqlite3Storage {
     pool: SqlitePool,
@@ -54,15 +156,33 @@ impl Sqlite3Storage {
 }
 
 impl Sqlite3Storage {
-    /// Migrates the database.
-    async fn migrate_db(&self) -> Result<()> {
-        debug!("Migrating database");
+    /// Creates tables of all modules, the first migration
+    async fn create_tables(&self, tx: &mut sqlx::Transaction<'_, Sqlite>) -> Result<()> {
+        debug!("Creating database tables");
         let block_hash_foreign_key = if self.modules.contains(&"blocks".to_string()) {
             format!(",
                 CONSTRAINT fk_{0}_block_hash FOREIGN KEY (block_hash) REFERENCES {0}_blocks(hash) ON DELETE CASCADE ON UPDATE CASCADE", self.tables_prefix)
         } else {
             "".to_string()
         };
+        let tx_hash_foreign_key = if self.modules.contains(&"transactions".to_string()) {
+            format!(",
+                CONSTRAINT fk_{0}_receipt_tx_hash FOREIGN KEY (transaction_hash) REFERENCES {0}_transactions(hash) ON DELETE CASCADE ON UPDATE CASCADE", self.tables_prefix)
+        } else {
+            "".to_string()
+        };
+        let log_tx_hash_foreign_key = if self.modules.contains(&"transactions".to_string()) {
+            format!(",
+                CONSTRAINT fk_{0}_log_tx_hash FOREIGN KEY (tx_hash) REFERENCES {0}_transactions(hash) ON DELETE CASCADE ON UPDATE CASCADE", self.tables_prefix)
+        } else {
+            "".to_string()
+        };
+        let trace_tx_hash_foreign_key = if self.modules.contains(&"transactions".to_string()) {
+            format!(",
+                CONSTRAINT fk_{0}_trace_tx_hash FOREIGN KEY (tx_hash) REFERENCES {0}_transactions(hash) ON DELETE CASCADE ON UPDATE CASCADE", self.tables_prefix)
+        } else {
+            "".to_string()
+        };
         let queries = vec![
             format!(
                 "CREATE TABLE IF NOT EXISTS {}_blocks (
@@ -106,28 +226,546 @@ impl Sqlite3Storage {
             );",
                 self.tables_prefix, block_hash_foreign_key
             ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {0}_receipts (
+                transaction_hash TEXT PRIMARY KEY NOT NULL,
+                block_hash TEXT,
+                block_number INTEGER,
+                transaction_index INTEGER,
+                status INTEGER,
+                energy_used INTEGER,
+                cumulative_energy_used INTEGER,
+                effective_energy_price TEXT,
+                contract_address TEXT,
+                logs_bloom TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                {1}
+            );",
+                self.tables_prefix, tx_hash_foreign_key
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {0}_logs (
+                block_number INTEGER,
+                block_hash TEXT,
+                tx_hash TEXT NOT NULL,
+                log_index INTEGER NOT NULL,
+                address TEXT,
+                topic0 TEXT,
+                topic1 TEXT,
+                topic2 TEXT,
+                topic3 TEXT,
+                data TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (tx_hash, log_index)
+                {1}
+            );",
+                self.tables_prefix, log_tx_hash_foreign_key
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {0}_traces (
+                block_number INTEGER,
+                block_hash TEXT,
+                tx_hash TEXT NOT NULL,
+                trace_address TEXT NOT NULL,
+                depth INTEGER,
+                call_type TEXT,
+                from_addr TEXT,
+                to_addr TEXT,
+                value TEXT,
+                error TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (tx_hash, trace_address)
+                {1}
+            );",
+                self.tables_prefix, trace_tx_hash_foreign_key
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}_balance_changes (
+                block_number INTEGER NOT NULL,
+                block_hash TEXT,
+                address TEXT NOT NULL,
+                delta TEXT NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (block_number, address)
+            );",
+                self.tables_prefix
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}_contracts (
+                address TEXT PRIMARY KEY,
+                creator TEXT NOT NULL,
+                tx_hash TEXT NOT NULL,
+                block_number INTEGER NOT NULL,
+                block_hash TEXT,
+                bytecode_hash TEXT,
+                is_cbc20 INTEGER NOT NULL DEFAULT 0,
+                name TEXT,
+                symbol TEXT,
+                decimals INTEGER,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+                self.tables_prefix
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}_decode_errors (
+                block_number INTEGER NOT NULL,
+                block_hash TEXT,
+                tx_hash TEXT NOT NULL,
+                address TEXT NOT NULL,
+                log_index INTEGER NOT NULL,
+                topics TEXT,
+                data TEXT,
+                error TEXT NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+                self.tables_prefix
+            ),
+            // discovered tokens are kept on rollbacks, as a token stays a token on any chain
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}_discovered_tokens (
+                address TEXT PRIMARY KEY,
+                token TEXT NOT NULL,
+                block_number INTEGER NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+                self.tables_prefix
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}_orphaned_blocks (
+                number INTEGER NOT NULL,
+                hash TEXT,
+                parent_hash TEXT,
+                nonce TEXT,
+                sha3_uncles TEXT,
+                logs_bloom TEXT,
+                transactions_root TEXT,
+                state_root TEXT,
+                receipts_root TEXT,
+                miner TEXT,
+                difficulty TEXT,
+                total_difficulty TEXT,
+                extra_data TEXT,
+                energy_limit INTEGER,
+                energy_used INTEGER,
+                timestamp INTEGER,
+                transaction_count INTEGER,
+                matured INTEGER,
+                created_at TIMESTAMP,
+                reorg_depth INTEGER NOT NULL,
+                detected_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+                self.tables_prefix
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}_orphaned_transactions (
+                hash TEXT NOT NULL,
+                nonce TEXT,
+                block_hash TEXT,
+                block_number INTEGER,
+                transaction_index INTEGER,
+                from_addr TEXT,
+                to_addr TEXT,
+                value TEXT,
+                energy TEXT,
+                energy_price TEXT,
+                input TEXT,
+                created_at TIMESTAMP,
+                reorg_depth INTEGER NOT NULL,
+                detected_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+                self.tables_prefix
+            ),
         ];
 
         for query in queries {
             sqlx::query(&query)
-                .execute(self.get_db())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        }
+
+        Ok(())
+    }
+
+    /// Converts values and amounts stored by the previous versions to the zero padded decimal strings.
+    /// Only blocks, transactions and CBC20 transfers were stored by them, tables of the other modules are
+    /// created with padded values. Token values were stored as zero padded hex, the others as decimal strings
+    async fn migrate_numeric_columns(&self, tx: &mut sqlx::Transaction<'_, Sqlite>) -> Result<()> {
+        let mut decimal_columns = vec![];
+        for column in ["difficulty", "total_difficulty"] {
+            decimal_columns.push((format!("{}_blocks", self.tables_prefix), column));
+        }
+        for column in ["value", "energy_price"] {
+            decimal_columns.push((format!("{}_transactions", self.tables_prefix), column));
+        }
+        for (table, column) in decimal_columns {
+            sqlx::query(
+                format!(
+                    "UPDATE {0} SET {1} = SUBSTR('{2}' || {1}, -78) WHERE LENGTH({1}) < 78",
+                    table,
+                    column,
+                    "0".repeat(78)
+                )
+                .as_str(),
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        }
+
+        let token_tables =
+            sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name LIKE ?")
+                .bind(format!("{}_cbc20_%_transfers", self.tables_prefix))
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        let hex_columns = token_tables
+            .into_iter()
+            .map(|row| (row.get::<String, _>("name"), "value"));
+        for (table, column) in hex_columns {
+            // padded decimals have 78 chars, so only the old hex values have 64
+            let values = sqlx::query(
+                format!(
+                    "SELECT DISTINCT {1} FROM {0} WHERE LENGTH({1}) = 64",
+                    table, column
+                )
+                .as_str(),
+            )
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            if !values.is_empty() {
+                info!("Migrating {}.{} to decimal values", table, column);
+            }
+            for row in values {
+                let hex = row.get::<String, _>(column);
+                let value = U256::from_str_radix(&hex, 16)
+                    .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                sqlx::query(
+                    format!("UPDATE {0} SET {1} = ? WHERE {1} = ?", table, column).as_str(),
+                )
+                .bind(sortable(&value.to_string()))
+                .bind(hex)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Renames token tables which were named by the first 8 chars of the token address.
+    /// Empty tables are dropped, as they are created with the new names at start.
+    /// Tables with transfers of several tokens are kept, so these tokens have to be extracted again
+    async fn migrate_token_table_names(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+    ) -> Result<()> {
+        let tables = sqlx::query(
+            format!(
+                "SELECT name FROM sqlite_master WHERE type='table' AND name LIKE '{}_%_transfers'",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        for row in tables {
+            let table = row.get::<String, _>("name");
+            let Some((token, id)) = legacy_transfers_table(&self.tables_prefix, &table) else {
+                continue;
+            };
+            let addresses = sqlx::query(format!("SELECT DISTINCT address FROM {}", table).as_str())
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?
+                .into_iter()
+                .map(|row| row.get::<String, _>("address"))
+                .collect::<Vec<_>>();
+            if addresses.len() > 1 {
+                warn!(
+                    "Table {} has transfers of tokens {:?}, they have to be extracted again",
+                    table, addresses
+                );
+                continue;
+            }
+            let mut suffixes = vec!["transfers"];
+            if token == "cbc20" {
+                suffixes.push("balances");
+                // the index is created again with the name of the renamed table
+                sqlx::query(
+                    format!(
+                        "DROP INDEX IF EXISTS idx_{}_cbc20_{}_balances_balance",
+                        self.tables_prefix, id
+                    )
+                    .as_str(),
+                )
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            }
+            for suffix in suffixes {
+                let old_table = format!("{}_{}_{}_{}", self.tables_prefix, token, id, suffix);
+                let query = match addresses.first() {
+                    Some(address) => format!(
+                        "ALTER TABLE {} RENAME TO {}_{}_{}_{}",
+                        old_table,
+                        self.tables_prefix,
+                        token,
+                        IcanAddress::table_id(address),
+                        suffix
+                    ),
+                    None => format!("DROP TABLE IF EXISTS {}", old_table),
+                };
+                info!("Migrating token table {}", old_table);
+                sqlx::query(&query)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            }
         }
+        Ok(())
+    }
+
+    /// Returns the latest block number seen by the transaction, so it matches the rows changed in it.
+    /// Only blocks and transactions are archived, so the number is read from their tables
+    async fn latest_archived_block(&self, tx: &mut sqlx::Transaction<'_, Sqlite>) -> Result<i64> {
+        let row = sqlx::query(
+            format!(
+                "SELECT COALESCE((SELECT MAX(number) FROM {0}_blocks), (SELECT MAX(block_number) FROM {0}_transactions), 0) AS number",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(row.get::<i64, _>("number"))
+    }
+
+    /// Copies blocks and transactions in the given range to the orphaned tables
+    /// so they are not lost when the blocks are removed after a reorg.
+    async fn archive_orphaned_blocks(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        from: i64,
+        to: i64,
+        reorg_depth: i64,
+    ) -> Result<()> {
+        let block_columns = "number, hash, parent_hash, nonce, sha3_uncles, logs_bloom, transactions_root, state_root, receipts_root, miner, difficulty, total_difficulty, extra_data, energy_limit, energy_used, timestamp, transaction_count, matured, created_at";
+        let result = sqlx::query(
+            format!(
+                "INSERT INTO {0}_orphaned_blocks ({1}, reorg_depth) SELECT {1}, ? FROM {0}_blocks WHERE number >= ? AND number <= ?",
+                self.tables_prefix, block_columns
+            )
+            .as_str(),
+        )
+        .bind(reorg_depth)
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        debug!("Archived orphaned blocks: {:?}", result.rows_affected());
 
+        let transaction_columns = "hash, nonce, block_hash, block_number, transaction_index, from_addr, to_addr, value, energy, energy_price, input, created_at";
+        let result = sqlx::query(
+            format!(
+                "INSERT INTO {0}_orphaned_transactions ({1}, reorg_depth) SELECT {1}, ? FROM {0}_transactions WHERE block_number >= ? AND block_number <= ?",
+                self.tables_prefix, transaction_columns
+            )
+            .as_str(),
+        )
+        .bind(reorg_depth)
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        debug!(
+            "Archived orphaned transactions: {:?}",
+            result.rows_affected()
+        );
+
+        Ok(())
+    }
+
+    /// Adds values of the token transfers to the balances of the holders.
+    /// If `revert` is set, transfers are subtracted instead, e.g. when their blocks are removed
+    async fn update_token_balances(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        balances_table: &str,
+        transfers: &[TokenTransfer],
+        revert: bool,
+    ) -> Result<()> {
+        let changes = TokenBalance::changes(transfers)
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        for (holder, (received, sent)) in changes {
+            let (received, sent) = if revert {
+                (sent, received)
+            } else {
+                (received, sent)
+            };
+            let row = sqlx::query(
+                format!(
+                    "SELECT {} FROM {} WHERE holder = ?",
+                    unpadded("balance"),
+                    balances_table
+                )
+                .as_str(),
+            )
+            .bind(&holder)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            let balance = TokenBalance::apply(
+                row.map(|row| row.get::<String, _>("balance")).as_deref(),
+                received,
+                sent,
+            )
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            sqlx::query(
+                format!(
+                    "INSERT INTO {} (holder, balance) VALUES (?, ?) ON CONFLICT (holder) DO UPDATE SET balance = excluded.balance",
+                    balances_table
+                )
+                .as_str(),
+            )
+            .bind(&holder)
+            .bind(sortable(&balance))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        }
+        Ok(())
+    }
+
+    /// Reverts balances changes made by the transfers of the watched CBC20 tokens in the blocks range.
+    /// Must be called before the transfers are removed
+    async fn revert_token_balances(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        from: i64,
+        to: i64,
+    ) -> Result<()> {
+        let stmt = sqlx::query(
+            format!(
+                "SELECT name FROM sqlite_master WHERE type='table' AND name LIKE '{}_cbc20_%_balances'",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let table_names: Vec<String> = stmt
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .collect();
+        for balances_table in table_names {
+            let transfers = sqlx::query_as::<_, TokenTransfer>(
+                format!(
+                    "SELECT {} FROM {}_transfers WHERE block_number >= ? AND block_number <= ?",
+                    transfer_columns(&balances_table),
+                    balances_table.trim_end_matches("_balances")
+                )
+                .as_str(),
+            )
+            .bind(from)
+            .bind(to)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            self.update_token_balances(tx, &balances_table, &transfers, true)
+                .await?;
+        }
         Ok(())
     }
 }
 
 #[async_trait]
 impl Storage for Sqlite3Storage {
-    /// Checks if the database exists. If not, it will be created. Then, the connection to the database will be established and the database will be migrated.
+    /// Checks if the database exists. If not, it will be created. Then, the connection to the database will be established and pending migrations will be applied.
     async fn prepare_db(&self) -> Result<()> {
-        self.migrate_db().await?;
+        self.run_migrations(false).await?;
         self.create_indexes().await?;
         Ok(())
     }
 
+    async fn schema_version(&self) -> Result<i64> {
+        let table = sqlx::query(
+            format!(
+                "SELECT name FROM sqlite_master WHERE type='table' AND name = '{}_schema_version'",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .fetch_optional(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        if table.is_none() {
+            return Ok(0);
+        }
+        let row = sqlx::query(
+            format!(
+                "SELECT COALESCE(MAX(version), 0) AS version FROM {}_schema_version",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .fetch_one(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(row.get::<i64, _>("version"))
+    }
+
+    async fn apply_migration(&self, migration: Migration) -> Result<()> {
+        info!("Applying migration {}", migration);
+        let mut tx = self
+            .get_db()
+            .begin()
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        sqlx::query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS {}_schema_version (
+                version INTEGER PRIMARY KEY NOT NULL,
+                description TEXT NOT NULL,
+                applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        match migration {
+            Migration::CreateTables => self.create_tables(&mut tx).await?,
+            Migration::NumericValues => self.migrate_numeric_columns(&mut tx).await?,
+            Migration::TokenTableNames => self.migrate_token_table_names(&mut tx).await?,
+        }
+        sqlx::query(
+            format!(
+                "INSERT INTO {}_schema_version (version, description) VALUES (?, ?)",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(migration.version())
+        .bind(migration.description())
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        tx.commit()
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(())
+    }
+
     async fn create_indexes(&self) -> Result<()> {
         let queries = vec![
             format!("CREATE INDEX IF NOT EXISTS idx_{0}_blocks_hash ON {0}_blocks (hash);", self.tables_prefix),
@@ -136,6 +774,20 @@ impl Storage for Sqlite3Storage {
             format!("CREATE INDEX IF NOT EXISTS idx_{0}_transactions_block_hash ON {0}_transactions (block_hash);", self.tables_prefix),
             format!("CREATE INDEX IF NOT EXISTS idx_{0}_transactions_from_addr ON {0}_transactions (from_addr);", self.tables_prefix),
             format!("CREATE INDEX IF NOT EXISTS idx_{0}_transactions_to_addr ON {0}_transactions (to_addr);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_receipts_block_number ON {0}_receipts (block_number);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_receipts_contract_address ON {0}_receipts (contract_address);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_logs_address ON {0}_logs (address);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_logs_topic0 ON {0}_logs (topic0);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_logs_block_number ON {0}_logs (block_number);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_traces_from_addr ON {0}_traces (from_addr);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_traces_to_addr ON {0}_traces (to_addr);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_traces_block_number ON {0}_traces (block_number);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_balance_changes_address ON {0}_balance_changes (address, block_number);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_contracts_creator ON {0}_contracts (creator);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_contracts_block_number ON {0}_contracts (block_number);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_decode_errors_block_number ON {0}_decode_errors (block_number);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_orphaned_blocks_number ON {0}_orphaned_blocks (number);", self.tables_prefix),
+            format!("CREATE INDEX IF NOT EXISTS idx_{0}_orphaned_transactions_block_hash ON {0}_orphaned_transactions (block_hash);", self.tables_prefix),
             format!("PRAGMA journal_mode = WAL;"),
             format!("PRAGMA wal_checkpoint(FULL);"),
         ];
@@ -153,7 +805,8 @@ impl Storage for Sqlite3Storage {
     async fn get_latest_block_number(&self) -> Result<i64> {
         let result = sqlx::query_as::<_, Block>(
             format!(
-                "SELECT * FROM {}_blocks ORDER BY number DESC LIMIT 1",
+                "SELECT {} FROM {}_blocks ORDER BY number DESC LIMIT 1",
+                block_columns(),
                 self.tables_prefix
             )
             .as_str(),
@@ -167,7 +820,8 @@ impl Storage for Sqlite3Storage {
                 // fetch block number from transactions table
                 let result = sqlx::query_as::<_, Transaction>(
                     format!(
-                        "SELECT * FROM {}_transactions ORDER BY block_number DESC LIMIT 1",
+                        "SELECT {} FROM {}_transactions ORDER BY block_number DESC LIMIT 1",
+                        transaction_columns(),
                         self.tables_prefix
                     )
                     .as_str(),
@@ -187,7 +841,8 @@ impl Storage for Sqlite3Storage {
                             Some(table_name) => {
                                 let result = sqlx::query_as::<_, TokenTransfer>(
                                     format!(
-                                        "SELECT * FROM {} ORDER BY block_number DESC LIMIT 1",
+                                        "SELECT {} FROM {} ORDER BY block_number DESC LIMIT 1",
+                                        transfer_columns(&table_name),
                                         table_name
                                     )
                                     .as_str(),
@@ -237,13 +892,20 @@ impl Storage for Sqlite3Storage {
                     "{}_{}_{}_transfers",
                     self.tables_prefix,
                     token,
-                    &address[..8]
+                    IcanAddress::table_id(&address)
                 );
                 let tx_hash_foreign_key = if self.modules.contains(&"transactions".to_string()) {
                     format!(", CONSTRAINT fk_{0}_tx_hash FOREIGN KEY (tx_hash) REFERENCES {0}_transactions(hash) ON DELETE CASCADE ON UPDATE CASCADE", self.tables_prefix)
                 } else {
                     "".to_string()
                 };
+                // NFT and multi-token transfers have an ID of the transferred token,
+                // rows of a multi-token batch share the transfer index and differ by the batch index
+                let token_id_column = if TOKEN_ID_TYPES.contains(&token.as_str()) {
+                    ", token_id TEXT NOT NULL, batch_index INTEGER NOT NULL DEFAULT 0"
+                } else {
+                    ""
+                };
                 let query = format!(
                     "CREATE TABLE IF NOT EXISTS {table_name} (
                     block_number INTEGER NOT NULL,
@@ -256,84 +918,291 @@ impl Storage for Sqlite3Storage {
                     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                     status INTEGER DEFAULT 0
                     {0}
+                    {1}
                 );",
-                    tx_hash_foreign_key
+                    token_id_column, tx_hash_foreign_key
                 );
                 sqlx::query(&query)
                     .execute(self.get_db())
                     .await
                     .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
                 debug!("Created token transfers table: {}", table_name);
+
+                if token == "cbc20" {
+                    let balances_table_name = format!(
+                        "{}_{}_{}_balances",
+                        self.tables_prefix,
+                        token,
+                        IcanAddress::table_id(&address)
+                    );
+                    let query = format!(
+                        "CREATE TABLE IF NOT EXISTS {balances_table_name} (
+                        holder TEXT PRIMARY KEY NOT NULL,
+                        balance TEXT NOT NULL
+                    );"
+                    );
+                    sqlx::query(&query)
+                        .execute(self.get_db())
+                        .await
+                        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                    sqlx::query(
+                        format!(
+                            "CREATE INDEX IF NOT EXISTS idx_{0}_balance ON {0} (balance);",
+                            balances_table_name
+                        )
+                        .as_str(),
+                    )
+                    .execute(self.get_db())
+                    .await
+                    .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                    debug!("Created token balances table: {}", balances_table_name);
+                }
             }
         }
         Ok(())
     }
 
-    async fn clean_block_data(&self, block_number: i64) -> Result<()> {
+    async fn create_decoded_tables(&self, tables: Vec<DecodedTable>) -> Result<()> {
+        for table in tables {
+            let table_name = format!("{}_{}", self.tables_prefix, table.name);
+            // argument columns are quoted, as their names come from the ABI
+            let query = format!(
+                "CREATE TABLE IF NOT EXISTS {table_name} (
+                block_number INTEGER NOT NULL,
+                block_hash TEXT,
+                tx_hash TEXT NOT NULL,
+                address TEXT NOT NULL,
+                log_index INTEGER NOT NULL,
+                status INTEGER DEFAULT 0,
+                {}
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );",
+                table
+                    .columns
+                    .iter()
+                    .map(|(name, _)| format!("{} TEXT,", quoted(name)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+            sqlx::query(&query)
+                .execute(self.get_db())
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            sqlx::query(
+                format!(
+                    "CREATE INDEX IF NOT EXISTS idx_{0}_block_number ON {0} (block_number);",
+                    table_name
+                )
+                .as_str(),
+            )
+            .execute(self.get_db())
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            debug!("Created decoded table: {}", table_name);
+        }
+        Ok(())
+    }
+
+    async fn rollback_blocks(&self, from: i64) -> Result<()> {
         let mut tx = self
             .get_db()
             .begin()
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        // the depth is read in the transaction, so blocks inserted meanwhile are counted
+        let reorg_depth = self.latest_archived_block(&mut tx).await? - from + 1;
 
-        sqlx::query(format!("DELETE FROM {}_blocks WHERE number = ?", self.tables_prefix).as_str())
-            .bind(block_number)
-            .execute(&mut tx)
+        self.archive_orphaned_blocks(&mut tx, from, i64::MAX, reorg_depth)
+            .await?;
+        self.revert_token_balances(&mut tx, from, i64::MAX).await?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_blocks WHERE number >= ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(from)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_transactions WHERE block_number >= ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(from)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_receipts WHERE block_number >= ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(from)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_logs WHERE block_number >= ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(from)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_traces WHERE block_number >= ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(from)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_balance_changes WHERE block_number >= ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(from)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_contracts WHERE block_number >= ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(from)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let stmt = sqlx::query(
+            format!(
+                "SELECT name FROM sqlite_master WHERE type='table' AND (name LIKE '{0}_%_transfers' OR name LIKE '{0}_%_calls' OR name LIKE '{0}_%_events' OR name = '{0}_decode_errors')",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .fetch_all(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
+
+        let table_names: Vec<String> = stmt
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .collect();
+        for table in table_names {
+            sqlx::query(format!("DELETE FROM {} WHERE block_number >= ?", table).as_str())
+                .bind(from)
+                .execute(&mut tx)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        }
+
+        tx.commit()
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
+        Ok(())
+    }
+
+    async fn clean_last_blocks(&self, number: i64) -> Result<()> {
+        let from = self.get_latest_block_number().await? - number + 1;
+        let mut tx = self
+            .get_db()
+            .begin()
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        self.revert_token_balances(&mut tx, from, i64::MAX).await?;
+
         sqlx::query(
             format!(
-                "DELETE FROM {}_transactions WHERE block_number = ?",
-                self.tables_prefix
+                "DELETE FROM {}_blocks WHERE number > (SELECT MAX(number) FROM {}_blocks) - ?",
+                self.tables_prefix, self.tables_prefix
             )
             .as_str(),
         )
-        .bind(block_number)
+        .bind(number)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_transactions WHERE block_number > (SELECT MAX(block_number) FROM {}_transactions) - ?",
+                self.tables_prefix, self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(number)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_receipts WHERE block_number > (SELECT MAX(block_number) FROM {}_receipts) - ?",
+                self.tables_prefix, self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(number)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        sqlx::query(
+            format!(
+                "DELETE FROM {}_logs WHERE block_number > (SELECT MAX(block_number) FROM {}_logs) - ?",
+                self.tables_prefix, self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(number)
         .execute(&mut tx)
         .await
         .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
-        let stmt = sqlx::query(
+        sqlx::query(
             format!(
-                "SELECT name FROM sqlite_master WHERE type='table' AND name LIKE '{}_%_transfers'",
-                self.tables_prefix
+                "DELETE FROM {}_traces WHERE block_number > (SELECT MAX(block_number) FROM {}_traces) - ?",
+                self.tables_prefix, self.tables_prefix
             )
             .as_str(),
         )
-        .fetch_all(&self.pool)
+        .bind(number)
+        .execute(&mut tx)
         .await
-        .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
-
-        let table_names: Vec<String> = stmt
-            .iter()
-            .map(|row| row.get::<String, _>("name"))
-            .collect();
-        for table in table_names {
-            sqlx::query(format!("DELETE FROM {} WHERE block_number = ?", table).as_str())
-                .bind(block_number)
-                .execute(&mut tx)
-                .await
-                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        }
-
-        tx.commit()
-            .await
-            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-
-        Ok(())
-    }
-
-    async fn clean_last_blocks(&self, number: i64) -> Result<()> {
-        let mut tx = self
-            .get_db()
-            .begin()
-            .await
-            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
         sqlx::query(
             format!(
-                "DELETE FROM {}_blocks WHERE number > (SELECT MAX(number) FROM {}_blocks) - ?",
+                "DELETE FROM {}_balance_changes WHERE block_number > (SELECT MAX(block_number) FROM {}_balance_changes) - ?",
                 self.tables_prefix, self.tables_prefix
             )
             .as_str(),
@@ -345,7 +1214,7 @@ impl Storage for Sqlite3Storage {
 
         sqlx::query(
             format!(
-                "DELETE FROM {}_transactions WHERE block_number > (SELECT MAX(block_number) FROM {}_transactions) - ?",
+                "DELETE FROM {}_contracts WHERE block_number > (SELECT MAX(block_number) FROM {}_contracts) - ?",
                 self.tables_prefix, self.tables_prefix
             )
             .as_str(),
@@ -357,7 +1226,7 @@ impl Storage for Sqlite3Storage {
 
         let stmt = sqlx::query(
             format!(
-                "SELECT name FROM sqlite_master WHERE type='table' AND name LIKE '{}_%_transfers'",
+                "SELECT name FROM sqlite_master WHERE type='table' AND (name LIKE '{0}_%_transfers' OR name LIKE '{0}_%_calls' OR name LIKE '{0}_%_events' OR name = '{0}_decode_errors')",
                 self.tables_prefix
             )
             .as_str(),
@@ -391,81 +1260,331 @@ impl Storage for Sqlite3Storage {
         Ok(())
     }
 
+    fn insert_threshold(&self) -> usize {
+        750
+    }
+
     async fn insert_blocks_with_txs_and_token_transfers(
         &self,
         insert_all: bool,
-        blocks: &mut Vec<Block>,
-        transactions: &mut Vec<Transaction>,
-        token_transfers: &mut HashMap<String, Vec<TokenTransfer>>,
+        batch: &mut Batch,
     ) -> Result<()> {
-        if blocks.len() > 750 || transactions.len() > 750 || insert_all {
+        let ready = self.is_batch_ready(batch, insert_all);
+        let Batch {
+            blocks,
+            transactions,
+            token_transfers,
+            receipts,
+            logs,
+            traces,
+            balance_changes,
+            contracts,
+            decoded,
+            decode_errors,
+        } = batch;
+        if ready {
             let mut tx = self
                 .get_db()
                 .begin()
                 .await
                 .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            // rows are stored with the timestamp of their block
             let mut timestamp_map = HashMap::new();
             let mut block_number_map = HashMap::new();
-            if !blocks.is_empty() {
-                let query = format!(
-                    "INSERT INTO {}_blocks (number, hash, parent_hash, nonce, sha3_uncles, logs_bloom, transactions_root, state_root, receipts_root, miner, difficulty, total_difficulty, extra_data, energy_limit, energy_used, timestamp, transaction_count, matured, created_at) VALUES {}",
-                    self.tables_prefix,
-                    blocks.iter().map(|block| {
-                        let created_at = Utc.timestamp_opt(block.timestamp, 0).unwrap().format("%Y-%m-%d %H:%M:%S").to_string();
-                        timestamp_map.insert(&block.hash, created_at.clone()); // Save timestamp for use in transactions
-                        format!(
-                            "({}, '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', {}, {}, {}, {}, {}, '{}')",
-                            block.number, block.hash, block.parent_hash, block.nonce, block.sha3_uncles, block.logs_bloom, block.transactions_root, block.state_root, block.receipts_root, block.miner, block.difficulty, block.total_difficulty, block.extra_data, block.energy_limit, block.energy_used, block.timestamp, block.transaction_count, block.matured, created_at
-                        )
-                    }).collect::<Vec<_>>().join(", ")
-                );
-                if self.modules.contains(&"blocks".to_string()) {
-                    sqlx::query(&query)
-                        .execute(&mut tx)
-                        .await
-                        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                    debug!("Inserted blocks: {:?}", blocks.len());
-                }
+            for block in blocks.iter() {
+                let created_at = Utc
+                    .timestamp_opt(block.timestamp, 0)
+                    .single()
+                    .ok_or(Sqlite3StorageError::InvalidTimestamp(
+                        block.number,
+                        block.timestamp,
+                    ))?
+                    .naive_utc();
+                timestamp_map.insert(&block.hash, created_at);
+            }
+            for transaction in transactions.iter() {
+                let created_at = block_value(&timestamp_map, &transaction.block_hash)?;
+                timestamp_map.insert(&transaction.hash, created_at);
+                block_number_map.insert(&transaction.hash, transaction.block_number);
             }
 
-            if !transactions.is_empty() {
-                let query = format!(
-                    "INSERT INTO {}_transactions (hash, nonce, block_hash, block_number, transaction_index, from_addr, to_addr, value, energy, energy_price, input, created_at) VALUES {}",
-                    self.tables_prefix,
-                    transactions.iter().map(|tx| {
-                        timestamp_map.insert(&tx.hash, timestamp_map.get(&tx.block_hash).unwrap().to_string()); // Save timestamp for use in token transfers
-                        block_number_map.insert(&tx.hash, tx.block_number); // save block number so we can set block number in token transfers
-                        format!(
-                        "('{}', '{}', '{}', {}, {}, '{}', '{}', '{}', '{}', '{}', '{}', '{}')",
-                        tx.hash, tx.nonce, tx.block_hash, tx.block_number, tx.transaction_index, tx.from, tx.to, tx.value, tx.energy, tx.energy_price, tx.input, timestamp_map.get(&tx.block_hash).unwrap()
-                    )}).collect::<Vec<_>>().join(", ")
-                );
-                if self.modules.contains(&"transactions".to_string()) {
-                    sqlx::query(&query)
-                        .execute(&mut tx)
-                        .await
-                        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                    debug!("Inserted transactions: {:?}", transactions.len());
-                }
+            if !blocks.is_empty() && self.modules.contains(&"blocks".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_blocks", self.tables_prefix),
+                    "number, hash, parent_hash, nonce, sha3_uncles, logs_bloom, transactions_root, state_root, receipts_root, miner, difficulty, total_difficulty, extra_data, energy_limit, energy_used, timestamp, transaction_count, matured, created_at",
+                    blocks,
+                    |row, block| {
+                        row.push_bind(block.number)
+                            .push_bind(&block.hash)
+                            .push_bind(&block.parent_hash)
+                            .push_bind(&block.nonce)
+                            .push_bind(&block.sha3_uncles)
+                            .push_bind(&block.logs_bloom)
+                            .push_bind(&block.transactions_root)
+                            .push_bind(&block.state_root)
+                            .push_bind(&block.receipts_root)
+                            .push_bind(&block.miner)
+                            .push_bind(sortable(&block.difficulty))
+                            .push_bind(sortable(&block.total_difficulty))
+                            .push_bind(&block.extra_data)
+                            .push_bind(block.energy_limit)
+                            .push_bind(block.energy_used)
+                            .push_bind(block.timestamp)
+                            .push_bind(block.transaction_count)
+                            .push_bind(block.matured)
+                            .push_bind(block_value(&timestamp_map, &block.hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted blocks: {:?}", blocks.len());
+            }
+
+            if !transactions.is_empty() && self.modules.contains(&"transactions".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_transactions", self.tables_prefix),
+                    "hash, nonce, block_hash, block_number, transaction_index, from_addr, to_addr, value, energy, energy_price, input, created_at",
+                    transactions,
+                    |row, transaction| {
+                        row.push_bind(&transaction.hash)
+                            .push_bind(&transaction.nonce)
+                            .push_bind(&transaction.block_hash)
+                            .push_bind(transaction.block_number)
+                            .push_bind(transaction.transaction_index)
+                            .push_bind(&transaction.from)
+                            .push_bind(&transaction.to)
+                            .push_bind(sortable(&transaction.value))
+                            .push_bind(&transaction.energy)
+                            .push_bind(sortable(&transaction.energy_price))
+                            .push_bind(&transaction.input)
+                            .push_bind(block_value(&timestamp_map, &transaction.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted transactions: {:?}", transactions.len());
             }
 
             for (table_name, transfers) in token_transfers.clone() {
                 if !transfers.is_empty() && self.modules.contains(&"token_transfers".to_string()) {
-                    let query = format!(
-                        "INSERT INTO {}_{} (block_number, from_addr, to_addr, value, tx_hash, address, transfer_index, created_at, status) VALUES {}",
-                        self.tables_prefix,
-                        table_name,
-                        transfers.iter().map(|tt| format!(
-                            "({},'{}', '{}', '{}', '{}', '{}', {}, '{}', {})",
-                            block_number_map.get(&tt.tx_hash).unwrap(), tt.from, tt.to, tt.value, tt.tx_hash, tt.address, tt.index, timestamp_map.get(&tt.tx_hash).unwrap(), tt.status
-                        )).collect::<Vec<_>>().join(", ")
-                    );
-                    sqlx::query(&query)
-                        .execute(&mut tx)
-                        .await
-                        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                    let with_token_id = has_token_ids(&table_name);
+                    insert_rows(
+                        &mut tx,
+                        &format!("{}_{}", self.tables_prefix, table_name),
+                        &format!(
+                            "block_number, from_addr, to_addr, value, tx_hash, address, transfer_index, created_at, status{}",
+                            if with_token_id { ", token_id, batch_index" } else { "" }
+                        ),
+                        &transfers,
+                        |row, tt| {
+                            row.push_bind(block_value(&block_number_map, &tt.tx_hash)?)
+                                .push_bind(&tt.from)
+                                .push_bind(&tt.to)
+                                .push_bind(sortable(&tt.value))
+                                .push_bind(&tt.tx_hash)
+                                .push_bind(&tt.address)
+                                .push_bind(tt.index)
+                                .push_bind(block_value(&timestamp_map, &tt.tx_hash)?)
+                                .push_bind(tt.status);
+                            if with_token_id {
+                                row.push_bind(sortable(&tt.token_id))
+                                    .push_bind(tt.batch_index);
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await
+                    .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
                     debug!("Inserted token transfers: {:?}", transfers.len());
+
+                    if table_name.starts_with("cbc20_") {
+                        let balances_table = format!(
+                            "{}_{}_balances",
+                            self.tables_prefix,
+                            table_name.trim_end_matches("_transfers")
+                        );
+                        self.update_token_balances(&mut tx, &balances_table, &transfers, false)
+                            .await?;
+                    }
+                }
+            }
+            if !receipts.is_empty() && self.modules.contains(&"receipts".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_receipts", self.tables_prefix),
+                    "transaction_hash, block_hash, block_number, transaction_index, status, energy_used, cumulative_energy_used, effective_energy_price, contract_address, logs_bloom, created_at",
+                    receipts,
+                    |row, r| {
+                        row.push_bind(&r.transaction_hash)
+                            .push_bind(&r.block_hash)
+                            .push_bind(r.block_number)
+                            .push_bind(r.transaction_index)
+                            .push_bind(r.status)
+                            .push_bind(r.energy_used)
+                            .push_bind(r.cumulative_energy_used)
+                            .push_bind(&r.effective_energy_price)
+                            .push_bind(&r.contract_address)
+                            .push_bind(&r.logs_bloom)
+                            .push_bind(block_value(&timestamp_map, &r.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted receipts: {:?}", receipts.len());
+            }
+            if !logs.is_empty() && self.modules.contains(&"logs".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_logs", self.tables_prefix),
+                    "block_number, block_hash, tx_hash, log_index, address, topic0, topic1, topic2, topic3, data, created_at",
+                    logs,
+                    |row, log| {
+                        row.push_bind(log.block_number)
+                            .push_bind(&log.block_hash)
+                            .push_bind(&log.tx_hash)
+                            .push_bind(log.log_index)
+                            .push_bind(&log.address)
+                            .push_bind(&log.topic0)
+                            .push_bind(&log.topic1)
+                            .push_bind(&log.topic2)
+                            .push_bind(&log.topic3)
+                            .push_bind(&log.data)
+                            .push_bind(block_value(&timestamp_map, &log.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted logs: {:?}", logs.len());
+            }
+            if !traces.is_empty() && self.modules.contains(&"traces".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_traces", self.tables_prefix),
+                    "block_number, block_hash, tx_hash, trace_address, depth, call_type, from_addr, to_addr, value, error, created_at",
+                    traces,
+                    |row, trace| {
+                        row.push_bind(trace.block_number)
+                            .push_bind(&trace.block_hash)
+                            .push_bind(&trace.tx_hash)
+                            .push_bind(&trace.trace_address)
+                            .push_bind(trace.depth)
+                            .push_bind(&trace.call_type)
+                            .push_bind(&trace.from)
+                            .push_bind(&trace.to)
+                            .push_bind(sortable(&trace.value))
+                            .push_bind(&trace.error)
+                            .push_bind(block_value(&timestamp_map, &trace.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted traces: {:?}", traces.len());
+            }
+            if !balance_changes.is_empty() && self.modules.contains(&"balance_changes".to_string())
+            {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_balance_changes", self.tables_prefix),
+                    "block_number, block_hash, address, delta, created_at",
+                    balance_changes,
+                    |row, change| {
+                        row.push_bind(change.block_number)
+                            .push_bind(&change.block_hash)
+                            .push_bind(&change.address)
+                            .push_bind(&change.delta)
+                            .push_bind(block_value(&timestamp_map, &change.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted balance changes: {:?}", balance_changes.len());
+            }
+            if !contracts.is_empty() && self.modules.contains(&"contracts".to_string()) {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_contracts", self.tables_prefix),
+                    "address, creator, tx_hash, block_number, block_hash, bytecode_hash, is_cbc20, name, symbol, decimals, created_at",
+                    contracts,
+                    |row, contract| {
+                        row.push_bind(&contract.address)
+                            .push_bind(&contract.creator)
+                            .push_bind(&contract.tx_hash)
+                            .push_bind(contract.block_number)
+                            .push_bind(&contract.block_hash)
+                            .push_bind(&contract.bytecode_hash)
+                            .push_bind(contract.is_cbc20)
+                            .push_bind(&contract.name)
+                            .push_bind(&contract.symbol)
+                            .push_bind(contract.decimals)
+                            .push_bind(block_value(&timestamp_map, &contract.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted contracts: {:?}", contracts.len());
+            }
+            for (table_name, rows) in decoded.iter() {
+                if rows.is_empty() {
+                    continue;
                 }
+                // argument columns are quoted, as their names come from the ABI
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_{}", self.tables_prefix, table_name),
+                    &format!(
+                        "block_number, block_hash, tx_hash, address, log_index, status, {}created_at",
+                        rows[0].args.iter().map(|(name, _)| format!("{}, ", quoted(name))).collect::<String>()
+                    ),
+                    rows,
+                    |row, decoded_row| {
+                        row.push_bind(decoded_row.block_number)
+                            .push_bind(&decoded_row.block_hash)
+                            .push_bind(&decoded_row.tx_hash)
+                            .push_bind(&decoded_row.address)
+                            .push_bind(decoded_row.log_index)
+                            .push_bind(decoded_row.status);
+                        for (_, value) in &decoded_row.args {
+                            row.push_bind(value);
+                        }
+                        row.push_bind(block_value(&timestamp_map, &decoded_row.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted decoded {}: {:?}", table_name, rows.len());
+            }
+            if !decode_errors.is_empty() {
+                insert_rows(
+                    &mut tx,
+                    &format!("{}_decode_errors", self.tables_prefix),
+                    "block_number, block_hash, tx_hash, address, log_index, topics, data, error, created_at",
+                    decode_errors,
+                    |row, failure| {
+                        row.push_bind(failure.block_number)
+                            .push_bind(&failure.block_hash)
+                            .push_bind(&failure.tx_hash)
+                            .push_bind(&failure.address)
+                            .push_bind(failure.log_index)
+                            .push_bind(&failure.topics)
+                            .push_bind(&failure.data)
+                            .push_bind(&failure.error)
+                            .push_bind(block_value(&timestamp_map, &failure.block_hash)?);
+                        Ok(())
+                    },
+                )
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+                debug!("Inserted decode errors: {:?}", decode_errors.len());
             }
             debug!("Committing transaction");
             tx.commit()
@@ -473,9 +1592,7 @@ impl Storage for Sqlite3Storage {
                 .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
             timestamp_map.clear();
             block_number_map.clear();
-            blocks.clear();
-            transactions.clear();
-            token_transfers.values_mut().for_each(|v| v.clear());
+            batch.clear();
         }
         Ok(())
     }
@@ -492,7 +1609,7 @@ impl Storage for Sqlite3Storage {
                 let cutoff_datetime = cutoff.format("%Y-%m-%d %H:%M:%S").to_string();
 
                 let stmt = sqlx::query(format!(
-                    "SELECT name FROM sqlite_master WHERE type='table' AND name LIKE '{}_%_transfers'", tables_prefix).as_str(),
+                    "SELECT name FROM sqlite_master WHERE type='table' AND (name LIKE '{0}_%_transfers' OR name LIKE '{0}_%_calls' OR name LIKE '{0}_%_events' OR name = '{0}_decode_errors')", tables_prefix).as_str(),
                 )
                 .fetch_all(&pool)
                 .await
@@ -513,6 +1630,10 @@ impl Storage for Sqlite3Storage {
 
                 table_names.push(format!("{}_blocks", tables_prefix));
                 table_names.push(format!("{}_transactions", tables_prefix));
+                table_names.push(format!("{}_receipts", tables_prefix));
+                table_names.push(format!("{}_logs", tables_prefix));
+                table_names.push(format!("{}_traces", tables_prefix));
+                // balance changes are not removed, balances are calculated from all of them
 
                 for table in table_names {
                     let delete_query = format!("DELETE FROM {} WHERE created_at < ?", table);
@@ -539,29 +1660,39 @@ impl Storage for Sqlite3Storage {
 
     async fn get_token_transfers(
         &self,
-        token_address: String,
-        from: Option<String>,
-        to: Option<String>,
+        token_address: IcanAddress,
+        from: Option<IcanAddress>,
+        to: Option<IcanAddress>,
     ) -> Result<Vec<TokenTransfer>> {
-        let selector = &token_address[..8];
+        let token_address = token_address.to_string();
+        let from = from.map(|from| from.to_string());
+        let to = to.map(|to| to.to_string());
+        let selector = IcanAddress::table_id(&token_address);
         let prefix = self.tables_prefix.clone();
-        let stmt = sqlx::query(&format!(
-            "SELECT name FROM sqlite_master WHERE type='table' AND name LIKE '{prefix}_%_{selector}_transfers'"
-        ))
-        .fetch_all(self.get_db())
-        .await .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        let stmt = sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name LIKE ?")
+            .bind(format!("{prefix}_%_{selector}_transfers"))
+            .fetch_all(self.get_db())
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
 
-        let table_name: String = stmt.first().map(|row| row.get("name")).unwrap();
+        // tokens which are not watched have no transfers table
+        let Some(table_name) = stmt.first().map(|row| row.get::<String, _>("name")) else {
+            return Ok(vec![]);
+        };
 
-        let mut query = format!("SELECT * FROM {table_name} WHERE 1 = 1");
+        let mut query = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT {} FROM {table_name} WHERE 1 = 1",
+            transfer_columns(&table_name)
+        ));
         if let Some(from) = from {
-            query += &format!(" AND from_addr = '{}'", from);
+            query.push(" AND from_addr = ").push_bind(from);
         }
         if let Some(to) = to {
-            query += &format!(" AND to_addr = '{}'", to);
+            query.push(" AND to_addr = ").push_bind(to);
         }
 
-        let token_transfers = sqlx::query_as::<_, TokenTransfer>(&query)
+        let token_transfers = query
+            .build_query_as::<TokenTransfer>()
             .fetch_all(self.get_db())
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
@@ -586,7 +1717,8 @@ impl Storage for Sqlite3Storage {
             .iter()
             .map(|table| {
                 format!(
-                    "SELECT from_addr, to_addr, value, tx_hash, address FROM {} WHERE tx_hash = ?",
+                    "SELECT {} FROM {} WHERE tx_hash = ?1",
+                    transfer_columns(table),
                     table
                 )
             })
@@ -603,9 +1735,10 @@ impl Storage for Sqlite3Storage {
 
     async fn get_address_token_transfers(
         &self,
-        address: String,
+        address: IcanAddress,
         transfer_type: TransferType,
     ) -> Result<Vec<TokenTransfer>> {
+        let address = address.to_string();
         let stmt = sqlx::query(
             format!(
                 "SELECT name FROM sqlite_master WHERE type='table' AND name LIKE '{}_%_transfers'",
@@ -622,29 +1755,279 @@ impl Storage for Sqlite3Storage {
         let query = table_names
             .iter()
             .map(|table| {
+                // all parts of the union share the bound address
                 let condition = match transfer_type {
-                    TransferType::From => format!("from_addr = '{}'", address),
-                    TransferType::To => format!("to_addr = '{}'", address),
-                    TransferType::All => {
-                        format!("from_addr = '{}' OR to_addr = '{}'", address, address)
-                    }
+                    TransferType::From => "from_addr = ?1",
+                    TransferType::To => "to_addr = ?1",
+                    TransferType::All => "from_addr = ?1 OR to_addr = ?1",
                 };
-                format!("SELECT * FROM {} WHERE {}", table, condition)
+                format!(
+                    "SELECT {} FROM {} WHERE {}",
+                    transfer_columns(table),
+                    table,
+                    condition
+                )
             })
             .collect::<Vec<_>>()
             .join(" UNION ALL ");
 
         let token_transfers = sqlx::query_as::<_, TokenTransfer>(&query)
+            .bind(address)
             .fetch_all(self.get_db())
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
         Ok(token_transfers)
     }
 
+    async fn get_token_balance(
+        &self,
+        token: IcanAddress,
+        holder: IcanAddress,
+    ) -> Result<TokenBalance> {
+        let token = token.to_string();
+        let holder = holder.to_string();
+        let balance = sqlx::query_as::<_, TokenBalance>(
+            format!(
+                "SELECT holder, {} FROM {}_cbc20_{}_balances WHERE holder = ?",
+                unpadded("balance"),
+                self.tables_prefix,
+                IcanAddress::table_id(&token)
+            )
+            .as_str(),
+        )
+        .bind(&holder)
+        .fetch_optional(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        Ok(balance.unwrap_or(TokenBalance {
+            holder,
+            balance: "0".to_string(),
+        }))
+    }
+
+    async fn get_top_holders(&self, token: IcanAddress, limit: i64) -> Result<Vec<TokenBalance>> {
+        let token = token.to_string();
+        // ordered by the padded column, as the selected balance is unpadded
+        let balances = sqlx::query_as::<_, TokenBalance>(
+            format!(
+                "SELECT holder, {0} FROM {1}_cbc20_{2}_balances ORDER BY {1}_cbc20_{2}_balances.balance DESC LIMIT ?",
+                unpadded("balance"),
+                self.tables_prefix,
+                IcanAddress::table_id(&token)
+            )
+            .as_str(),
+        )
+        .bind(limit)
+        .fetch_all(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(balances)
+    }
+
+    async fn get_transaction_receipt(&self, tx_hash: String) -> Result<Receipt> {
+        let receipt = sqlx::query_as::<_, Receipt>(
+            format!(
+                "SELECT * FROM {}_receipts WHERE transaction_hash = ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(tx_hash)
+        .fetch_one(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(receipt)
+    }
+
+    async fn get_transaction_logs(&self, tx_hash: String) -> Result<Vec<Log>> {
+        let logs = sqlx::query_as::<_, Log>(
+            format!(
+                "SELECT * FROM {}_logs WHERE tx_hash = ? ORDER BY log_index",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(tx_hash)
+        .fetch_all(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(logs)
+    }
+
+    async fn get_balance_changes(&self, address: IcanAddress) -> Result<Vec<BalanceChange>> {
+        let address = address.to_string();
+        let balance_changes = sqlx::query_as::<_, BalanceChange>(
+            format!(
+                "SELECT * FROM {}_balance_changes WHERE address = ? ORDER BY block_number",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(address)
+        .fetch_all(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(balance_changes)
+    }
+
+    async fn get_balance_at(&self, address: IcanAddress, block_number: i64) -> Result<String> {
+        let address = address.to_string();
+        let stmt = sqlx::query(
+            format!(
+                "SELECT delta FROM {}_balance_changes WHERE address = ? AND block_number <= ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(address)
+        .bind(block_number)
+        .fetch_all(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        // deltas do not fit into sqlite integers, so they are summed here
+        let balance = stmt
+            .iter()
+            .try_fold(0i128, |balance, row| {
+                row.get::<String, _>("delta")
+                    .parse::<i128>()
+                    .map(|delta| balance + delta)
+            })
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(balance.to_string())
+    }
+
+    async fn get_transaction_traces(&self, tx_hash: String) -> Result<Vec<Trace>> {
+        let mut traces = sqlx::query_as::<_, Trace>(
+            format!(
+                "SELECT {} FROM {}_traces WHERE tx_hash = ?",
+                trace_columns(),
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(tx_hash)
+        .fetch_all(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        traces.sort_by_key(|trace| trace.call_path());
+        Ok(traces)
+    }
+
+    async fn get_logs(
+        &self,
+        address: Option<IcanAddress>,
+        topic0: Option<String>,
+        from_block: Option<i64>,
+        to_block: Option<i64>,
+    ) -> Result<Vec<Log>> {
+        let address = address.map(|address| address.to_string());
+        let mut query = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT * FROM {}_logs WHERE 1 = 1",
+            self.tables_prefix
+        ));
+        if let Some(address) = address {
+            query.push(" AND address = ").push_bind(address);
+        }
+        if let Some(topic0) = topic0 {
+            query.push(" AND topic0 = ").push_bind(topic0);
+        }
+        if let Some(from_block) = from_block {
+            query.push(" AND block_number >= ").push_bind(from_block);
+        }
+        if let Some(to_block) = to_block {
+            query.push(" AND block_number <= ").push_bind(to_block);
+        }
+        query.push(" ORDER BY block_number, log_index");
+
+        let logs = query
+            .build_query_as::<Log>()
+            .fetch_all(self.get_db())
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(logs)
+    }
+
+    async fn add_discovered_token(
+        &self,
+        token: String,
+        address: String,
+        block_number: i64,
+    ) -> Result<()> {
+        sqlx::query(
+            format!(
+                "INSERT OR IGNORE INTO {}_discovered_tokens (address, token, block_number) VALUES (?, ?, ?)",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(address)
+        .bind(token)
+        .bind(block_number)
+        .execute(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(())
+    }
+
+    async fn get_discovered_tokens(&self) -> Result<HashMap<String, HashSet<String>>> {
+        let stmt = sqlx::query(
+            format!(
+                "SELECT token, address FROM {}_discovered_tokens",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .fetch_all(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+
+        let mut tokens: HashMap<String, HashSet<String>> = HashMap::new();
+        for row in stmt {
+            tokens
+                .entry(row.get("token"))
+                .or_default()
+                .insert(row.get("address"));
+        }
+        Ok(tokens)
+    }
+
+    async fn get_contract(&self, address: IcanAddress) -> Result<Contract> {
+        let address = address.to_string();
+        let contract = sqlx::query_as::<_, Contract>(
+            format!(
+                "SELECT * FROM {}_contracts WHERE address = ?",
+                self.tables_prefix
+            )
+            .as_str(),
+        )
+        .bind(address)
+        .fetch_one(self.get_db())
+        .await
+        .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(contract)
+    }
+
+    async fn get_contracts(&self, only_cbc20: bool) -> Result<Vec<Contract>> {
+        let mut query = format!("SELECT * FROM {}_contracts", self.tables_prefix);
+        if only_cbc20 {
+            query += " WHERE is_cbc20 = 1";
+        }
+        query += " ORDER BY block_number";
+
+        let contracts = sqlx::query_as::<_, Contract>(&query)
+            .fetch_all(self.get_db())
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+        Ok(contracts)
+    }
+
     async fn get_block_transactions(&self, block_number: i64) -> Result<Vec<Transaction>> {
         let transactions = sqlx::query_as::<_, Transaction>(
             format!(
-                "SELECT * FROM {}_transactions WHERE block_number = ?",
+                "SELECT {} FROM {}_transactions WHERE block_number = ?",
+                transaction_columns(),
                 self.tables_prefix
             )
             .as_str(),
@@ -659,7 +2042,8 @@ impl Storage for Sqlite3Storage {
     async fn get_transaction_by_hash(&self, hash: String) -> Result<Transaction> {
         let transaction = sqlx::query_as::<_, Transaction>(
             format!(
-                "SELECT * FROM {}_transactions WHERE hash = ?",
+                "SELECT {} FROM {}_transactions WHERE hash = ?",
+                transaction_columns(),
                 self.tables_prefix
             )
             .as_str(),
@@ -673,7 +2057,12 @@ impl Storage for Sqlite3Storage {
 
     async fn get_all_blocks(&self) -> Result<Vec<Block>> {
         let blocks = sqlx::query_as::<_, Block>(
-            format!("SELECT * FROM {}_blocks", self.tables_prefix).as_str(),
+            format!(
+                "SELECT {} FROM {}_blocks",
+                block_columns(),
+                self.tables_prefix
+            )
+            .as_str(),
         )
         .fetch_all(self.get_db())
         .await
@@ -685,12 +2074,14 @@ impl Storage for Sqlite3Storage {
     /// if end is negative, it will return all blocks from start to the latest block.
     async fn get_blocks_in_range(&self, start: i64, end: i64) -> Result<Vec<Block>> {
         let mut query = format!(
-            "SELECT * FROM {}_blocks WHERE number >= ? AND number <= ?",
+            "SELECT {} FROM {}_blocks WHERE number >= ? AND number <= ?",
+            block_columns(),
             self.tables_prefix
         );
         if end < 0 {
             query = format!(
-                "SELECT * FROM {}_blocks WHERE number >= ?",
+                "SELECT {} FROM {}_blocks WHERE number >= ?",
+                block_columns(),
                 self.tables_prefix
             );
         }
@@ -706,7 +2097,8 @@ impl Storage for Sqlite3Storage {
     async fn get_block_by_number(&self, block_number: i64) -> Result<Block> {
         let block = sqlx::query_as::<_, Block>(
             format!(
-                "SELECT * FROM {}_blocks WHERE number = ?",
+                "SELECT {} FROM {}_blocks WHERE number = ?",
+                block_columns(),
                 self.tables_prefix
             )
             .as_str(),
@@ -721,7 +2113,8 @@ impl Storage for Sqlite3Storage {
     async fn get_block_by_hash(&self, block_hash: String) -> Result<Block> {
         let block = sqlx::query_as::<_, Block>(
             format!(
-                "SELECT * FROM {}_blocks WHERE hash = '?'",
+                "SELECT {} FROM {}_blocks WHERE hash = ?",
+                block_columns(),
                 self.tables_prefix
             )
             .as_str(),
@@ -1,2 +1,4 @@
+mod migration;
 mod storage;
+pub use migration::{has_token_ids, legacy_transfers_table, Migration, TOKEN_ID_TYPES};
 pub use storage::Storage;
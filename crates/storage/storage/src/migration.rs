@@ -0,0 +1,80 @@
+use std::fmt;
+
+/// Migration is a single change of the database schema, implemented by every storage backend.
+/// Applied migrations are recorded in the `{prefix}_schema_version` table, so each of them runs once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Migration {
+    /// Create tables of all modules
+    CreateTables,
+    /// Store values and amounts as numbers instead of text and hex strings
+    NumericValues,
+    /// Name token tables by the account of the token instead of the first 8 chars of its address
+    TokenTableNames,
+}
+
+/// Token types which have tables of transfers for each token
+const TOKEN_TYPES: [&str; 3] = ["cbc20", "cbc721", "cbc1155"];
+
+/// Token types which transfers identify the transferred token, their tables have the `token_id` column
+pub const TOKEN_ID_TYPES: [&str; 2] = ["cbc721", "cbc1155"];
+
+/// Returns whether the transfers table has the `token_id` column.
+/// The table is named `{token}_{id}_transfers`, with or without the tables prefix
+pub fn has_token_ids(table: &str) -> bool {
+    TOKEN_ID_TYPES.into_iter().any(|token| {
+        table.starts_with(&format!("{}_", token)) || table.contains(&format!("_{}_", token))
+    })
+}
+
+/// Returns the token type and the old ID of the transfers table named before `Migration::TokenTableNames`,
+/// e.g. `cbc20` and `cb12abcd` of `etl_cbc20_cb12abcd_transfers`
+pub fn legacy_transfers_table<'a>(prefix: &str, table: &'a str) -> Option<(&'static str, &'a str)> {
+    let name = table
+        .strip_prefix(prefix)?
+        .strip_prefix('_')?
+        .strip_suffix("_transfers")?;
+    TOKEN_TYPES.into_iter().find_map(|token| {
+        let id = name.strip_prefix(token)?.strip_prefix('_')?;
+        (id.len() == 8).then_some((token, id))
+    })
+}
+
+impl Migration {
+    /// All migrations in the order they are applied.
+    /// New migrations must be appended to the end, applied migrations must not be changed
+    pub const ALL: [Migration; 3] = [
+        Migration::CreateTables,
+        Migration::NumericValues,
+        Migration::TokenTableNames,
+    ];
+
+    pub fn version(&self) -> i64 {
+        match self {
+            Migration::CreateTables => 1,
+            Migration::NumericValues => 2,
+            Migration::TokenTableNames => 3,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Migration::CreateTables => "create tables",
+            Migration::NumericValues => "store values and amounts as numeric columns",
+            Migration::TokenTableNames => "name token tables by the token account",
+        }
+    }
+
+    /// Returns migrations which are newer than the given schema version, in the order they are applied
+    pub fn pending(version: i64) -> Vec<Migration> {
+        Migration::ALL
+            .into_iter()
+            .filter(|migration| migration.version() > version)
+            .collect()
+    }
+}
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.version(), self.description())
+    }
+}
@@ -1,9 +1,13 @@
+use crate::Migration;
 use async_trait::async_trait;
 use std::collections::{HashMap, HashSet};
 use std::marker::Send;
 use std::{error::Error, pin::Pin};
 use tokio::time::Duration;
-use types::{Block, TokenTransfer, Transaction, TransferType};
+use types::{
+    BalanceChange, Batch, Block, Contract, DecodedTable, IcanAddress, Log, Receipt, TokenBalance,
+    TokenTransfer, Trace, Transaction, TransferType,
+};
 
 #[async_trait]
 pub trait Storage: Send + Sync {
@@ -12,6 +16,33 @@ pub trait Storage: Send + Sync {
     async fn prepare_db(&self) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
     /// Create indexes
     async fn create_indexes(&self) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
+    /// Get the version of the last applied migration, 0 if the schema is not versioned yet
+    async fn schema_version(&self) -> Result<i64, Pin<Box<dyn Error + Send + Sync>>>;
+    /// Apply the migration and record its version in one transaction
+    async fn apply_migration(
+        &self,
+        migration: Migration,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
+    /// Apply migrations newer than the schema version in order.
+    /// Returns the pending migrations, which are only listed if `dry_run` is set
+    async fn run_migrations(
+        &self,
+        dry_run: bool,
+    ) -> Result<Vec<Migration>, Pin<Box<dyn Error + Send + Sync>>> {
+        let pending = Migration::pending(self.schema_version().await?);
+        if !dry_run {
+            for migration in &pending {
+                self.apply_migration(*migration).await?;
+            }
+        }
+        Ok(pending)
+    }
+    /// Switch the bulk load of historical blocks on or off. While it is on, the storage may drop indexes
+    /// and load batches with its fastest method. Switching it off builds indexes again with `create_indexes`.
+    /// Storages without a faster load method keep inserting batches as usual
+    async fn set_bulk_mode(&self, _enabled: bool) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        Ok(())
+    }
     /// Get the latest block number in the database
     async fn get_latest_block_number(&self) -> Result<i64, Pin<Box<dyn Error + Send + Sync>>>;
     /// Update blocks to matured
@@ -25,22 +56,43 @@ pub trait Storage: Send + Sync {
         &self,
         tokens: HashMap<String, HashSet<String>>,
     ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
-    /// Clean block data with all related transactions and token transfers
+    /// Create tables of the contract calls and events decoded with the contract ABI
+    async fn create_decoded_tables(
+        &self,
+        tables: Vec<DecodedTable>,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
+    /// Clean block data with all related transactions and token transfers.
+    /// Blocks after it do not continue the chain anymore, so they are rolled back with it
+    /// by `rollback_blocks`, which archives the orphaned blocks
     async fn clean_block_data(
         &self,
         block_number: i64,
-    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>> {
+        self.rollback_blocks(block_number).await
+    }
+    /// Remove all blocks starting from the given block number with all related transactions
+    /// and token transfers in one transaction. Used to roll back orphaned blocks after a reorg
+    async fn rollback_blocks(&self, from: i64) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
     /// Remove last blocks data
     async fn clean_last_blocks(&self, number: i64)
         -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
-    /// Insert blocks with transactions and token transfers
+    /// Insert blocks with transactions, token transfers, receipts, logs, traces and balance changes.
+    /// The batch is inserted and cleared once `is_batch_ready` returns true
     async fn insert_blocks_with_txs_and_token_transfers(
         &self,
         insert_all: bool,
-        blocks: &mut Vec<Block>,
-        transactions: &mut Vec<Transaction>,
-        token_transfers: &mut HashMap<String, Vec<TokenTransfer>>,
+        batch: &mut Batch,
     ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
+    /// Number of blocks or transactions above which the batch is inserted without `insert_all`
+    fn insert_threshold(&self) -> usize {
+        500
+    }
+    /// Returns whether the batch is big enough to be inserted or `insert_all` is set
+    fn is_batch_ready(&self, batch: &Batch, insert_all: bool) -> bool {
+        insert_all
+            || batch.blocks.len() > self.insert_threshold()
+            || batch.transactions.len() > self.insert_threshold()
+    }
 
     async fn start_cleanup_task(&self, interval: Duration, retention_duration: Duration);
 
@@ -70,9 +122,9 @@ pub trait Storage: Send + Sync {
     ) -> Result<Transaction, Pin<Box<dyn Error + Send + Sync>>>;
     async fn get_token_transfers(
         &self,
-        token_address: String,
-        from: Option<String>,
-        to: Option<String>,
+        token_address: IcanAddress,
+        from: Option<IcanAddress>,
+        to: Option<IcanAddress>,
     ) -> Result<Vec<TokenTransfer>, Pin<Box<dyn Error + Send + Sync>>>;
 
     async fn get_transaction_token_transfers(
@@ -82,7 +134,85 @@ pub trait Storage: Send + Sync {
 
     async fn get_address_token_transfers(
         &self,
-        address: String,
+        address: IcanAddress,
         transfer_type: TransferType,
     ) -> Result<Vec<TokenTransfer>, Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Get balance of the holder of the watched CBC20 token.
+    async fn get_token_balance(
+        &self,
+        token: IcanAddress,
+        holder: IcanAddress,
+    ) -> Result<TokenBalance, Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Get holders of the watched CBC20 token with the biggest balances.
+    async fn get_top_holders(
+        &self,
+        token: IcanAddress,
+        limit: i64,
+    ) -> Result<Vec<TokenBalance>, Pin<Box<dyn Error + Send + Sync>>>;
+
+    async fn get_transaction_receipt(
+        &self,
+        tx_hash: String,
+    ) -> Result<Receipt, Pin<Box<dyn Error + Send + Sync>>>;
+
+    async fn get_transaction_logs(
+        &self,
+        tx_hash: String,
+    ) -> Result<Vec<Log>, Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Get native coin balance changes of the address ordered by block number
+    async fn get_balance_changes(
+        &self,
+        address: IcanAddress,
+    ) -> Result<Vec<BalanceChange>, Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Get native coin balance of the address at the block height as a decimal number in ore.
+    /// Balance is a sum of the stored changes, so it only includes changes since the first synced block
+    async fn get_balance_at(
+        &self,
+        address: IcanAddress,
+        block_number: i64,
+    ) -> Result<String, Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Get internal calls of the transaction in depth-first order
+    async fn get_transaction_traces(
+        &self,
+        tx_hash: String,
+    ) -> Result<Vec<Trace>, Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Get logs emitted by the contract address and/or with the topic0 in the blocks range
+    async fn get_logs(
+        &self,
+        address: Option<IcanAddress>,
+        topic0: Option<String>,
+        from_block: Option<i64>,
+        to_block: Option<i64>,
+    ) -> Result<Vec<Log>, Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Remember the token found by the token discovery, so it is watched after restart
+    async fn add_discovered_token(
+        &self,
+        token: String,
+        address: String,
+        block_number: i64,
+    ) -> Result<(), Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Get the tokens found by the token discovery grouped by the token type
+    async fn get_discovered_tokens(
+        &self,
+    ) -> Result<HashMap<String, HashSet<String>>, Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Get the deployed contract by its address
+    async fn get_contract(
+        &self,
+        address: IcanAddress,
+    ) -> Result<Contract, Pin<Box<dyn Error + Send + Sync>>>;
+
+    /// Get deployed contracts ordered by block number. If `only_cbc20` is set, only likely CBC20 tokens are returned
+    async fn get_contracts(
+        &self,
+        only_cbc20: bool,
+    ) -> Result<Vec<Contract>, Pin<Box<dyn Error + Send + Sync>>>;
 }
@@ -0,0 +1,230 @@
+use base_primitives::Address;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AddressError {
+    #[error("address must have 44 hex chars, got {0}")]
+    InvalidLength(usize),
+    #[error("address is not valid hex")]
+    InvalidHex,
+    #[error("invalid address checksum, expected {0}")]
+    InvalidChecksum(String),
+}
+
+/// Core ICAN address: 2 chars of the network prefix (cb - mainnet, ab - devin, ce - private),
+/// 2 checksum digits and 40 hex chars of the account.
+/// Formatted as 44 lowercase hex chars without the 0x prefix, which is how addresses are stored
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct IcanAddress([u8; 22]);
+
+impl IcanAddress {
+    pub const ZERO: IcanAddress = IcanAddress([0; 22]);
+
+    pub fn from_bytes(bytes: [u8; 22]) -> Self {
+        IcanAddress(bytes)
+    }
+
+    /// Parses the hex address without validating the checksum. Accepts the 0x prefix and upper case chars.
+    /// Used for addresses coming from the chain, e.g. decoded from calldata, which are not checked by the node
+    pub fn from_hex(address: &str) -> Result<Self, AddressError> {
+        let hex = address.trim();
+        let hex = hex
+            .strip_prefix("0x")
+            .or_else(|| hex.strip_prefix("0X"))
+            .unwrap_or(hex);
+        if hex.len() != 44 {
+            return Err(AddressError::InvalidLength(hex.len()));
+        }
+        let mut bytes = [0u8; 22];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = hex
+                .get(i * 2..i * 2 + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or(AddressError::InvalidHex)?;
+        }
+        Ok(IcanAddress(bytes))
+    }
+
+    /// Normalizes the hex address from the chain. Values which are not addresses are only lowercased
+    pub fn normalize(address: &str) -> String {
+        match IcanAddress::from_hex(address) {
+            Ok(address) => address.to_string(),
+            Err(_) => address.trim_start_matches("0x").to_lowercase(),
+        }
+    }
+
+    /// Identifies tables of the contract by the first 16 hex chars of its account, as names with the whole
+    /// address exceed the 63 chars of Postgres identifiers. Accounts are hashes, so their prefixes do not collide
+    pub fn table_id(address: &str) -> String {
+        let address = IcanAddress::normalize(address);
+        address.get(4..20).unwrap_or(&address).to_string()
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 22] {
+        &self.0
+    }
+
+    /// Network prefix, e.g. 0xcb for mainnet
+    pub fn network_prefix(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Checksum digits which the address must have
+    pub fn expected_checksum(&self) -> String {
+        let hex = self.to_string();
+        // ICAN checksum is ISO 7064 mod 97-10 of the account, the network prefix and "00"
+        let rearranged = format!("{}{}00", &hex[4..], &hex[..2]);
+        let remainder = rearranged.chars().fold(0u32, |remainder, c| {
+            let value = c.to_digit(36).unwrap();
+            if value < 10 {
+                (remainder * 10 + value) % 97
+            } else {
+                (remainder * 100 + value) % 97
+            }
+        });
+        format!("{:02}", 98 - remainder)
+    }
+
+    pub fn is_checksum_valid(&self) -> bool {
+        self.to_string()[2..4] == self.expected_checksum()
+    }
+}
+
+/// Addresses returned by the node always have 22 bytes, so they are converted without a fallback
+impl From<Address> for IcanAddress {
+    fn from(address: Address) -> Self {
+        IcanAddress(address.into())
+    }
+}
+
+/// Parses the address entered by the user, validating the checksum.
+/// The zero address is accepted, as it is used for mints and burns
+impl FromStr for IcanAddress {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let address = IcanAddress::from_hex(s)?;
+        if address != IcanAddress::ZERO && !address.is_checksum_valid() {
+            return Err(AddressError::InvalidChecksum(format!(
+                "{:02x}{}",
+                address.network_prefix(),
+                address.expected_checksum()
+            )));
+        }
+        Ok(address)
+    }
+}
+
+impl fmt::Display for IcanAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for IcanAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "IcanAddress({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Core Token on mainnet
+    const CTN: &str = "cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c";
+    /// Account on devin
+    const DEVIN: &str = "ab416902d2548d52352a05423d13266ee7aaf140a068";
+
+    #[test]
+    fn parses_valid_addresses() {
+        for address in [CTN, DEVIN] {
+            let parsed: IcanAddress = address.parse().unwrap();
+            assert_eq!(parsed.to_string(), address);
+            assert!(parsed.is_checksum_valid());
+        }
+        assert_eq!(CTN.parse::<IcanAddress>().unwrap().network_prefix(), 0xcb);
+        assert_eq!(DEVIN.parse::<IcanAddress>().unwrap().network_prefix(), 0xab);
+    }
+
+    #[test]
+    fn parses_prefixed_and_upper_case_addresses() {
+        let address: IcanAddress = format!("0x{}", CTN.to_uppercase()).parse().unwrap();
+        assert_eq!(address.to_string(), CTN);
+        assert_eq!(
+            IcanAddress::from_hex(&format!(" 0X{} ", CTN))
+                .unwrap()
+                .to_string(),
+            CTN
+        );
+    }
+
+    #[test]
+    fn accepts_zero_address() {
+        let address: IcanAddress = "0".repeat(44).parse().unwrap();
+        assert_eq!(address, IcanAddress::ZERO);
+    }
+
+    #[test]
+    fn rejects_invalid_checksum() {
+        // checksum digits of the devin account are changed
+        let address = format!("ab42{}", &DEVIN[4..]);
+        assert_eq!(
+            address.parse::<IcanAddress>(),
+            Err(AddressError::InvalidChecksum("ab41".to_string()))
+        );
+        // account of the mainnet address with the network prefix of devin
+        let address = format!("ab19{}", &CTN[4..]);
+        assert!(matches!(
+            address.parse::<IcanAddress>(),
+            Err(AddressError::InvalidChecksum(_))
+        ));
+        // checksum is not validated for the addresses from the chain
+        assert!(IcanAddress::from_hex(&format!("ab42{}", &DEVIN[4..])).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_length_and_hex() {
+        assert_eq!(
+            CTN[..42].parse::<IcanAddress>(),
+            Err(AddressError::InvalidLength(42))
+        );
+        assert_eq!(
+            format!("{}00", CTN).parse::<IcanAddress>(),
+            Err(AddressError::InvalidLength(46))
+        );
+        assert_eq!(
+            format!("zz{}", &CTN[2..]).parse::<IcanAddress>(),
+            Err(AddressError::InvalidHex)
+        );
+        assert_eq!(
+            format!("é{}", &CTN[2..]).parse::<IcanAddress>(),
+            Err(AddressError::InvalidHex)
+        );
+    }
+
+    #[test]
+    fn normalizes_addresses() {
+        assert_eq!(
+            IcanAddress::normalize(&format!("0x{}", CTN.to_uppercase())),
+            CTN
+        );
+        // values which are not addresses are only lowercased
+        assert_eq!(IcanAddress::normalize("0xABCD"), "abcd");
+        assert_eq!(IcanAddress::normalize(""), "");
+    }
+
+    #[test]
+    fn identifies_tables_by_account() {
+        assert_eq!(IcanAddress::table_id(CTN), "c7acc4c292d2943b");
+        assert_eq!(
+            IcanAddress::table_id(&CTN.to_uppercase()),
+            "c7acc4c292d2943b"
+        );
+        assert_eq!(IcanAddress::table_id("abcd"), "abcd");
+    }
+}
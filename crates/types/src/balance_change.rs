@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// BalanceChange is a change of the native coin balance of the address in the block.
+/// Delta is a signed decimal number in ore
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct BalanceChange {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub address: String,
+    pub delta: String,
+}
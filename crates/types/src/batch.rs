@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use crate::{
+    BalanceChange, Block, Contract, DecodeFailure, DecodedRow, Log, Receipt, TokenTransfer, Trace,
+    Transaction,
+};
+
+/// Data extracted from one or more blocks which is inserted into the storage at once
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    pub blocks: Vec<Block>,
+    pub transactions: Vec<Transaction>,
+    /// Token transfers grouped by the name of the table they are stored in
+    pub token_transfers: HashMap<String, Vec<TokenTransfer>>,
+    pub receipts: Vec<Receipt>,
+    pub logs: Vec<Log>,
+    pub traces: Vec<Trace>,
+    pub balance_changes: Vec<BalanceChange>,
+    pub contracts: Vec<Contract>,
+    /// Calls and events of the contracts with ABI grouped by the name of the table they are stored in
+    pub decoded: HashMap<String, Vec<DecodedRow>>,
+    /// Calls and events of the watched contracts which could not be decoded
+    pub decode_errors: Vec<DecodeFailure>,
+}
+
+impl Batch {
+    /// Moves all data from the other batch into this one
+    pub fn extend(&mut self, other: Batch) {
+        self.blocks.extend(other.blocks);
+        self.transactions.extend(other.transactions);
+        for (key, values) in other.token_transfers {
+            self.token_transfers.entry(key).or_default().extend(values);
+        }
+        self.receipts.extend(other.receipts);
+        self.logs.extend(other.logs);
+        self.traces.extend(other.traces);
+        self.balance_changes.extend(other.balance_changes);
+        self.contracts.extend(other.contracts);
+        for (key, values) in other.decoded {
+            self.decoded.entry(key).or_default().extend(values);
+        }
+        self.decode_errors.extend(other.decode_errors);
+    }
+
+    /// Removes all data from the batch, keeping the token transfers and decoded tables
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.transactions.clear();
+        self.token_transfers.values_mut().for_each(|v| v.clear());
+        self.receipts.clear();
+        self.logs.clear();
+        self.traces.clear();
+        self.balance_changes.clear();
+        self.contracts.clear();
+        self.decoded.values_mut().for_each(|v| v.clear());
+        self.decode_errors.clear();
+    }
+}
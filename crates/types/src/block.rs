@@ -1,3 +1,4 @@
+use crate::IcanAddress;
 use atoms_rpc_types::Block as AtomsBlock;
 use base_primitives::{hex::ToHexExt, U256};
 use serde::{Deserialize, Serialize};
@@ -39,7 +40,7 @@ impl From<AtomsBlock> for Block {
                 .expect("block hash must be set")
                 .encode_hex(), // todo:error2215 do smth fix expects
             logs_bloom: val.header.logs_bloom.encode_hex(),
-            miner: val.header.miner.to_string(),
+            miner: IcanAddress::from(val.header.miner).to_string(),
             nonce: val
                 .header
                 .nonce
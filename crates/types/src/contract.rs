@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Contract is a deployed smart contract.
+/// Name, symbol and decimals are set only for the contracts which look like CBC20 tokens.
+/// Bytecode hash is empty if the code of the contract could not be read from the node
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Contract {
+    pub address: String,
+    pub creator: String,
+    pub tx_hash: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub bytecode_hash: String,
+    pub is_cbc20: i64,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: i64,
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// Table of the decoded calls or events of a contract method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedTable {
+    pub name: String,
+    /// Column name and ABI type of every method argument
+    pub columns: Vec<(String, String)>,
+}
+
+/// Contract function call or event decoded with the contract ABI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedRow {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub address: String,
+    /// Index of the log in the block for events, 0 for calls
+    pub log_index: i64,
+    pub status: i64,
+    /// Column name and value of every method argument
+    pub args: Vec<(String, String)>,
+}
+
+/// Call or event of a watched contract which could not be decoded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodeFailure {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub address: String,
+    /// Index of the log in the block for events, 0 for calls
+    pub log_index: i64,
+    /// Comma separated topics of the event, empty for calls
+    pub topics: String,
+    /// Calldata of the call or data of the event
+    pub data: String,
+    pub error: String,
+}
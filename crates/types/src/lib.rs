@@ -1,3 +1,6 @@
+pub mod address;
+pub use address::{AddressError, IcanAddress};
+
 pub mod block;
 pub use block::Block;
 
@@ -10,5 +13,29 @@ pub use network::Network;
 pub mod token_transfer;
 pub use token_transfer::TokenTransfer;
 
+pub mod token_balance;
+pub use token_balance::TokenBalance;
+
 pub mod transfer_type;
 pub use transfer_type::TransferType;
+
+pub mod receipt;
+pub use receipt::Receipt;
+
+pub mod log;
+pub use log::Log;
+
+pub mod trace;
+pub use trace::{CallFrame, Trace, TraceResult};
+
+pub mod balance_change;
+pub use balance_change::BalanceChange;
+
+pub mod contract;
+pub use contract::Contract;
+
+pub mod decoded;
+pub use decoded::{DecodeFailure, DecodedRow, DecodedTable};
+
+pub mod batch;
+pub use batch::Batch;
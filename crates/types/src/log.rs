@@ -0,0 +1,38 @@
+use crate::IcanAddress;
+use atoms_rpc_types::Log as AtomsLog;
+use base_primitives::{hex::ToHexExt, B256};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Log {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub log_index: i64,
+    pub address: String,
+    pub topic0: Option<String>,
+    pub topic1: Option<String>,
+    pub topic2: Option<String>,
+    pub topic3: Option<String>,
+    pub data: String,
+}
+
+impl From<&AtomsLog> for Log {
+    fn from(val: &AtomsLog) -> Self {
+        let topics = val.topics();
+        let topic = |i: usize| topics.get(i).map(|t| t.encode_hex());
+        Log {
+            block_number: val.block_number.unwrap_or(0) as i64,
+            block_hash: val.block_hash.unwrap_or(B256::ZERO).encode_hex(),
+            tx_hash: val.transaction_hash.unwrap_or(B256::ZERO).encode_hex(),
+            log_index: val.log_index.map(|i| i as i64).unwrap_or(0),
+            address: IcanAddress::from(val.address()).to_string(),
+            topic0: topic(0),
+            topic1: topic(1),
+            topic2: topic(2),
+            topic3: topic(3),
+            data: val.data().data.encode_hex(),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::IcanAddress;
+use atoms_rpc_types::TransactionReceipt as AtomsReceipt;
+use base_primitives::{hex::ToHexExt, B256};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Receipt {
+    pub transaction_hash: String,
+    pub block_hash: String,
+    pub block_number: i64,
+    pub transaction_index: i64,
+    pub status: i64,
+    pub energy_used: i64,
+    pub cumulative_energy_used: i64,
+    pub effective_energy_price: String,
+    pub contract_address: String,
+    pub logs_bloom: String,
+}
+
+impl From<&AtomsReceipt> for Receipt {
+    fn from(val: &AtomsReceipt) -> Self {
+        Receipt {
+            block_hash: val.block_hash.unwrap_or(B256::ZERO).encode_hex(),
+            block_number: val.block_number.unwrap_or(0) as i64,
+            contract_address: {
+                val.contract_address
+                    .map(|a| IcanAddress::from(a).to_string())
+                    .unwrap_or("".to_string())
+            },
+            cumulative_energy_used: val.inner.cumulative_energy_used() as i64,
+            effective_energy_price: val.effective_energy_price.to_string(),
+            energy_used: val.energy_used as i64,
+            logs_bloom: val.inner.logs_bloom().encode_hex(),
+            status: if val.status() { 1 } else { 0 },
+            transaction_hash: val.transaction_hash.encode_hex(),
+            transaction_index: { val.transaction_index.map(|t| t as i64).unwrap_or(0) },
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use base_primitives::{ruint::ParseError, U256};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+
+use crate::TokenTransfer;
+
+/// TokenBalance is a balance of the token holder.
+/// Balance is a decimal string, the same as transfer values, stored as a numeric column by the storage
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct TokenBalance {
+    pub holder: String,
+    pub balance: String,
+}
+
+impl TokenBalance {
+    /// Returns the balance after receiving `received` and sending `sent` tokens.
+    /// Balance can not go below zero, e.g. if transfers were synced not from the token deployment
+    pub fn apply(balance: Option<&str>, received: U256, sent: U256) -> Result<String, ParseError> {
+        let balance = match balance {
+            Some(balance) => U256::from_str_radix(balance, 10)?,
+            None => U256::ZERO,
+        };
+        let balance = balance.saturating_add(received).saturating_sub(sent);
+        Ok(balance.to_string())
+    }
+
+    /// Sums values received and sent by every holder in successful transfers.
+    /// Zero address is skipped as tokens are minted from it and burned to it
+    pub fn changes(
+        transfers: &[TokenTransfer],
+    ) -> Result<HashMap<String, (U256, U256)>, ParseError> {
+        let mut changes: HashMap<String, (U256, U256)> = HashMap::new();
+        for transfer in transfers.iter().filter(|transfer| transfer.status == 1) {
+            let value = U256::from_str_radix(&transfer.value, 10)?;
+            if !is_zero_address(&transfer.to) {
+                let (received, _) = changes.entry(transfer.to.clone()).or_default();
+                *received = received.saturating_add(value);
+            }
+            if !is_zero_address(&transfer.from) {
+                let (_, sent) = changes.entry(transfer.from.clone()).or_default();
+                *sent = sent.saturating_add(value);
+            }
+        }
+        Ok(changes)
+    }
+}
+
+fn is_zero_address(address: &str) -> bool {
+    address.chars().all(|c| c == '0')
+}
@@ -14,4 +14,11 @@ pub struct TokenTransfer {
     #[sqlx(rename = "transfer_index")]
     pub index: i64,
     pub status: i64,
+    /// ID of the transferred token for NFT and multi-token contracts. Empty for the others
+    #[sqlx(default)]
+    pub token_id: String,
+    /// Position of the (id, amount) pair in the batch transfer, 0 for single transfers.
+    /// Pairs of one batch share the transfer index, so they are told apart by it
+    #[sqlx(default)]
+    pub batch_index: i64,
 }
@@ -0,0 +1,208 @@
+use crate::IcanAddress;
+use base_primitives::U256;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::str::FromStr;
+
+/// Call frame returned by the callTracer of the node
+#[derive(Debug, Clone, Deserialize)]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: String,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub calls: Vec<CallFrame>,
+}
+
+/// Trace of a single transaction returned by debug_traceBlockByNumber
+#[derive(Debug, Clone, Deserialize)]
+pub struct TraceResult {
+    #[serde(default, rename = "txHash")]
+    pub tx_hash: Option<String>,
+    pub result: CallFrame,
+}
+
+/// Trace is a single call of the transaction call tree.
+/// Root call has an empty trace address, its subcalls have "0", "1" and their subcalls "0,0", "0,1" etc.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Trace {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub trace_address: String,
+    pub depth: i64,
+    pub call_type: String,
+    #[sqlx(rename = "from_addr")]
+    pub from: String,
+    #[sqlx(rename = "to_addr")]
+    pub to: String,
+    pub value: String,
+    pub error: String,
+}
+
+impl Trace {
+    /// Flattens the call tree into the list of traces in depth-first order
+    pub fn from_call_frame(
+        block_number: i64,
+        block_hash: &str,
+        tx_hash: &str,
+        frame: &CallFrame,
+    ) -> Vec<Trace> {
+        let mut traces = vec![];
+        Self::flatten(
+            block_number,
+            block_hash,
+            tx_hash,
+            frame,
+            vec![],
+            &mut traces,
+        );
+        traces
+    }
+
+    /// Returns the trace address as the list of subcall indexes.
+    /// Sorting traces by it gives the depth-first order of calls
+    pub fn call_path(&self) -> Vec<usize> {
+        self.trace_address
+            .split(',')
+            .filter(|i| !i.is_empty())
+            .map(|i| i.parse().unwrap_or_default())
+            .collect()
+    }
+
+    fn flatten(
+        block_number: i64,
+        block_hash: &str,
+        tx_hash: &str,
+        frame: &CallFrame,
+        trace_address: Vec<usize>,
+        traces: &mut Vec<Trace>,
+    ) {
+        traces.push(Trace {
+            block_number,
+            block_hash: block_hash.to_string(),
+            tx_hash: tx_hash.to_string(),
+            trace_address: trace_address
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            depth: trace_address.len() as i64,
+            call_type: frame.call_type.to_lowercase(),
+            from: IcanAddress::normalize(&frame.from),
+            to: frame
+                .to
+                .as_deref()
+                .map(IcanAddress::normalize)
+                .unwrap_or("".to_string()),
+            value: frame
+                .value
+                .as_deref()
+                .and_then(|v| U256::from_str(v).ok())
+                .unwrap_or(U256::ZERO)
+                .to_string(),
+            error: frame.error.clone().unwrap_or("".to_string()),
+        });
+        for (i, call) in frame.calls.iter().enumerate() {
+            let mut call_address = trace_address.clone();
+            call_address.push(i);
+            Self::flatten(
+                block_number,
+                block_hash,
+                tx_hash,
+                call,
+                call_address,
+                traces,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_HASH: &str = "4d1f0a7e3b2c9d8e";
+
+    /// Traces of a block with a transfer making nested calls and a contract creation
+    fn fixture() -> Vec<TraceResult> {
+        serde_json::from_str(include_str!("../fixtures/debug_trace_block.json")).unwrap()
+    }
+
+    fn flatten(result: &TraceResult) -> Vec<Trace> {
+        let tx_hash = result.tx_hash.as_deref().unwrap_or_default();
+        Trace::from_call_frame(7, BLOCK_HASH, tx_hash, &result.result)
+    }
+
+    #[test]
+    fn flattens_call_tree_depth_first() {
+        let traces = flatten(&fixture()[0]);
+        assert_eq!(
+            traces
+                .iter()
+                .map(|trace| (
+                    trace.trace_address.as_str(),
+                    trace.depth,
+                    trace.call_type.as_str()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("", 0, "call"),
+                ("0", 1, "call"),
+                ("1", 1, "delegatecall"),
+                ("1,0", 2, "call"),
+            ]
+        );
+        assert!(traces.iter().all(|trace| trace.block_number == 7
+            && trace.block_hash == BLOCK_HASH
+            && trace.tx_hash
+                == "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"));
+        assert_eq!(
+            traces.iter().map(Trace::call_path).collect::<Vec<_>>(),
+            vec![vec![], vec![0], vec![1], vec![1, 0]]
+        );
+    }
+
+    #[test]
+    fn normalizes_addresses_and_values() {
+        let traces = flatten(&fixture()[0]);
+        assert_eq!(
+            traces[0].from,
+            "ab416902d2548d52352a05423d13266ee7aaf140a068"
+        );
+        assert_eq!(traces[0].value, "10000000000000000");
+        assert_eq!(traces[1].to, "cb19c7acc4c292d2943ba23c2eaa5d9c5a6652a8710c");
+        assert_eq!(traces[1].value, "1000000000000000000");
+        // calls without value do not move coins
+        assert_eq!(traces[2].value, "0");
+        assert_eq!(traces[3].value, "100");
+    }
+
+    #[test]
+    fn keeps_errors_of_failed_calls() {
+        let traces = flatten(&fixture()[0]);
+        assert_eq!(
+            traces
+                .iter()
+                .map(|trace| trace.error.as_str())
+                .collect::<Vec<_>>(),
+            vec!["", "", "execution reverted", ""]
+        );
+    }
+
+    #[test]
+    fn flattens_creation_without_calls() {
+        let traces = flatten(&fixture()[1]);
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].call_type, "create");
+        assert_eq!(traces[0].trace_address, "");
+        assert_eq!(traces[0].to, "cb57a5f2c1e7d1f9b1f7bff1c5a6a3b8d9e2f4a6b8c0");
+        assert_eq!(traces[0].value, "0");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::IcanAddress;
 use atoms_rpc_types::Transaction as AtomsTrasaction;
 use base_primitives::{hex::ToHexExt, B256};
 use serde::{Deserialize, Serialize};
@@ -27,11 +28,15 @@ impl From<&AtomsTrasaction> for Transaction {
             block_number: val.block_number.unwrap_or(0) as i64,
             energy: val.energy.to_string(),
             energy_price: val.energy_price.unwrap_or(0).to_string(),
-            from: val.from.to_string(),
+            from: IcanAddress::from(val.from).to_string(),
             hash: val.hash.encode_hex(),
             input: val.input.encode_hex(),
             nonce: val.nonce.to_string(),
-            to: { val.to.map(|t| t.to_string()).unwrap_or("".to_string()) },
+            to: {
+                val.to
+                    .map(|t| IcanAddress::from(t).to_string())
+                    .unwrap_or("".to_string())
+            },
             transaction_index: { val.transaction_index.map(|t| t as i64).unwrap_or(0) },
             value: val.value.to_string(),
         }